@@ -0,0 +1,267 @@
+use clap::ValueEnum;
+use hitster_core::{AudioSource, Hit, HitId, HitsterData, Pack, parse_year};
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    path::{Path, PathBuf},
+};
+use symphonia::core::{
+    formats::FormatOptions,
+    io::MediaSourceStream,
+    meta::{MetadataOptions, MetadataRevision, StandardTagKey},
+    probe::Hint,
+};
+use time::OffsetDateTime;
+use uuid::Uuid;
+use walkdir::WalkDir;
+
+const EXTENSIONS: [&str; 3] = ["flac", "mp3", "ogg"];
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum GroupBy {
+    /// one pack per folder
+    Folder,
+    /// one pack per album tag, files without one fall back to their folder
+    Album,
+}
+
+#[derive(Default)]
+struct Tags {
+    artist: Option<String>,
+    title: Option<String>,
+    album: Option<String>,
+    year: Option<u32>,
+    original_year: Option<u32>,
+}
+
+impl Tags {
+    fn read(&mut self, revision: &MetadataRevision) {
+        for tag in revision.tags().iter() {
+            let value = tag.value.to_string().trim().to_string();
+
+            if value.is_empty() {
+                continue;
+            }
+
+            match tag.std_key {
+                Some(StandardTagKey::Artist) => self.artist = Some(value),
+                Some(StandardTagKey::TrackTitle) => self.title = Some(value),
+                Some(StandardTagKey::Album) => self.album = Some(value),
+                Some(StandardTagKey::Date) | Some(StandardTagKey::ReleaseDate) => {
+                    self.year = self.year.or(parse_year(&value))
+                }
+                Some(StandardTagKey::OriginalDate) => {
+                    self.original_year = self.original_year.or(parse_year(&value))
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// dates are tagged as 1985, 1985-03-01 or similar, we only care about the year
+fn read_tags(file: &Path) -> Option<Tags> {
+    let mut hint = Hint::new();
+
+    if let Some(ext) = file.extension().and_then(|e| e.to_str()) {
+        hint.with_extension(ext);
+    }
+
+    let mss = MediaSourceStream::new(Box::new(File::open(file).ok()?), Default::default());
+    let mut probed = symphonia::default::get_probe()
+        .format(
+            &hint,
+            mss,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )
+        .ok()?;
+
+    let mut tags = Tags::default();
+
+    // tags in front of the container (e.g. id3v2) first, the container ones take precedence
+    if let Some(revision) = probed.metadata.get().as_ref().and_then(|m| m.current()) {
+        tags.read(revision);
+    }
+
+    if let Some(revision) = probed.format.metadata().current() {
+        tags.read(revision);
+    }
+
+    Some(tags)
+}
+
+/// scan a directory for tagged audio files and write them into a hits.yml file
+pub fn import(directory: PathBuf, output: PathBuf, group_by: GroupBy) -> bool {
+    let mut hits = HashMap::<String, Hit>::new();
+    let mut packs = HashMap::<String, Pack>::new();
+    let mut unreadable = vec![];
+    let mut missing_tags = vec![];
+    let mut missing_year = vec![];
+
+    // hits and packs which were imported before keep their ids
+    let hits_ref = match fs::read_to_string(&output) {
+        Ok(s) => match serde_yml::from_str::<HitsterData>(&s) {
+            Ok(data) => data,
+            Err(e) => {
+                println!("unable to parse {}: {}", output.display(), e);
+                return false;
+            }
+        },
+        Err(_) => HitsterData::new(vec![], vec![]),
+    };
+
+    let root_name = directory
+        .canonicalize()
+        .ok()
+        .and_then(|d| d.file_name().map(|n| n.to_string_lossy().to_string()))
+        .unwrap_or_else(|| "Library".into());
+
+    let mut files = WalkDir::new(&directory)
+        .follow_links(true)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .map(|e| e.into_path())
+        .filter(|p| {
+            p.extension()
+                .and_then(|e| e.to_str())
+                .is_some_and(|e| EXTENSIONS.contains(&e.to_lowercase().as_str()))
+        })
+        .collect::<Vec<_>>();
+
+    files.sort();
+
+    if files.is_empty() {
+        println!("no audio files found in {}", directory.display());
+        return false;
+    }
+
+    for file in files.into_iter() {
+        let relative = file.strip_prefix(&directory).unwrap().to_path_buf();
+
+        let Some(tags) = read_tags(&file) else {
+            unreadable.push(relative);
+            continue;
+        };
+
+        let (Some(artist), Some(title)) = (tags.artist, tags.title) else {
+            missing_tags.push(relative);
+            continue;
+        };
+
+        let Some(year) = tags.original_year.or(tags.year) else {
+            missing_year.push(relative);
+            continue;
+        };
+
+        let folder = relative
+            .parent()
+            .filter(|p| !p.as_os_str().is_empty())
+            .map(|p| {
+                p.components()
+                    .map(|c| c.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join(" - ")
+            })
+            .unwrap_or_else(|| root_name.clone());
+
+        let pack = match group_by {
+            GroupBy::Folder => folder,
+            GroupBy::Album => tags.album.unwrap_or(folder),
+        };
+
+        if !packs.contains_key(&pack) {
+            let pack_ref = hits_ref.get_packs().into_iter().find(|p| p.name == pack);
+
+            packs.insert(
+                pack.clone(),
+                Pack {
+                    id: pack_ref.map(|p| p.id).unwrap_or_else(Uuid::new_v4),
+                    name: pack.clone(),
+                    last_modified: pack_ref
+                        .map(|p| p.last_modified)
+                        .unwrap_or_else(OffsetDateTime::now_utc),
+                },
+            );
+        }
+
+        // the file source expects paths relative to the library directory
+        let path = relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        let hit_ref = hits_ref.get_hit(&HitId::Location(path.clone()));
+        let pack_id = packs.get(&pack).unwrap().id;
+
+        let mut hit = Hit {
+            artist,
+            title,
+            year,
+            playback_offset: hit_ref.map(|h| h.playback_offset).unwrap_or(0),
+            playback_length: hit_ref.and_then(|h| h.playback_length),
+            snippets: hit_ref.map(|h| h.snippets.clone()).unwrap_or_default(),
+            packs: vec![pack_id],
+            belongs_to: hit_ref.map(|h| h.belongs_to.clone()).unwrap_or_default(),
+            id: hit_ref.map(|h| h.id).unwrap_or_else(Uuid::new_v4),
+            yt_id: String::new(),
+            source: AudioSource::File,
+            location: path.clone(),
+            downloaded: false,
+            last_modified: OffsetDateTime::now_utc(),
+        };
+
+        if let Some(h) = hit_ref
+            && h.title == hit.title
+            && h.artist == hit.artist
+            && h.year == hit.year
+            && h.packs == hit.packs
+        {
+            hit.last_modified = h.last_modified;
+        }
+
+        hits.insert(path, hit);
+    }
+
+    let imported = hits.len();
+
+    let data = HitsterData::new(
+        hits.into_values().collect::<Vec<_>>(),
+        packs.into_values().collect::<Vec<_>>(),
+    );
+
+    if let Err(e) = fs::write(&output, serde_yml::to_string(&data).unwrap()) {
+        println!("unable to write {}: {}", output.display(), e);
+        return false;
+    }
+
+    println!(
+        "imported {} hits in {} packs into {}",
+        imported,
+        data.get_packs().len(),
+        output.display()
+    );
+    println!(
+        "set LIBRARY_DIRECTORY to {} for the server to find the audio files",
+        directory.display()
+    );
+
+    for (message, files) in [
+        ("unable to read", &unreadable),
+        ("missing artist or title", &missing_tags),
+        ("missing a year", &missing_year),
+    ] {
+        if files.is_empty() {
+            continue;
+        }
+
+        println!("{} files skipped, {}:", files.len(), message);
+
+        for file in files.iter() {
+            println!("\t{}", file.display());
+        }
+    }
+
+    unreadable.is_empty() && missing_tags.is_empty() && missing_year.is_empty()
+}
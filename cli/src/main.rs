@@ -1,5 +1,7 @@
+mod import;
 mod migrate;
 mod users;
+mod verify;
 
 use anyhow::Result;
 use bitflags::Flags;
@@ -27,6 +29,31 @@ enum Commands {
     },
     /// manage users
     Users(UsersArgs),
+    /// import hits from a folder of tagged audio files (mp3, flac, ogg) into a yaml file
+    Import {
+        /// the folder to scan, usually the library directory of the server
+        directory: PathBuf,
+        /// the yaml file to write, ids of hits and packs already within it are kept
+        #[arg(short, long, default_value = "hits.yml")]
+        output: PathBuf,
+        /// how to group the hits into packs
+        #[arg(short, long, value_enum, default_value_t = import::GroupBy::Folder)]
+        group_by: import::GroupBy,
+    },
+    /// compare the years of hits against a reference dataset, e.g. exported from MusicBrainz
+    VerifyYears {
+        /// a tab separated file with artist, title and release date of one song per line
+        reference: PathBuf,
+        /// the yaml file containing the hits to verify
+        #[arg(short = 'i', long, default_value = "hits.yml")]
+        hits: PathBuf,
+        /// how many years a hit may differ from the reference
+        #[arg(short, long, default_value_t = 0)]
+        tolerance: u32,
+        /// write the years suggested by the reference into the yaml file
+        #[arg(short, long, action = clap::ArgAction::SetTrue)]
+        fix: bool,
+    },
 }
 
 #[derive(Args)]
@@ -109,6 +136,27 @@ async fn main() -> Result<ExitCode, Box<dyn Error>> {
                 return Ok(ExitCode::from(1));
             }
         }
+        Commands::Import {
+            directory,
+            output,
+            group_by,
+        } => {
+            let success = import::import(directory.clone(), output.clone(), *group_by);
+            if !success {
+                return Ok(ExitCode::from(1));
+            }
+        }
+        Commands::VerifyYears {
+            reference,
+            hits,
+            tolerance,
+            fix,
+        } => {
+            let success = verify::verify_years(hits.clone(), reference.clone(), *tolerance, *fix);
+            if !success {
+                return Ok(ExitCode::from(1));
+            }
+        }
         Commands::Users(args) => {
             let db = env::var("DATABASE_URL").expect("DATABASEURL environment variable not found");
             match &args.command {
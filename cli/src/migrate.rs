@@ -1,4 +1,4 @@
-use hitster_core::{Hit, HitId, HitsterData, Pack};
+use hitster_core::{AudioSource, Hit, HitId, HitsterData, Pack};
 use regex_lite::Regex;
 use std::{collections::HashMap, fs, path::PathBuf};
 use terminal_menu::{button, label, list, menu, mut_menu, run};
@@ -79,10 +79,17 @@ pub fn migrate(file: PathBuf) -> bool {
                         title,
                         year,
                         playback_offset,
+                        playback_length: hit_ref.as_ref().and_then(|h| h.playback_length),
+                        snippets: hit_ref
+                            .as_ref()
+                            .map(|h| h.snippets.clone())
+                            .unwrap_or_default(),
                         packs: vec![packs.get(&pack).unwrap().id],
                         belongs_to,
                         id: hit_ref.as_ref().map(|h| h.id).unwrap_or_else(Uuid::new_v4),
                         yt_id: my_yt_id,
+                        source: AudioSource::YouTube,
+                        location: String::new(),
                         downloaded: false,
                         last_modified: hit_ref
                             .as_ref()
@@ -0,0 +1,81 @@
+use hitster_core::{Hit, HitsterData, Pack};
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::BufReader,
+    path::PathBuf,
+};
+use time::OffsetDateTime;
+
+/// compare the years of the hits within a hits.yml file against a reference dataset
+/// and optionally correct them
+pub fn verify_years(hits: PathBuf, reference: PathBuf, tolerance: u32, fix: bool) -> bool {
+    let data = match fs::read_to_string(&hits)
+        .map_err(|e| e.to_string())
+        .and_then(|s| serde_yml::from_str::<HitsterData>(&s).map_err(|e| e.to_string()))
+    {
+        Ok(data) => data,
+        Err(e) => {
+            println!("unable to read {}: {}", hits.display(), e);
+            return false;
+        }
+    };
+
+    let mismatches = match File::open(&reference)
+        .and_then(|f| data.verify_years(BufReader::new(f), tolerance))
+    {
+        Ok(mismatches) => mismatches,
+        Err(e) => {
+            println!("unable to read {}: {}", reference.display(), e);
+            return false;
+        }
+    };
+
+    if mismatches.is_empty() {
+        println!("the years of all hits match the reference");
+        return true;
+    }
+
+    println!("{} hits differ from the reference:", mismatches.len());
+
+    for (hit, year) in mismatches.iter() {
+        println!(
+            "\t{} - {}: {} (reference suggests {})",
+            hit.artist, hit.title, hit.year, year
+        );
+    }
+
+    if !fix {
+        return false;
+    }
+
+    let years = mismatches
+        .into_iter()
+        .map(|(hit, year)| (hit.id, year))
+        .collect::<HashMap<_, _>>();
+    let now = OffsetDateTime::now_utc();
+
+    let fixed = HitsterData::new(
+        data.get_hits()
+            .into_iter()
+            .cloned()
+            .map(|mut hit| {
+                if let Some(year) = years.get(&hit.id) {
+                    hit.year = *year;
+                    hit.last_modified = now;
+                }
+                hit
+            })
+            .collect::<Vec<Hit>>(),
+        data.get_packs().into_iter().cloned().collect::<Vec<Pack>>(),
+    );
+
+    if let Err(e) = fs::write(&hits, serde_yml::to_string(&fixed).unwrap()) {
+        println!("unable to write {}: {}", hits.display(), e);
+        return false;
+    }
+
+    println!("corrected {} hits in {}", years.len(), hits.display());
+
+    true
+}
@@ -139,6 +139,14 @@ export const GamesResponse = z.object({
 
 export type GamesResponse = z.infer<typeof GamesResponse>
 
+export const AudioToken = z.object({
+    token: z.string(),
+    url: z.string(),
+    expires_at: z.string(),
+})
+
+export type AudioToken = z.infer<typeof AudioToken>
+
 export const GameSettings = z.object({
     start_tokens: z.optional(z.number()),
     hit_duration: z.optional(z.number()),
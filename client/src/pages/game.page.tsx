@@ -654,6 +654,7 @@ export default function Game() {
             )}
             <HitPlayer
                 src={hitSrc}
+                resolveSrc={() => gameService.createAudioToken(game.id)}
                 duration={
                     game.state === GameState.Confirming ? 0 : game.hit_duration
                 }
@@ -27,6 +27,8 @@ interface HitPlayerTimers {
 
 export type HitPlayerProps = {
     src: string
+    // called before every playback to get the URL to play instead of src, e.g. with a single-use token
+    resolveSrc?: () => Promise<string>
     duration: number
     onPlay?: () => void
     autoplay?: boolean
@@ -40,10 +42,18 @@ export type HitPlayerRef = {
 
 export const HitPlayer = forwardRef<HitPlayerRef, HitPlayerProps>(
     function HitPlayer(
-        { src, duration, onPlay, autoplay, shortcut }: HitPlayerProps,
+        {
+            src,
+            resolveSrc,
+            duration,
+            onPlay,
+            autoplay,
+            shortcut,
+        }: HitPlayerProps,
         ref,
     ) {
         const player = useRef<Howl | null>(null)
+        const playbackCounter = useRef(0)
         const [playing, setPlaying] = useState(false)
         const timers = useRef<HitPlayerTimers>({
             sfxTimer: null,
@@ -54,13 +64,25 @@ export const HitPlayer = forwardRef<HitPlayerRef, HitPlayerProps>(
         const [sfxVolume] = useLocalStorage("sfxVolume", "1.0")
         const modalShown = useModalShown()
 
-        const play = useEffectEvent(() => {
+        const play = useEffectEvent(async () => {
             if (timers.current.stopTimer) {
                 clearTimeout(timers.current.stopTimer)
             }
             player.current?.stop()
+            const playbackId = ++playbackCounter.current
+            let url = src
+            if (resolveSrc !== undefined) {
+                try {
+                    url = await resolveSrc()
+                } catch {
+                    setPlaying(false)
+                    return
+                }
+            }
+            // the playback got stopped or restarted while waiting for the url
+            if (playbackId !== playbackCounter.current) return
             const plr = new Howl({
-                src: [src],
+                src: [url],
                 format: "audio/mpeg",
                 html5: true,
                 volume: parseFloat(volume),
@@ -122,6 +144,7 @@ export const HitPlayer = forwardRef<HitPlayerRef, HitPlayerProps>(
                     clearTimeout(timers.current.sfxTimer)
                     timers.current.sfxTimer = null
                 }
+                playbackCounter.current++
                 player.current?.pause()
                 if (src !== "" && player.current !== null) {
                     EventManager.publish(Events.playSfx, { sfx: Sfx.stopHit })
@@ -1,5 +1,5 @@
 import type { GameMode, GameSettings } from "../entities"
-import { Game, GamesResponse } from "../entities"
+import { AudioToken, Game, GamesResponse } from "../entities"
 import fetchAuth from "../fetch"
 
 export default class GameService {
@@ -74,6 +74,16 @@ export default class GameService {
         throw { message: (await res.json()).message, status: res.status }
     }
 
+    async createAudioToken(game_id: string): Promise<string> {
+        const res = await fetchAuth(`/api/games/${game_id}/audio`, {
+            method: "POST",
+            credentials: "include",
+        })
+
+        if (res.status == 200) return AudioToken.parse(await res.json()).url
+        throw { message: (await res.json()).message, status: res.status }
+    }
+
     async guess(game_id: string, slot_id: number | null, player_id?: string) {
         const res = await fetchAuth(
             `/api/games/${game_id}/guess/${player_id ?? ""}`,
@@ -12,8 +12,10 @@ mod hitster_core {
         convert::From,
         env,
         hash::{Hash, Hasher},
-        path::{Path, PathBuf},
+        io::{self, BufRead},
+        path::{Component, Path, PathBuf},
     };
+    use strsim::normalized_levenshtein;
     use time::OffsetDateTime;
     use unicode_normalization::UnicodeNormalization;
     use uuid::Uuid;
@@ -24,6 +26,87 @@ mod hitster_core {
         deunicoded.to_lowercase()
     }
 
+    /// reduces a title or artist to the parts people would actually say out loud,
+    /// e.g. "The Beatles" and "Let It Be (Remastered 2009)" become "beatles" and "let it be"
+    fn normalize_name(s: &str) -> String {
+        let mut depth = 0_usize;
+        let stripped = normalize_text(s)
+            .chars()
+            .filter_map(|c| match c {
+                '(' | '[' => {
+                    depth += 1;
+                    None
+                }
+                // unmatched closing brackets are ignored
+                ')' | ']' => {
+                    depth = depth.saturating_sub(1);
+                    None
+                }
+                _ if depth > 0 => None,
+                c if c.is_alphanumeric() => Some(c),
+                _ => Some(' '),
+            })
+            .collect::<String>();
+        let words = stripped.split_whitespace().collect::<Vec<_>>();
+
+        match words.split_first() {
+            Some((&"the", rest)) if !rest.is_empty() => rest.join(" "),
+            _ => words.join(" "),
+        }
+    }
+
+    /// artist and title of a hit as they get compared when looking for duplicates
+    fn duplicate_key(hit: &Hit) -> (String, String) {
+        let key = |s: &str| {
+            normalize_text(s)
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+
+        (key(&hit.artist), key(&hit.title))
+    }
+
+    /// splits the artist of a hit into the individual artists, e.g. for "Queen & David Bowie"
+    fn split_artists(artist: &str) -> Vec<&str> {
+        artist
+            .split([',', '&', '/'])
+            .flat_map(|a| a.split(" feat. "))
+            .flat_map(|a| a.split(" ft. "))
+            .flat_map(|a| a.split(" and "))
+            .collect()
+    }
+
+    /// keys under which a hit can be found within a year reference,
+    /// the full artist as well as each of multiple artists
+    fn year_reference_keys(hit: &Hit) -> Vec<(String, String)> {
+        let normalized = normalize_text(&hit.artist);
+        let title = normalize_name(&hit.title);
+        let mut keys = vec![(normalize_name(&normalized), title.clone())];
+
+        for artist in split_artists(&normalized).into_iter() {
+            let key = (normalize_name(artist), title.clone());
+
+            if !key.0.is_empty() && !keys.contains(&key) {
+                keys.push(key);
+            }
+        }
+
+        keys
+    }
+
+    /// dates are written as 1985, 1985-03-01 or similar, we only care about the year
+    pub fn parse_year(date: &str) -> Option<u32> {
+        date.trim()
+            .get(..4)
+            .and_then(|y| y.parse::<u32>().ok())
+            .filter(|y| *y > 0)
+    }
+
+    fn compare_names(a: &str, b: &str) -> f64 {
+        normalized_levenshtein(&normalize_name(a), &normalize_name(b))
+    }
+
     #[derive(Clone, Eq, Debug, Serialize, Deserialize)]
     pub struct Hit {
         pub artist: String,
@@ -32,8 +115,21 @@ mod hitster_core {
         pub year: u32,
         pub packs: Vec<Uuid>,
         pub playback_offset: u16,
+        /// how many seconds of the hit get played, until the end of the audio if not set
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub playback_length: Option<u16>,
+        /// alternative playback offsets, games rotate between them and the playback_offset
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        pub snippets: Vec<u16>,
         pub id: Uuid,
+        /// the id of the YouTube video, empty for other sources
+        #[serde(default)]
         pub yt_id: String,
+        #[serde(default, skip_serializing_if = "AudioSource::is_youtube")]
+        pub source: AudioSource,
+        /// the file path or URL of the audio, empty for YouTube hits
+        #[serde(default, skip_serializing_if = "String::is_empty")]
+        pub location: String,
         #[serde(with = "time::serde::rfc3339")]
         #[serde(default = "OffsetDateTime::now_utc")]
         pub last_modified: OffsetDateTime,
@@ -41,6 +137,49 @@ mod hitster_core {
         pub downloaded: bool,
     }
 
+    /// where the audio of a hit gets fetched from
+    #[derive(
+        Clone, Copy, Eq, PartialEq, Hash, Debug, Default, Serialize, Deserialize, JsonSchema,
+    )]
+    #[serde(rename_all = "snake_case")]
+    pub enum AudioSource {
+        /// the yt_id is the id of a YouTube video
+        #[default]
+        #[serde(rename = "youtube")]
+        YouTube,
+        /// the location is the path of an audio file, relative to the library directory of the server
+        File,
+        /// the location is an HTTP(S) URL which points to an audio file
+        Url,
+    }
+
+    impl AudioSource {
+        pub fn is_youtube(&self) -> bool {
+            *self == AudioSource::YouTube
+        }
+    }
+
+    impl From<String> for AudioSource {
+        fn from(value: String) -> Self {
+            match value.as_str() {
+                "youtube" => AudioSource::YouTube,
+                "file" => AudioSource::File,
+                "url" => AudioSource::Url,
+                _ => panic!("invalid audio source: {value}"),
+            }
+        }
+    }
+
+    impl From<AudioSource> for &'static str {
+        fn from(value: AudioSource) -> Self {
+            match value {
+                AudioSource::YouTube => "youtube",
+                AudioSource::File => "file",
+                AudioSource::Url => "url",
+            }
+        }
+    }
+
     #[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize, JsonSchema)]
     #[serde(rename_all = "snake_case")]
     pub enum HitIssueType {
@@ -58,12 +197,49 @@ mod hitster_core {
         }
     }
 
+    /// the steps an issue moves through until it got taken care of
+    #[derive(Copy, Clone, Eq, PartialEq, Debug, Serialize, Deserialize, JsonSchema)]
+    #[serde(rename_all = "snake_case")]
+    pub enum HitIssueState {
+        Open,
+        InProgress,
+        Resolved,
+        WontFix,
+    }
+
+    impl From<String> for HitIssueState {
+        fn from(value: String) -> Self {
+            match value.as_str() {
+                "open" => HitIssueState::Open,
+                "in_progress" => HitIssueState::InProgress,
+                "resolved" => HitIssueState::Resolved,
+                "wont_fix" => HitIssueState::WontFix,
+                _ => panic!("invalid hit issue state: {value}"),
+            }
+        }
+    }
+
+    impl From<HitIssueState> for &'static str {
+        fn from(value: HitIssueState) -> Self {
+            match value {
+                HitIssueState::Open => "open",
+                HitIssueState::InProgress => "in_progress",
+                HitIssueState::Resolved => "resolved",
+                HitIssueState::WontFix => "wont_fix",
+            }
+        }
+    }
+
     #[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize, JsonSchema)]
     pub struct HitIssue {
         pub id: Uuid,
         pub hit_id: Uuid,
         pub r#type: HitIssueType,
         pub message: String,
+        pub state: HitIssueState,
+        /// the user who takes care of the issue
+        pub assignee_id: Option<Uuid>,
+        pub assignee_name: Option<String>,
         #[serde(with = "time::serde::rfc3339")]
         #[schemars(with = "String")]
         pub created_at: OffsetDateTime,
@@ -77,28 +253,103 @@ mod hitster_core {
             env::var("DOWNLOAD_DIRECTORY").unwrap_or("./hits".to_string())
         }
 
-        pub fn file(&self) -> PathBuf {
-            Path::new(&Hit::download_dir()).join(format!(
-                "{}_{}.mp3",
-                self.yt_id.as_str(),
-                self.playback_offset
-            ))
+        pub fn library_dir() -> String {
+            env::var("LIBRARY_DIRECTORY").unwrap_or("./library".to_string())
         }
 
-        pub fn exists(&self) -> bool {
-            self.file().is_file()
+        /// the file of the snippet starting at the playback offset
+        pub fn file(&self, extension: &str) -> PathBuf {
+            self.snippet_file(self.playback_offset, extension)
+        }
+
+        /// the file of the snippet starting at the given offset,
+        /// the extension allows storing the snippet in multiple formats
+        pub fn snippet_file(&self, offset: u16, extension: &str) -> PathBuf {
+            // paths and URLs don't make for proper file names
+            let name = match self.source {
+                AudioSource::YouTube => self.yt_id.clone(),
+                AudioSource::File | AudioSource::Url => self.id.to_string(),
+            };
+
+            Path::new(&Hit::download_dir()).join(match self.playback_length {
+                Some(length) => format!("{}_{}_{}.{}", name, offset, length, extension),
+                None => format!("{}_{}.{}", name, offset, extension),
+            })
+        }
+
+        /// the offsets of all snippets of this hit, starting with the playback offset
+        pub fn snippet_offsets(&self) -> Vec<u16> {
+            let mut offsets = vec![self.playback_offset];
+
+            for offset in self.snippets.iter() {
+                if !offsets.contains(offset) {
+                    offsets.push(*offset);
+                }
+            }
+
+            offsets
+        }
+
+        /// identifies the audio of this hit, no two hits may share it.
+        /// The YouTube video id for YouTube hits, the location otherwise
+        pub fn source_id(&self) -> HitId {
+            match self.source {
+                AudioSource::YouTube => HitId::YtId(self.yt_id.clone()),
+                AudioSource::File | AudioSource::Url => HitId::Location(self.location.clone()),
+            }
+        }
+
+        /// the audio file within the library directory for hits with a file source.
+        /// Paths which would leave the library directory aren't allowed
+        pub fn source_file(&self) -> Option<PathBuf> {
+            let path = Path::new(&self.location);
+
+            if self.source != AudioSource::File
+                || !path.components().all(|c| matches!(c, Component::Normal(_)))
+            {
+                return None;
+            }
+
+            Some(Path::new(&Hit::library_dir()).join(path))
+        }
+
+        /// whether all snippets of this hit got processed into all of the given formats
+        pub fn exists(&self, extensions: &[&str]) -> bool {
+            self.snippet_offsets().into_iter().all(|offset| {
+                extensions
+                    .iter()
+                    .all(|extension| self.snippet_file(offset, extension).is_file())
+            })
+        }
+
+        /// how similar the given title is to the title of this hit,
+        /// between 0.0 (nothing in common) and 1.0 (identical)
+        pub fn compare_title(&self, title: &str) -> f64 {
+            compare_names(&self.title, title)
+        }
+
+        /// how similar the given artist is to the artist of this hit,
+        /// between 0.0 (nothing in common) and 1.0 (identical).
+        /// Naming one of multiple artists is sufficient.
+        pub fn compare_artist(&self, artist: &str) -> f64 {
+            let normalized = normalize_text(&self.artist);
+
+            split_artists(&normalized)
+                .into_iter()
+                .map(|a| compare_names(a, artist))
+                .fold(compare_names(&normalized, artist), f64::max)
         }
     }
 
     impl PartialEq for Hit {
         fn eq(&self, h: &Self) -> bool {
-            self.yt_id == h.yt_id
+            self.source_id() == h.source_id()
         }
     }
 
     impl Hash for Hit {
         fn hash<H: Hasher>(&self, state: &mut H) {
-            self.yt_id.hash(state);
+            self.source_id().hash(state);
         }
     }
 
@@ -127,6 +378,8 @@ mod hitster_core {
     pub enum HitId {
         Id(Uuid),
         YtId(String),
+        /// the file path or URL of hits which don't come from YouTube
+        Location(String),
     }
 
     #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -143,7 +396,7 @@ mod hitster_core {
             HitsterData {
                 hits: hits
                     .into_iter()
-                    .map(|h| (vec![HitId::Id(h.id), HitId::YtId(h.yt_id.clone())], h))
+                    .map(|h| (vec![HitId::Id(h.id), h.source_id()], h))
                     .collect::<MultiKeyMap<HitId, Hit>>(),
                 packs: packs
                     .into_iter()
@@ -172,7 +425,7 @@ mod hitster_core {
                 ),
             );
             self.hits
-                .insert_many(vec![HitId::Id(hit.id), HitId::YtId(hit.yt_id.clone())], hit);
+                .insert_many(vec![HitId::Id(hit.id), hit.source_id()], hit);
         }
 
         pub fn get_packs(&self) -> Vec<&Pack> {
@@ -190,6 +443,97 @@ mod hitster_core {
             self.hits.get(hit_id)
         }
 
+        /// find hits which likely are the same song, e.g. uploaded as different videos.
+        /// Artist and title need to match, the years may differ by up to max_year_distance,
+        /// e.g. for the single and the album release. Songs known for different things don't count
+        pub fn find_duplicates(&self, max_year_distance: u32) -> Vec<(&Hit, &Hit)> {
+            let mut groups = HashMap::<(String, String), Vec<&Hit>>::new();
+            let mut duplicates = vec![];
+
+            for hit in self.hits.values() {
+                groups.entry(duplicate_key(hit)).or_default().push(hit);
+            }
+
+            for hits in groups.values_mut().filter(|h| h.len() > 1) {
+                hits.sort_by_key(|h| (h.year, h.id));
+
+                for (i, hit) in hits.iter().enumerate() {
+                    for other in hits[i + 1..].iter() {
+                        if other.year - hit.year > max_year_distance {
+                            break;
+                        }
+
+                        // e.g. the main themes of different movies by the same composer
+                        if !hit.belongs_to.is_empty()
+                            && !other.belongs_to.is_empty()
+                            && normalize_text(&hit.belongs_to) != normalize_text(&other.belongs_to)
+                        {
+                            continue;
+                        }
+
+                        duplicates.push((*hit, *other));
+                    }
+                }
+            }
+
+            duplicates
+        }
+
+        /// compare the years of all hits against a reference dataset, e.g. exported from MusicBrainz.
+        /// The reference contains one song per line with artist, title and release date separated by tabs,
+        /// the earliest release of a song counts. Returns all hits whose year differs by more than tolerance
+        /// together with the year suggested by the reference
+        pub fn verify_years<R: BufRead>(
+            &self,
+            reference: R,
+            tolerance: u32,
+        ) -> io::Result<Vec<(&Hit, u32)>> {
+            let mut keys = HashMap::<(String, String), Vec<&Hit>>::new();
+            let mut years = HashMap::<Uuid, (&Hit, u32)>::new();
+
+            for hit in self.hits.values() {
+                for key in year_reference_keys(hit).into_iter() {
+                    keys.entry(key).or_default().push(hit);
+                }
+            }
+
+            for line in reference.lines() {
+                let line = line?;
+                let mut fields = line.split('\t');
+
+                let (Some(artist), Some(title), Some(year)) = (
+                    fields.next(),
+                    fields.next(),
+                    fields.next().and_then(parse_year),
+                ) else {
+                    continue;
+                };
+
+                let Some(hits) = keys.get(&(normalize_name(artist), normalize_name(title))) else {
+                    continue;
+                };
+
+                for hit in hits.iter() {
+                    years
+                        .entry(hit.id)
+                        .and_modify(|(_, y)| *y = (*y).min(year))
+                        .or_insert((*hit, year));
+                }
+            }
+
+            let mut mismatches = years
+                .into_values()
+                .filter(|(hit, year)| hit.year.abs_diff(*year) > tolerance)
+                .collect::<Vec<_>>();
+
+            mismatches.sort_by(|(a, _), (b, _)| {
+                natord::compare_ignore_case(&a.artist, &b.artist)
+                    .then_with(|| natord::compare_ignore_case(&a.title, &b.title))
+            });
+
+            Ok(mismatches)
+        }
+
         pub fn get_pack(&self, pack_id: Uuid) -> Option<&Pack> {
             self.packs.get(&pack_id)
         }
@@ -198,7 +542,7 @@ mod hitster_core {
             if let Some(hit) = self.hits.get(hit) {
                 self.index.remove(&HitId::Id(hit.id));
                 self.hits
-                    .remove_many([&HitId::Id(hit.id), &HitId::YtId(hit.yt_id.clone())]);
+                    .remove_many([&HitId::Id(hit.id), &hit.source_id()]);
                 true
             } else {
                 false
@@ -373,14 +717,89 @@ mod hitster_core {
                 hit_id: row.try_get::<Uuid, &str>("hit_id")?,
                 r#type: issue_type,
                 message: row.try_get("message")?,
+                state: HitIssueState::from(row.try_get::<String, &str>("state")?),
+                assignee_id: row.try_get::<Option<Uuid>, &str>("assignee_id")?,
+                assignee_name: row.try_get("assignee_name")?,
                 created_at: row.try_get::<OffsetDateTime, &str>("created_at")?,
                 last_modified: row.try_get::<OffsetDateTime, &str>("last_modified")?,
             })
         }
     }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn hit(artist: &str, title: &str, year: u32, belongs_to: &str) -> Hit {
+            Hit {
+                artist: artist.into(),
+                title: title.into(),
+                belongs_to: belongs_to.into(),
+                year,
+                packs: vec![],
+                playback_offset: 0,
+                playback_length: None,
+                snippets: vec![],
+                id: Uuid::new_v4(),
+                yt_id: Uuid::new_v4().to_string(),
+                source: AudioSource::YouTube,
+                location: "".into(),
+                last_modified: OffsetDateTime::now_utc(),
+                downloaded: false,
+            }
+        }
+
+        #[test]
+        fn normalize_name_strips_brackets_and_articles() {
+            assert_eq!(normalize_name("The Beatles"), "beatles");
+            assert_eq!(normalize_name("Let It Be (Remastered 2009)"), "let it be");
+            assert_eq!(normalize_name("Café [Live] (feat. Someone)"), "cafe");
+            assert_eq!(normalize_name("The"), "the");
+        }
+
+        #[test]
+        fn normalize_name_ignores_unmatched_brackets() {
+            assert_eq!(normalize_name("Song) Title"), "song title");
+            assert_eq!(normalize_name("Song]) (Live)"), "song");
+        }
+
+        #[test]
+        fn find_duplicates_within_year_distance() {
+            let data = HitsterData::new(
+                vec![
+                    hit("Queen", "Bohemian Rhapsody", 1975, ""),
+                    hit("queen", "Bohemian  Rhapsody", 1976, ""),
+                    hit("Queen", "Bohemian Rhapsody", 1990, ""),
+                    hit("Queen", "Another One Bites The Dust", 1980, ""),
+                ],
+                vec![],
+            );
+
+            let duplicates = data.find_duplicates(1);
+
+            assert_eq!(duplicates.len(), 1);
+            assert_eq!((duplicates[0].0.year, duplicates[0].1.year), (1975, 1976));
+            assert_eq!(data.find_duplicates(15).len(), 3);
+        }
+
+        #[test]
+        fn find_duplicates_respects_belongs_to() {
+            let data = HitsterData::new(
+                vec![
+                    hit("John Williams", "Main Theme", 1977, "Star Wars"),
+                    hit("John Williams", "Main Theme", 1977, "Superman"),
+                    hit("John Williams", "Main Theme", 1977, ""),
+                ],
+                vec![],
+            );
+
+            // hits without belongs_to can still be duplicates of either one
+            assert_eq!(data.find_duplicates(0).len(), 2);
+        }
+    }
 }
 
 pub use hitster_core::{
-    Hit, HitId, HitIssue, HitIssueType, HitsterData, HitsterFileFormat, Pack, Permissions, Token,
-    User,
+    AudioSource, Hit, HitId, HitIssue, HitIssueState, HitIssueType, HitsterData, HitsterFileFormat,
+    Pack, Permissions, Token, User, parse_year,
 };
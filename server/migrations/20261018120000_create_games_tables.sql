@@ -0,0 +1,79 @@
+CREATE TABLE games (
+    -- game id, 8 alphanumeric characters
+    id TEXT UNIQUE PRIMARY KEY,
+    -- game state (open, guessing, intercepting, confirming)
+    state TEXT NOT NULL,
+    -- visibility scope of the game (public, private, local)
+    mode TEXT NOT NULL,
+    -- playback length of a hit when guessing
+    hit_duration INTEGER NOT NULL,
+    -- amount of tokens every player starts with
+    start_tokens INTEGER NOT NULL,
+    -- amount of hits needed to win
+    goal INTEGER NOT NULL,
+    -- id of the player who last scored a hit, if any
+    last_scored_id TEXT,
+    -- date of last modification
+    last_modified TEXT NOT NULL
+) WITHOUT ROWID;
+
+CREATE TABLE games_packs (
+    -- game id
+    game_id TEXT NOT NULL,
+    -- pack id, UUID4 string
+    pack_id TEXT NOT NULL,
+    PRIMARY KEY (game_id, pack_id),
+    FOREIGN KEY (game_id) REFERENCES games (id) ON DELETE CASCADE
+) WITHOUT ROWID;
+
+CREATE TABLE games_players (
+    -- game id
+    game_id TEXT NOT NULL,
+    -- player id, UUID4 string (identical to the user id for non-virtual players)
+    id TEXT NOT NULL,
+    -- position of the player within the turn order
+    position INTEGER NOT NULL,
+    -- name that is shown to other players
+    name TEXT NOT NULL,
+    -- player state (waiting, guessing, intercepting, confirming)
+    state TEXT NOT NULL,
+    -- wether the player created the game (boolean)
+    creator BOOLEAN NOT NULL,
+    -- amount of tokens the player currently has
+    tokens INTEGER NOT NULL,
+    -- wether it is this player's turn (boolean)
+    turn_player BOOLEAN NOT NULL,
+    -- the slot the player guessed, if any
+    guess_id INTEGER,
+    guess_from_year INTEGER,
+    guess_to_year INTEGER,
+    -- wether the player is a virtual player in a local game (boolean)
+    virtual BOOLEAN NOT NULL,
+    PRIMARY KEY (game_id, id),
+    FOREIGN KEY (game_id) REFERENCES games (id) ON DELETE CASCADE
+) WITHOUT ROWID;
+
+CREATE TABLE games_hits (
+    -- game id
+    game_id TEXT NOT NULL,
+    -- hit id, UUID4 string
+    hit_id TEXT NOT NULL,
+    -- where the hit lives within the game (remaining, remembered, player, revealed)
+    location TEXT NOT NULL,
+    -- the player owning the hit if location is player
+    player_id TEXT,
+    -- position of the hit within its location
+    position INTEGER NOT NULL,
+    FOREIGN KEY (game_id) REFERENCES games (id) ON DELETE CASCADE
+);
+
+CREATE INDEX games_hits_game_id ON games_hits (game_id);
+
+CREATE TABLE virtual_users (
+    -- user id, UUID4 string
+    id TEXT UNIQUE PRIMARY KEY,
+    -- generated name of the user
+    name TEXT NOT NULL,
+    -- login tokens (JSON)
+    tokens TEXT NOT NULL
+) WITHOUT ROWID;
@@ -0,0 +1,27 @@
+-- entries are kept after the game got removed, so there is no foreign key to games
+CREATE TABLE games_history (
+    -- sequential id, defines the order of entries
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    -- game id
+    game_id TEXT NOT NULL,
+    -- what happened (join, leave, start, stop, guess, intercept, reveal, confirm, skip, claim)
+    action TEXT NOT NULL,
+    -- id of the player who caused the entry, if any
+    player_id TEXT,
+    -- name of the player at the time the entry was recorded
+    player_name TEXT,
+    -- hit id, if a hit was involved
+    hit_id TEXT,
+    -- the slot the player guessed, if any
+    slot_id INTEGER,
+    slot_from_year INTEGER,
+    slot_to_year INTEGER,
+    -- amount of tokens the player had after the entry was recorded
+    tokens INTEGER,
+    -- wether the guess got confirmed (boolean)
+    confirmed BOOLEAN,
+    -- date of creation
+    created_at TEXT NOT NULL
+);
+
+CREATE INDEX games_history_game_id ON games_history (game_id);
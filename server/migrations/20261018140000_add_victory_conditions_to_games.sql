@@ -0,0 +1,17 @@
+-- condition which decides when a game is over (goal, rounds, time, deck)
+ALTER TABLE games ADD COLUMN victory_condition TEXT NOT NULL DEFAULT 'goal';
+-- amount of rounds to play when the victory condition is rounds
+ALTER TABLE games ADD COLUMN rounds INTEGER NOT NULL DEFAULT 10;
+-- time limit in minutes when the victory condition is time
+ALTER TABLE games ADD COLUMN time_limit INTEGER NOT NULL DEFAULT 30;
+-- tie breakers which get applied in order (JSON)
+ALTER TABLE games ADD COLUMN tie_breakers TEXT NOT NULL DEFAULT '[]';
+-- amount of turns that were completed since the game started
+ALTER TABLE games ADD COLUMN turns INTEGER NOT NULL DEFAULT 0;
+-- amount of rounds that were completed since the game started
+ALTER TABLE games ADD COLUMN rounds_played INTEGER NOT NULL DEFAULT 0;
+-- date the game got started, if it is running
+ALTER TABLE games ADD COLUMN started_at TEXT;
+
+-- amount of wrong guesses the player made since the game started
+ALTER TABLE games_players ADD COLUMN wrong_guesses INTEGER NOT NULL DEFAULT 0;
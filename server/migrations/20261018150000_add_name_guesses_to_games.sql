@@ -0,0 +1,10 @@
+-- wether the turn player can earn a token by naming title and artist (boolean)
+ALTER TABLE games ADD COLUMN name_hits BOOLEAN NOT NULL DEFAULT FALSE;
+-- similarity in percent a named title and artist need to be judged correct
+ALTER TABLE games ADD COLUMN name_tolerance INTEGER NOT NULL DEFAULT 80;
+-- title the turn player named, if any
+ALTER TABLE games ADD COLUMN name_guess_title TEXT;
+-- artist the turn player named, if any
+ALTER TABLE games ADD COLUMN name_guess_artist TEXT;
+-- judgement of the named title and artist (correct, wrong, ambiguous)
+ALTER TABLE games ADD COLUMN name_guess_judgement TEXT;
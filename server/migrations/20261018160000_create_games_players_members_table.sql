@@ -0,0 +1,21 @@
+-- wether players are teams of multiple users sharing one timeline (boolean)
+ALTER TABLE games ADD COLUMN teams BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE TABLE games_players_members (
+    -- game id
+    game_id TEXT NOT NULL,
+    -- id of the player (team) the member belongs to
+    player_id TEXT NOT NULL,
+    -- member id, UUID4 string (identical to the user id for non-virtual members)
+    id TEXT NOT NULL,
+    -- position of the member within the team
+    position INTEGER NOT NULL,
+    -- name that is shown to other players
+    name TEXT NOT NULL,
+    -- wether the member is a virtual player in a local game (boolean)
+    virtual BOOLEAN NOT NULL,
+    -- wether the member created the game, only they get the creator's rights within the creator's team (boolean)
+    creator BOOLEAN NOT NULL DEFAULT FALSE,
+    PRIMARY KEY (game_id, player_id, id),
+    FOREIGN KEY (game_id) REFERENCES games (id) ON DELETE CASCADE
+) WITHOUT ROWID;
@@ -0,0 +1,8 @@
+-- time in seconds the turn player has to guess, 0 disables the timeout
+ALTER TABLE games ADD COLUMN guess_timeout INTEGER NOT NULL DEFAULT 0;
+-- time in seconds other players have to intercept, 0 disables the timeout
+ALTER TABLE games ADD COLUMN intercept_timeout INTEGER NOT NULL DEFAULT 0;
+-- time in seconds a guess can be confirmed, 0 disables the timeout
+ALTER TABLE games ADD COLUMN confirm_timeout INTEGER NOT NULL DEFAULT 0;
+-- date the current phase times out, if it can time out at all
+ALTER TABLE games ADD COLUMN deadline TEXT;
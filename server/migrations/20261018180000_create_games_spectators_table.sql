@@ -0,0 +1,12 @@
+CREATE TABLE games_spectators (
+    -- game id
+    game_id TEXT NOT NULL,
+    -- user id, UUID4 string
+    id TEXT NOT NULL,
+    -- position of the spectator within the list of spectators
+    position INTEGER NOT NULL,
+    -- name that is shown to other users
+    name TEXT NOT NULL,
+    PRIMARY KEY (game_id, id),
+    FOREIGN KEY (game_id) REFERENCES games (id) ON DELETE CASCADE
+) WITHOUT ROWID;
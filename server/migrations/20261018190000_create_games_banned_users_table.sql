@@ -0,0 +1,12 @@
+CREATE TABLE games_banned_users (
+    -- game id
+    game_id TEXT NOT NULL,
+    -- user id, UUID4 string
+    id TEXT NOT NULL,
+    -- position of the user within the ban list
+    position INTEGER NOT NULL,
+    -- name of the user at the time they got banned
+    name TEXT NOT NULL,
+    PRIMARY KEY (game_id, id),
+    FOREIGN KEY (game_id) REFERENCES games (id) ON DELETE CASCADE
+) WITHOUT ROWID;
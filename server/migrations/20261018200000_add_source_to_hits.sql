@@ -0,0 +1,4 @@
+-- where the audio of the hit gets fetched from, either youtube, file or url
+ALTER TABLE hits ADD COLUMN source TEXT NOT NULL DEFAULT 'youtube';
+-- file path or URL of the audio for hits which don't come from YouTube, yt_id stays empty for those
+ALTER TABLE hits ADD COLUMN location TEXT NOT NULL DEFAULT '';
@@ -0,0 +1,49 @@
+-- the version of each hit as it got merged from the codebase last
+-- used as the common ancestor to find out which side changed a hit since then
+CREATE TABLE hits_merge_base (
+    -- hit id, UUID4 string
+    hit_id TEXT UNIQUE PRIMARY KEY,
+    -- song title
+    title TEXT NOT NULL,
+    -- song artist
+    artist TEXT NOT NULL,
+    -- id of the YouTube video
+    yt_id TEXT NOT NULL,
+    -- where the audio gets fetched from
+    source TEXT NOT NULL,
+    -- file path or URL of the audio if it doesn't come from YouTube
+    location TEXT NOT NULL,
+    -- year of release
+    year INTEGER NOT NULL,
+    -- offset to cut off after downloading
+    playback_offset INTEGER NOT NULL,
+    -- belongs to some musical, series, film or whatever
+    belongs_to TEXT NOT NULL,
+    FOREIGN KEY (hit_id) REFERENCES hits (id) ON DELETE CASCADE
+) WITHOUT ROWID;
+
+-- hits which got changed within both the codebase and the database since the last merge
+-- the row contains the version of the codebase, the database keeps its own until an admin resolves the conflict
+CREATE TABLE hits_merge_conflicts (
+    -- hit id, UUID4 string
+    hit_id TEXT UNIQUE PRIMARY KEY,
+    -- song title
+    title TEXT NOT NULL,
+    -- song artist
+    artist TEXT NOT NULL,
+    -- id of the YouTube video
+    yt_id TEXT NOT NULL,
+    -- where the audio gets fetched from
+    source TEXT NOT NULL,
+    -- file path or URL of the audio if it doesn't come from YouTube
+    location TEXT NOT NULL,
+    -- year of release
+    year INTEGER NOT NULL,
+    -- offset to cut off after downloading
+    playback_offset INTEGER NOT NULL,
+    -- belongs to some musical, series, film or whatever
+    belongs_to TEXT NOT NULL,
+    -- date the conflict was found
+    created_at TEXT NOT NULL,
+    FOREIGN KEY (hit_id) REFERENCES hits (id) ON DELETE CASCADE
+) WITHOUT ROWID;
@@ -0,0 +1,50 @@
+-- every change users made to hits, entries are kept after the hit got removed
+CREATE TABLE hits_history (
+    -- sequential id, defines the order of entries
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    -- hit id, UUID4 string
+    hit_id TEXT NOT NULL,
+    -- version of the hit, counting up from 1 for each hit
+    version INTEGER NOT NULL,
+    -- what happened (create, update, delete, import, revert)
+    action TEXT NOT NULL,
+    -- id of the user who made the change
+    user_id TEXT,
+    -- name of the user at the time the change was made
+    user_name TEXT,
+    -- the hit after the change, or right before it got deleted
+    title TEXT NOT NULL,
+    artist TEXT NOT NULL,
+    yt_id TEXT NOT NULL,
+    source TEXT NOT NULL,
+    location TEXT NOT NULL,
+    year INTEGER NOT NULL,
+    playback_offset INTEGER NOT NULL,
+    belongs_to TEXT NOT NULL,
+    -- ids of the packs the hit belonged to, JSON array
+    packs TEXT NOT NULL,
+    -- date of creation
+    created_at TEXT NOT NULL,
+    UNIQUE (hit_id, version)
+);
+
+-- every change users made to packs, entries are kept after the pack got removed
+CREATE TABLE packs_history (
+    -- sequential id, defines the order of entries
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    -- pack id, UUID4 string
+    pack_id TEXT NOT NULL,
+    -- version of the pack, counting up from 1 for each pack
+    version INTEGER NOT NULL,
+    -- what happened (create, update, delete, import, revert)
+    action TEXT NOT NULL,
+    -- id of the user who made the change
+    user_id TEXT,
+    -- name of the user at the time the change was made
+    user_name TEXT,
+    -- name of the pack after the change, or right before it got deleted
+    name TEXT NOT NULL,
+    -- date of creation
+    created_at TEXT NOT NULL,
+    UNIQUE (pack_id, version)
+);
@@ -0,0 +1,23 @@
+-- workflow state of the issue (open, in_progress, resolved, wont_fix)
+ALTER TABLE hit_issues ADD COLUMN state TEXT NOT NULL DEFAULT 'open';
+-- id of the user who takes care of the issue
+ALTER TABLE hit_issues ADD COLUMN assignee_id TEXT;
+-- name of that user at the time the issue got assigned
+ALTER TABLE hit_issues ADD COLUMN assignee_name TEXT;
+
+-- discussion about hit issues
+CREATE TABLE hit_issues_comments (
+    -- comment id, UUID4 string
+    id TEXT UNIQUE PRIMARY KEY,
+    -- issue id, UUID4 string
+    issue_id TEXT NOT NULL,
+    -- id of the user who wrote the comment
+    user_id TEXT NOT NULL,
+    -- name of the user at the time the comment was written
+    user_name TEXT NOT NULL,
+    -- the comment itself
+    message TEXT NOT NULL,
+    -- date of creation
+    created_at TEXT NOT NULL,
+    FOREIGN KEY (issue_id) REFERENCES hit_issues (id) ON DELETE CASCADE
+) WITHOUT ROWID;
@@ -0,0 +1,14 @@
+-- how many seconds of the hit get played, NULL to play until the end of the audio
+ALTER TABLE hits ADD COLUMN playback_length INTEGER;
+-- alternative playback offsets games rotate between, JSON array
+ALTER TABLE hits ADD COLUMN snippets TEXT NOT NULL DEFAULT '[]';
+
+-- merge bases, conflicts and the history keep track of them as well
+ALTER TABLE hits_merge_base ADD COLUMN playback_length INTEGER;
+ALTER TABLE hits_merge_base ADD COLUMN snippets TEXT NOT NULL DEFAULT '[]';
+
+ALTER TABLE hits_merge_conflicts ADD COLUMN playback_length INTEGER;
+ALTER TABLE hits_merge_conflicts ADD COLUMN snippets TEXT NOT NULL DEFAULT '[]';
+
+ALTER TABLE hits_history ADD COLUMN playback_length INTEGER;
+ALTER TABLE hits_history ADD COLUMN snippets TEXT NOT NULL DEFAULT '[]';
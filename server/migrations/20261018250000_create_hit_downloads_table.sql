@@ -0,0 +1,20 @@
+-- hits which still need to be downloaded
+CREATE TABLE hit_downloads (
+    -- hit id, UUID4 string
+    hit_id TEXT UNIQUE PRIMARY KEY,
+    -- state of the download (queued, downloading, processing, failed, cancelled)
+    status TEXT NOT NULL DEFAULT 'queued',
+    -- downloads with a higher priority get processed first
+    priority INTEGER NOT NULL DEFAULT 0,
+    -- how often the download was attempted already
+    attempts INTEGER NOT NULL DEFAULT 0,
+    -- date the download may be attempted again
+    next_attempt_at TEXT NOT NULL,
+    -- error of the last failed attempt
+    last_error TEXT,
+    -- date of creation
+    created_at TEXT NOT NULL,
+    -- date of last modification
+    last_modified TEXT NOT NULL,
+    FOREIGN KEY (hit_id) REFERENCES hits (id) ON DELETE CASCADE
+) WITHOUT ROWID;
@@ -0,0 +1,272 @@
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use hmac::{Hmac, Mac};
+use rocket::{
+    http::{ContentType, Status},
+    request::{self, FromRequest, Outcome, Request},
+    response::{self, Responder, Response},
+};
+use rocket_okapi::{
+    OpenApiError,
+    r#gen::OpenApiGenerator,
+    okapi::{
+        openapi3::{RefOr, Response as OpenApiResponse, Responses},
+        schemars::Map,
+    },
+    request::{OpenApiFromRequest, RequestHeaderInput},
+    response::OpenApiResponderInner,
+};
+use sha2::Sha256;
+use std::{io::Cursor, sync::LazyLock};
+
+static TOKEN_KEY: LazyLock<[u8; 32]> = LazyLock::new(rand::random);
+
+/// padded audio has a size of a multiple of this, so that hits of a similar length can't be told apart
+const PADDING_BUCKET: usize = 256 * 1024;
+
+/// the padding additionally varies by up to this amount
+const PADDING_JITTER: usize = 16 * 1024;
+
+/// request guard collecting the headers of partial and conditional requests for audio files
+pub struct AudioRequest {
+    range: Option<String>,
+    if_none_match: Option<String>,
+    if_range: Option<String>,
+}
+
+impl AudioRequest {
+    /// respond with the requested part of the audio, or nothing at all if the client already has it
+    pub fn respond(
+        &self,
+        mut body: Vec<u8>,
+        content_type: ContentType,
+        etag: Option<String>,
+        cache_control: &'static str,
+    ) -> AudioResponse {
+        let mut response = AudioResponse {
+            status: Status::Ok,
+            content_type,
+            etag,
+            cache_control,
+            content_range: None,
+            body: vec![],
+        };
+
+        if let Some(etag) = response.etag.as_ref()
+            && self.if_none_match.as_ref().is_some_and(|tags| {
+                tags.split(',')
+                    .map(|t| t.trim())
+                    .any(|t| t == "*" || t == etag)
+            })
+        {
+            response.status = Status::NotModified;
+            return response;
+        }
+
+        let length = body.len() as u64;
+
+        // ranges of an outdated version of the file would be garbage, the client gets the entire file instead
+        let range = self
+            .range
+            .as_ref()
+            .filter(|_| {
+                self.if_range
+                    .as_ref()
+                    .is_none_or(|t| response.etag.as_ref() == Some(t))
+            })
+            .and_then(|r| parse_range(r, length));
+
+        match range {
+            Some(Some((start, end))) => {
+                body.truncate(end as usize + 1);
+                body.drain(..start as usize);
+                response.body = body;
+                response.status = Status::PartialContent;
+                response.content_range = Some(format!("bytes {}-{}/{}", start, end, length));
+            }
+            Some(None) => {
+                response.status = Status::RangeNotSatisfiable;
+                response.content_range = Some(format!("bytes */{}", length));
+            }
+            None => {
+                response.body = body;
+            }
+        }
+
+        response
+    }
+}
+
+/// sign the given data with a key which is only valid until the server restarts
+pub fn sign(data: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(TOKEN_KEY.as_slice()).unwrap();
+
+    mac.update(data.as_bytes());
+
+    URL_SAFE_NO_PAD.encode(mac.finalize().into_bytes())
+}
+
+/// whether the signature was created by sign() for the given data
+pub fn verify(data: &str, signature: &str) -> bool {
+    let Ok(signature) = URL_SAFE_NO_PAD.decode(signature) else {
+        return false;
+    };
+    let mut mac = Hmac::<Sha256>::new_from_slice(TOKEN_KEY.as_slice()).unwrap();
+
+    mac.update(data.as_bytes());
+    mac.verify_slice(&signature).is_ok()
+}
+
+/// the amount of bytes to pad audio of the given length with.
+/// Only the bucket the audio falls into can be told from the padded size, no matter how often it gets requested
+pub fn padding(length: usize, jitter: usize) -> usize {
+    length.div_ceil(PADDING_BUCKET) * PADDING_BUCKET - length + jitter % PADDING_JITTER
+}
+
+/// parse a range header of a file with the given length.
+/// Returns None if the header should be ignored, Some(None) if the range can't be satisfied
+/// and the first and last byte of the range otherwise.
+/// Only single ranges are supported, clients asking for multiple ones get the entire file
+fn parse_range(range: &str, length: u64) -> Option<Option<(u64, u64)>> {
+    let (start, end) = range.trim().strip_prefix("bytes=")?.split_once('-')?;
+    let (start, end) = (start.trim(), end.trim());
+
+    let range = if start.is_empty() {
+        // the last bytes of the file
+        let suffix = end.parse::<u64>().ok()?;
+
+        (suffix > 0 && length > 0).then(|| (length - suffix.min(length), length - 1))
+    } else {
+        let start = start.parse::<u64>().ok()?;
+        let end = if end.is_empty() {
+            u64::MAX
+        } else {
+            end.parse::<u64>().ok()?
+        };
+
+        if end < start {
+            return None;
+        }
+
+        (start < length).then(|| (start, end.min(length - 1)))
+    };
+
+    Some(range)
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for AudioRequest {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        let headers = req.headers();
+
+        Outcome::Success(AudioRequest {
+            range: headers.get_one("Range").map(String::from),
+            if_none_match: headers.get_one("If-None-Match").map(String::from),
+            if_range: headers.get_one("If-Range").map(String::from),
+        })
+    }
+}
+
+impl OpenApiFromRequest<'_> for AudioRequest {
+    fn from_request_input(
+        _gen: &mut OpenApiGenerator,
+        _name: String,
+        _required: bool,
+    ) -> rocket_okapi::Result<RequestHeaderInput> {
+        Ok(RequestHeaderInput::None)
+    }
+}
+
+/// an audio file, or the requested part of it
+pub struct AudioResponse {
+    status: Status,
+    content_type: ContentType,
+    etag: Option<String>,
+    cache_control: &'static str,
+    content_range: Option<String>,
+    body: Vec<u8>,
+}
+
+impl<'r, 'o: 'r> Responder<'r, 'o> for AudioResponse {
+    fn respond_to(self, _: &'r Request<'_>) -> response::Result<'o> {
+        let mut response = Response::build();
+
+        response
+            .status(self.status)
+            .raw_header("Accept-Ranges", "bytes")
+            // the format gets negotiated via the Accept header
+            .raw_header("Vary", "Accept")
+            .raw_header("Cache-Control", self.cache_control);
+
+        if let Some(etag) = self.etag {
+            response.raw_header("ETag", etag);
+        }
+
+        if let Some(content_range) = self.content_range {
+            response.raw_header("Content-Range", content_range);
+        }
+
+        if self.status == Status::Ok || self.status == Status::PartialContent {
+            response
+                .header(self.content_type)
+                .sized_body(self.body.len(), Cursor::new(self.body));
+        }
+
+        response.ok()
+    }
+}
+
+impl OpenApiResponderInner for AudioResponse {
+    fn responses(_generator: &mut OpenApiGenerator) -> Result<Responses, OpenApiError> {
+        let mut responses = Map::new();
+        responses.insert(
+            "200".to_string(),
+            RefOr::Object(OpenApiResponse {
+                description: "\
+                # [200 OK](https://developer.mozilla.org/en-US/docs/Web/HTTP/Status/200)\n\
+                The entire audio file.\
+                "
+                .to_string(),
+                ..Default::default()
+            }),
+        );
+        responses.insert(
+            "206".to_string(),
+            RefOr::Object(OpenApiResponse {
+                description: "\
+                # [206 Partial Content](https://developer.mozilla.org/en-US/docs/Web/HTTP/Status/206)\n\
+                The part of the audio file requested via the Range header.\
+                "
+                .to_string(),
+                ..Default::default()
+            }),
+        );
+        responses.insert(
+            "304".to_string(),
+            RefOr::Object(OpenApiResponse {
+                description: "\
+                # [304 Not Modified](https://developer.mozilla.org/en-US/docs/Web/HTTP/Status/304)\n\
+                The ETag handed in via the If-None-Match header is still up to date.\
+                "
+                .to_string(),
+                ..Default::default()
+            }),
+        );
+        responses.insert(
+            "416".to_string(),
+            RefOr::Object(OpenApiResponse {
+                description: "\
+                # [416 Range Not Satisfiable](https://developer.mozilla.org/en-US/docs/Web/HTTP/Status/416)\n\
+                The requested range lies outside of the audio file.\
+                "
+                .to_string(),
+                ..Default::default()
+            }),
+        );
+        Ok(Responses {
+            responses,
+            ..Default::default()
+        })
+    }
+}
@@ -1,9 +1,41 @@
-use crate::hits::HitPayload;
-use hitster_core::{Hit, User};
-use rocket::serde::json::Json;
-use rocket_okapi::okapi::{schemars, schemars::JsonSchema};
+use crate::{
+    HitsterConfig,
+    hits::HitPayload,
+    services::{GameService, ServiceHandle, ServiceStore},
+};
+use hitster_core::{Hit, Permissions, Token, User};
+use rocket::{
+    Build, Orbit, Rocket,
+    fairing::{self, Fairing, Info, Kind},
+    request::{self, FromRequest, Outcome, Request},
+    serde::json::Json,
+    tokio::{
+        sync::{
+            broadcast::{Receiver, Sender, channel, error::RecvError},
+            mpsc::unbounded_channel,
+        },
+        time::{Duration, interval},
+    },
+};
+use rocket_db_pools::Database;
+use rocket_okapi::{
+    r#gen::OpenApiGenerator,
+    okapi::{
+        openapi3::{Object, Parameter, ParameterValue},
+        schemars,
+        schemars::JsonSchema,
+    },
+    request::{OpenApiFromRequest, RequestHeaderInput},
+};
 use serde::{Deserialize, Serialize};
-use std::{collections::VecDeque, convert::From, default::Default};
+use sqlx::{FromRow, QueryBuilder, Sqlite, SqliteConnection, SqlitePool};
+use std::{
+    collections::{HashMap, VecDeque},
+    convert::From,
+    default::Default,
+    sync::{Arc, Mutex},
+};
+use time::OffsetDateTime;
 use uuid::Uuid;
 
 /// A payload to address a slot
@@ -12,6 +44,12 @@ use uuid::Uuid;
 pub struct SlotPayload {
     /// The slot ID for a certain player, or no slot at all
     pub id: Option<u8>,
+    /// The title of the hit, if the turn player wants to name it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// The artist of the hit, if the turn player wants to name it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub artist: Option<String>,
 }
 
 /// Game settings
@@ -30,6 +68,36 @@ pub struct GameSettingsPayload {
     /// packs to draw hits from
     #[serde(skip_serializing_if = "Option::is_none")]
     pub packs: Option<Vec<Uuid>>,
+    /// the condition which decides when the game is over
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub victory_condition: Option<VictoryCondition>,
+    /// the amount of rounds to play if the victory condition is VictoryCondition.Rounds
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rounds: Option<u8>,
+    /// the time limit in minutes if the victory condition is VictoryCondition.Time
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time_limit: Option<u16>,
+    /// tie breakers which get applied in order if multiple players have the most hits
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tie_breakers: Option<Vec<TieBreaker>>,
+    /// wether the turn player earns a token by naming title and artist instead of being confirmed by another player
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name_hits: Option<bool>,
+    /// the similarity in percent a named title and artist need to have to be judged correct
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name_tolerance: Option<u8>,
+    /// wether players are teams of multiple users sharing one timeline
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub teams: Option<bool>,
+    /// the time in seconds the turn player has to guess before their turn gets skipped, 0 disables the timeout
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub guess_timeout: Option<u16>,
+    /// the time in seconds other players have to intercept before they pass automatically, 0 disables the timeout
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub intercept_timeout: Option<u16>,
+    /// the time in seconds a guess can be confirmed before it gets rejected automatically, 0 disables the timeout
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub confirm_timeout: Option<u16>,
 }
 
 /// options when creating a game
@@ -51,10 +119,34 @@ impl From<Json<GameSettingsPayload>> for GameSettingsPayload {
             hit_duration: src.hit_duration,
             start_tokens: src.start_tokens,
             packs: src.packs.clone(),
+            victory_condition: src.victory_condition,
+            rounds: src.rounds,
+            time_limit: src.time_limit,
+            tie_breakers: src.tie_breakers.clone(),
+            name_hits: src.name_hits,
+            name_tolerance: src.name_tolerance,
+            teams: src.teams,
+            guess_timeout: src.guess_timeout,
+            intercept_timeout: src.intercept_timeout,
+            confirm_timeout: src.confirm_timeout,
         }
     }
 }
 
+/// a token to play the current hit once
+
+#[derive(Serialize, JsonSchema)]
+pub struct AudioTokenPayload {
+    /// the token itself
+    pub token: String,
+    /// the URL to fetch the audio file with the token from
+    pub url: String,
+    /// the token can't be used anymore afterwards
+    #[serde(with = "time::serde::rfc3339")]
+    #[schemars(with = "String")]
+    pub expires_at: OffsetDateTime,
+}
+
 /// confirmation
 
 #[derive(Deserialize, Serialize, JsonSchema)]
@@ -78,6 +170,29 @@ pub enum GameState {
     Confirming,
 }
 
+impl From<String> for GameState {
+    fn from(value: String) -> Self {
+        match value.as_str() {
+            "open" => GameState::Open,
+            "guessing" => GameState::Guessing,
+            "intercepting" => GameState::Intercepting,
+            "confirming" => GameState::Confirming,
+            _ => panic!("invalid game state: {value}"),
+        }
+    }
+}
+
+impl From<GameState> for &'static str {
+    fn from(value: GameState) -> Self {
+        match value {
+            GameState::Open => "open",
+            GameState::Guessing => "guessing",
+            GameState::Intercepting => "intercepting",
+            GameState::Confirming => "confirming",
+        }
+    }
+}
+
 /// visibility scope of a game
 
 #[derive(Deserialize, Serialize, JsonSchema, Clone, Eq, PartialEq, Debug, Copy)]
@@ -91,6 +206,122 @@ pub enum GameMode {
     Local,
 }
 
+impl From<String> for GameMode {
+    fn from(value: String) -> Self {
+        match value.as_str() {
+            "public" => GameMode::Public,
+            "private" => GameMode::Private,
+            "local" => GameMode::Local,
+            _ => panic!("invalid game mode: {value}"),
+        }
+    }
+}
+
+impl From<GameMode> for &'static str {
+    fn from(value: GameMode) -> Self {
+        match value {
+            GameMode::Public => "public",
+            GameMode::Private => "private",
+            GameMode::Local => "local",
+        }
+    }
+}
+
+/// the condition which decides when a game is over
+
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Eq, PartialEq, Debug, Copy)]
+#[serde(rename_all_fields = "snake_case")]
+pub enum VictoryCondition {
+    /// the first player to collect the goal amount of hits wins
+    Goal,
+    /// the game ends after a fixed amount of rounds, the player with the most hits wins
+    Rounds,
+    /// the game ends with the first turn that finishes after the time limit, the player with the most hits wins
+    Time,
+    /// the game ends once all hits were played, the player with the most hits wins
+    Deck,
+}
+
+impl From<String> for VictoryCondition {
+    fn from(value: String) -> Self {
+        match value.as_str() {
+            "goal" => VictoryCondition::Goal,
+            "rounds" => VictoryCondition::Rounds,
+            "time" => VictoryCondition::Time,
+            "deck" => VictoryCondition::Deck,
+            _ => panic!("invalid victory condition: {value}"),
+        }
+    }
+}
+
+impl From<VictoryCondition> for &'static str {
+    fn from(value: VictoryCondition) -> Self {
+        match value {
+            VictoryCondition::Goal => "goal",
+            VictoryCondition::Rounds => "rounds",
+            VictoryCondition::Time => "time",
+            VictoryCondition::Deck => "deck",
+        }
+    }
+}
+
+/// criteria to decide between players with the same amount of hits
+
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Eq, PartialEq, Debug, Copy)]
+#[serde(rename_all_fields = "snake_case")]
+pub enum TieBreaker {
+    /// the player with the most tokens left wins
+    Tokens,
+    /// the player with the fewest wrong guesses wins
+    WrongGuesses,
+}
+
+/// the verdict on a named title and artist
+
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Eq, PartialEq, Debug, Copy)]
+#[serde(rename_all_fields = "snake_case")]
+pub enum NameJudgement {
+    /// title and artist are close enough, the turn player receives a token
+    Correct,
+    /// title or artist are way off or weren't named at all, the turn player doesn't receive a token
+    Wrong,
+    /// the names are close, but not close enough, another player needs to confirm
+    Ambiguous,
+}
+
+impl From<String> for NameJudgement {
+    fn from(value: String) -> Self {
+        match value.as_str() {
+            "correct" => NameJudgement::Correct,
+            "wrong" => NameJudgement::Wrong,
+            "ambiguous" => NameJudgement::Ambiguous,
+            _ => panic!("invalid name judgement: {value}"),
+        }
+    }
+}
+
+impl From<NameJudgement> for &'static str {
+    fn from(value: NameJudgement) -> Self {
+        match value {
+            NameJudgement::Correct => "correct",
+            NameJudgement::Wrong => "wrong",
+            NameJudgement::Ambiguous => "ambiguous",
+        }
+    }
+}
+
+/// title and artist of the current hit as named by the turn player
+
+#[derive(Serialize, JsonSchema, Clone, Eq, PartialEq, Debug)]
+pub struct NameGuess {
+    /// the named title
+    pub title: String,
+    /// the named artist
+    pub artist: String,
+    /// the verdict, available once the hit got revealed
+    pub judgement: Option<NameJudgement>,
+}
+
 #[derive(Clone, Eq, PartialEq, Debug)]
 pub struct Game {
     pub id: String,
@@ -105,6 +336,35 @@ pub struct Game {
     pub mode: GameMode,
     pub remembered_hits: Vec<Hit>,
     pub last_scored: Option<Player>,
+    pub victory_condition: VictoryCondition,
+    pub rounds: u8,
+    pub time_limit: u16,
+    pub tie_breakers: Vec<TieBreaker>,
+    pub turns: u16,
+    pub rounds_played: u16,
+    pub started_at: Option<OffsetDateTime>,
+    pub name_hits: bool,
+    pub name_tolerance: u8,
+    pub name_guess: Option<NameGuess>,
+    pub teams: bool,
+    pub guess_timeout: u16,
+    pub intercept_timeout: u16,
+    pub confirm_timeout: u16,
+    pub deadline: Option<OffsetDateTime>,
+    pub spectators: Vec<Spectator>,
+    pub banned: Vec<BannedUser>,
+}
+
+impl Game {
+    /// seconds left until the current phase times out, if it can time out at all
+    pub fn remaining_time(&self) -> Option<u16> {
+        self.deadline.map(|d| {
+            (d - OffsetDateTime::now_utc())
+                .as_seconds_f64()
+                .ceil()
+                .clamp(0.0, u16::MAX as f64) as u16
+        })
+    }
 }
 
 /// all information related to a game
@@ -131,6 +391,44 @@ pub struct GamePayload {
     pub mode: GameMode,
     /// the player who last scored a hit
     pub last_scored: Option<PlayerPayload>,
+    /// the condition which decides when the game is over
+    pub victory_condition: VictoryCondition,
+    /// the amount of rounds to play if the victory condition is VictoryCondition.Rounds
+    pub rounds: u8,
+    /// the time limit in minutes if the victory condition is VictoryCondition.Time
+    pub time_limit: u16,
+    /// tie breakers which get applied in order if multiple players have the most hits
+    pub tie_breakers: Vec<TieBreaker>,
+    /// the amount of turns that were completed since the game started
+    pub turns: u16,
+    /// the amount of rounds that were completed since the game started, a round is over once the turn passes on to the first player again
+    pub rounds_played: u16,
+    /// when the game got started
+    #[serde(with = "time::serde::rfc3339::option")]
+    #[schemars(with = "Option<String>")]
+    pub started_at: Option<OffsetDateTime>,
+    /// wether the turn player earns a token by naming title and artist
+    pub name_hits: bool,
+    /// the similarity in percent a named title and artist need to have to be judged correct
+    pub name_tolerance: u8,
+    /// title and artist named by the turn player in the current turn
+    pub name_guess: Option<NameGuess>,
+    /// wether players are teams of multiple users sharing one timeline
+    pub teams: bool,
+    /// the time in seconds the turn player has to guess, 0 if there is no timeout
+    pub guess_timeout: u16,
+    /// the time in seconds other players have to intercept, 0 if there is no timeout
+    pub intercept_timeout: u16,
+    /// the time in seconds a guess can be confirmed, 0 if there is no timeout
+    pub confirm_timeout: u16,
+    /// when the current phase times out
+    #[serde(with = "time::serde::rfc3339::option")]
+    #[schemars(with = "Option<String>")]
+    pub deadline: Option<OffsetDateTime>,
+    /// users who watch the game without playing
+    pub spectators: Vec<Spectator>,
+    /// users who were banned by the creator and can't join or spectate the game anymore
+    pub banned: Vec<BannedUser>,
 }
 
 impl From<&Game> for GamePayload {
@@ -146,6 +444,27 @@ impl From<&Game> for GamePayload {
             packs: game.packs.clone(),
             mode: game.mode,
             last_scored: game.last_scored.as_ref().map(|p| p.into()),
+            victory_condition: game.victory_condition,
+            rounds: game.rounds,
+            time_limit: game.time_limit,
+            tie_breakers: game.tie_breakers.clone(),
+            turns: game.turns,
+            rounds_played: game.rounds_played,
+            started_at: game.started_at,
+            name_hits: game.name_hits,
+            name_tolerance: game.name_tolerance,
+            // the named title and artist would give away the hit before it got revealed
+            name_guess: game
+                .name_guess
+                .clone()
+                .filter(|_| game.state == GameState::Confirming),
+            teams: game.teams,
+            guess_timeout: game.guess_timeout,
+            intercept_timeout: game.intercept_timeout,
+            confirm_timeout: game.confirm_timeout,
+            deadline: game.deadline,
+            spectators: game.spectators.clone(),
+            banned: game.banned.clone(),
         }
     }
 }
@@ -177,6 +496,29 @@ pub enum PlayerState {
     Confirming,
 }
 
+impl From<String> for PlayerState {
+    fn from(value: String) -> Self {
+        match value.as_str() {
+            "waiting" => PlayerState::Waiting,
+            "guessing" => PlayerState::Guessing,
+            "intercepting" => PlayerState::Intercepting,
+            "confirming" => PlayerState::Confirming,
+            _ => panic!("invalid player state: {value}"),
+        }
+    }
+}
+
+impl From<PlayerState> for &'static str {
+    fn from(value: PlayerState) -> Self {
+        match value {
+            PlayerState::Waiting => "waiting",
+            PlayerState::Guessing => "guessing",
+            PlayerState::Intercepting => "intercepting",
+            PlayerState::Confirming => "confirming",
+        }
+    }
+}
+
 #[derive(Serialize, Clone, Eq, PartialEq, Debug)]
 pub struct Player {
     pub id: Uuid,
@@ -189,6 +531,84 @@ pub struct Player {
     pub turn_player: bool,
     pub guess: Option<Slot>,
     pub r#virtual: bool,
+    pub wrong_guesses: u8,
+    pub members: Vec<TeamMember>,
+}
+
+impl Player {
+    /// wether the given user or virtual player acts as this player,
+    /// either by being this player or by being a member of this team
+    pub fn contains(&self, id: Uuid) -> bool {
+        if self.members.is_empty() {
+            self.id == id
+        } else {
+            self.members.iter().any(|m| m.id == id)
+        }
+    }
+
+    /// wether the given user or virtual player acts as the creator of the game,
+    /// members of the creator's team only do if they created the game themselves
+    pub fn is_creator(&self, id: Uuid) -> bool {
+        if self.members.is_empty() {
+            self.creator && self.id == id
+        } else {
+            self.creator && self.members.iter().any(|m| m.creator && m.id == id)
+        }
+    }
+}
+
+/// a user or virtual player who is part of a team
+
+#[derive(Serialize, JsonSchema, Clone, Eq, PartialEq, Debug)]
+pub struct TeamMember {
+    /// the id of the user or virtual player
+    pub id: Uuid,
+    /// the name that is shown to other users
+    pub name: String,
+    /// wether the member is virtual (in a local game) or an actual user
+    pub r#virtual: bool,
+    /// wether this member created the game, the other members of the team don't get the creator's rights
+    pub creator: bool,
+}
+
+impl From<&User> for TeamMember {
+    fn from(u: &User) -> Self {
+        Self {
+            id: u.id,
+            name: u.name.clone(),
+            r#virtual: false,
+            creator: false,
+        }
+    }
+}
+
+/// a user who watches a game without playing
+
+#[derive(Serialize, JsonSchema, Clone, Eq, PartialEq, Debug)]
+pub struct Spectator {
+    /// the id of the user
+    pub id: Uuid,
+    /// the name that is shown to other users
+    pub name: String,
+}
+
+impl From<&User> for Spectator {
+    fn from(u: &User) -> Self {
+        Self {
+            id: u.id,
+            name: u.name.clone(),
+        }
+    }
+}
+
+/// a user who got banned from a game
+
+#[derive(Serialize, JsonSchema, Clone, Eq, PartialEq, Debug)]
+pub struct BannedUser {
+    /// the id of the user
+    pub id: Uuid,
+    /// the name of the user at the time they got banned
+    pub name: String,
 }
 
 /// a player who is part of a game
@@ -215,6 +635,10 @@ pub struct PlayerPayload {
     pub guess: Option<Slot>,
     /// wether the player is virtual (in a local game) or an actual user
     pub r#virtual: bool,
+    /// the amount of wrong guesses this player made
+    pub wrong_guesses: u8,
+    /// the users sharing this player's timeline if the game is played in teams
+    pub members: Vec<TeamMember>,
 }
 
 impl From<&Player> for PlayerPayload {
@@ -230,6 +654,8 @@ impl From<&Player> for PlayerPayload {
             turn_player: p.turn_player,
             guess: p.guess.clone(),
             r#virtual: p.r#virtual,
+            wrong_guesses: p.wrong_guesses,
+            members: p.members.clone(),
         }
     }
 }
@@ -259,6 +685,8 @@ impl Default for Player {
             turn_player: false,
             guess: None,
             r#virtual: true,
+            wrong_guesses: 0,
+            members: vec![],
         }
     }
 }
@@ -276,6 +704,8 @@ impl From<&User> for Player {
 
 #[derive(Serialize, JsonSchema, Clone, Eq, PartialEq, Debug)]
 pub struct GameEvent {
+    #[serde(skip)]
+    pub id: u64,
     #[serde(skip)]
     pub game_id: String,
     #[serde(skip)]
@@ -291,12 +721,25 @@ pub struct GameEvent {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub winner: Option<PlayerPayload>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub winners: Option<Vec<PlayerPayload>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub last_scored: Option<PlayerPayload>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name_guess: Option<NameGuess>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remaining_time: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub spectators: Option<Vec<Spectator>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub banned: Option<Vec<BannedUser>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub game: Option<GamePayload>,
 }
 
 impl Default for GameEvent {
     fn default() -> Self {
         Self {
+            id: 0,
             game_id: "".into(),
             event: "".into(),
             players: None,
@@ -304,7 +747,1527 @@ impl Default for GameEvent {
             hit: None,
             settings: None,
             winner: None,
+            winners: None,
+            last_scored: None,
+            name_guess: None,
+            remaining_time: None,
+            spectators: None,
+            banned: None,
+            game: None,
+        }
+    }
+}
+
+/// commands which can be sent to a game via websocket, they behave identical to their REST counterparts
+
+#[derive(Deserialize, JsonSchema)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum GameCommand {
+    /// join the game, see PATCH /games/game_id/join
+    Join {
+        player: Option<String>,
+        team: Option<Uuid>,
+    },
+    /// guess a slot, see POST /games/game_id/guess
+    Guess {
+        slot: SlotPayload,
+        player_id: Option<Uuid>,
+    },
+    /// confirm a guess, see POST /games/game_id/confirm
+    Confirm { confirm: bool },
+    /// skip the current hit, see POST /games/game_id/skip
+    Skip { player_id: Option<Uuid> },
+    /// claim a hit, see POST /games/game_id/claim
+    Claim { player_id: Option<Uuid> },
+    /// update the game settings, see PATCH /games/game_id/update
+    Update { settings: GameSettingsPayload },
+}
+
+impl From<&GameCommand> for &'static str {
+    fn from(value: &GameCommand) -> Self {
+        match value {
+            GameCommand::Join { .. } => "join",
+            GameCommand::Guess { .. } => "guess",
+            GameCommand::Confirm { .. } => "confirm",
+            GameCommand::Skip { .. } => "skip",
+            GameCommand::Claim { .. } => "claim",
+            GameCommand::Update { .. } => "update",
+        }
+    }
+}
+
+/// messages sent to clients connected to a game via websocket
+
+#[derive(Serialize, JsonSchema, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum GameSocketMessage {
+    /// a game event, identical to the ones sent by the event stream
+    Event {
+        id: u64,
+        event: String,
+        data: Box<GameEvent>,
+    },
+    /// a command got executed successfully
+    Success { command: String, message: String },
+    /// a command failed, the status code is the one the REST API would respond with
+    Error {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        command: Option<String>,
+        message: String,
+        http_status_code: u16,
+    },
+}
+
+impl From<GameEvent> for GameSocketMessage {
+    fn from(value: GameEvent) -> Self {
+        Self::Event {
+            id: value.id,
+            event: value.event.clone(),
+            data: Box::new(value),
+        }
+    }
+}
+
+/// the amount of events per game which can be replayed to reconnecting clients
+const GAME_EVENT_LOG_SIZE: usize = 128;
+
+/// how long the log of a removed game is kept, so that the events announcing the removal still get numbered
+const GAME_EVENT_LOG_GRACE_PERIOD: time::Duration = time::Duration::minutes(1);
+
+/// the latest events of a game
+struct GameEventLog {
+    last_id: u64,
+    events: VecDeque<GameEvent>,
+    removed_at: Option<OffsetDateTime>,
+}
+
+/// distributes game events to all subscribers.
+/// Events are numbered per game and the latest ones are kept,
+/// so that clients can catch up on what they missed after reconnecting
+#[derive(Clone)]
+pub struct GameEventQueue {
+    sender: Sender<GameEvent>,
+    logs: Arc<Mutex<HashMap<String, GameEventLog>>>,
+}
+
+impl Default for GameEventQueue {
+    fn default() -> Self {
+        Self {
+            sender: channel::<GameEvent>(1024).0,
+            logs: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+}
+
+impl GameEventQueue {
+    /// returns the amount of subscribers who received the event
+    pub fn send(&self, mut event: GameEvent) -> usize {
+        let mut logs = self.logs.lock().unwrap();
+
+        let log = logs
+            .entry(event.game_id.clone())
+            .or_insert_with(|| GameEventLog {
+                last_id: 0,
+                events: VecDeque::new(),
+                removed_at: None,
+            });
+
+        log.last_id += 1;
+        event.id = log.last_id;
+
+        log.events.push_back(event.clone());
+
+        if log.events.len() > GAME_EVENT_LOG_SIZE {
+            log.events.pop_front();
+        }
+
+        // sending while still holding the lock keeps the ids in order
+        self.sender.send(event).unwrap_or(0)
+    }
+
+    pub fn subscribe(&self) -> Receiver<GameEvent> {
+        self.sender.subscribe()
+    }
+
+    /// forget the events of a removed game. The log is kept for a short while,
+    /// so that events sent right after the removal don't start counting from 0 again
+    pub fn remove(&self, game_id: &str) {
+        let mut logs = self.logs.lock().unwrap();
+        let now = OffsetDateTime::now_utc();
+
+        if let Some(log) = logs.get_mut(game_id) {
+            log.removed_at = Some(now);
+        }
+
+        logs.retain(|_, l| {
+            l.removed_at
+                .is_none_or(|r| r + GAME_EVENT_LOG_GRACE_PERIOD > now)
+        });
+    }
+
+    /// the id of the latest event of a game
+    pub fn last_id(&self, game_id: &str) -> u64 {
+        self.logs
+            .lock()
+            .unwrap()
+            .get(game_id)
+            .map(|l| l.last_id)
+            .unwrap_or(0)
+    }
+
+    /// all events of a game after the given id,
+    /// or none at all if some of them aren't available anymore
+    pub fn replay(&self, game_id: &str, last_id: u64) -> Option<Vec<GameEvent>> {
+        let logs = self.logs.lock().unwrap();
+        let log = logs.get(game_id)?;
+
+        if last_id > log.last_id || log.events.front().is_some_and(|e| e.id > last_id + 1) {
+            return None;
+        }
+
+        Some(
+            log.events
+                .iter()
+                .filter(|e| e.id > last_id)
+                .cloned()
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    /// subscribe to the events of a single game, starting after the given event id
+    pub fn subscribe_game(
+        &self,
+        game_id: &str,
+        last_id: Option<u64>,
+        user: Option<User>,
+        game_service: ServiceHandle<GameService>,
+    ) -> GameEventSubscription {
+        let mut subscription = GameEventSubscription {
+            game_id: game_id.into(),
+            user,
+            queue: self.clone(),
+            game_service,
+            receiver: self.subscribe(),
+            last_id: last_id.unwrap_or(0),
+            missed: VecDeque::new(),
+        };
+
+        if last_id.is_some() {
+            subscription.catch_up();
+        }
+
+        subscription
+    }
+}
+
+/// the events of a single game as received by one client.
+/// Events the client missed are replayed first,
+/// a snapshot of the game replaces the events which aren't available anymore
+pub struct GameEventSubscription {
+    game_id: String,
+    user: Option<User>,
+    queue: GameEventQueue,
+    game_service: ServiceHandle<GameService>,
+    receiver: Receiver<GameEvent>,
+    last_id: u64,
+    missed: VecDeque<GameEvent>,
+}
+
+impl GameEventSubscription {
+    /// the next event, or none once no more events will be sent
+    pub async fn next(&mut self) -> Option<GameEvent> {
+        loop {
+            if let Some(event) = self.missed.pop_front() {
+                self.last_id = event.id;
+                return Some(event);
+            }
+
+            match self.receiver.recv().await {
+                Ok(event) => {
+                    if event.game_id == self.game_id && event.id > self.last_id {
+                        self.last_id = event.id;
+                        return Some(event);
+                    }
+                }
+                Err(RecvError::Closed) => return None,
+                Err(RecvError::Lagged(_)) => self.catch_up(),
+            }
+        }
+    }
+
+    fn catch_up(&mut self) {
+        if let Some(events) = self.queue.replay(&self.game_id, self.last_id) {
+            self.missed.extend(events);
+            return;
+        }
+
+        let last_id = self.queue.last_id(&self.game_id);
+
+        if let Some(game) = self
+            .game_service
+            .lock()
+            .get(&self.game_id, self.user.as_ref())
+        {
+            self.missed.push_back(GameEvent {
+                id: last_id,
+                game_id: game.id.clone(),
+                event: "snapshot".into(),
+                game: Some((&game).into()),
+                ..Default::default()
+            });
+        }
+
+        self.last_id = last_id;
+    }
+}
+
+/// the id of the last event a client received before reconnecting to an event stream
+pub struct LastEventId(pub Option<u64>);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for LastEventId {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        Outcome::Success(LastEventId(
+            req.headers()
+                .get_one("Last-Event-ID")
+                .and_then(|id| id.parse::<u64>().ok()),
+        ))
+    }
+}
+
+impl OpenApiFromRequest<'_> for LastEventId {
+    fn from_request_input(
+        r#gen: &mut OpenApiGenerator,
+        _name: String,
+        _required: bool,
+    ) -> rocket_okapi::Result<RequestHeaderInput> {
+        Ok(RequestHeaderInput::Parameter(Parameter {
+            name: "Last-Event-ID".into(),
+            location: "header".into(),
+            description: Some(
+                "the id of the last event received, browsers send it automatically when reconnecting"
+                    .into(),
+            ),
+            required: false,
+            deprecated: false,
+            allow_empty_value: false,
+            value: ParameterValue::Schema {
+                style: None,
+                explode: None,
+                allow_reserved: false,
+                schema: r#gen.json_schema::<u64>(),
+                example: None,
+                examples: None,
+            },
+            extensions: Object::default(),
+        }))
+    }
+}
+
+/// kinds of entries within the history of a game
+
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Eq, PartialEq, Debug, Copy)]
+#[serde(rename_all_fields = "snake_case")]
+pub enum GameHistoryAction {
+    /// a player joined the game
+    Join,
+    /// a player left the game
+    Leave,
+    /// the game got started
+    Start,
+    /// the game got stopped
+    Stop,
+    /// the turn player guessed a slot
+    Guess,
+    /// another player intercepted by guessing a slot, or passed if no slot is set
+    Intercept,
+    /// the hit got revealed, the player (if any) received the hit
+    Reveal,
+    /// the guess of the turn player got confirmed or rejected
+    Confirm,
+    /// a player skipped the current hit by paying a token
+    Skip,
+    /// a player claimed a hit by paying three tokens
+    Claim,
+    /// a player didn't react in time and got passed
+    Timeout,
+}
+
+impl From<String> for GameHistoryAction {
+    fn from(value: String) -> Self {
+        match value.as_str() {
+            "join" => GameHistoryAction::Join,
+            "leave" => GameHistoryAction::Leave,
+            "start" => GameHistoryAction::Start,
+            "stop" => GameHistoryAction::Stop,
+            "guess" => GameHistoryAction::Guess,
+            "intercept" => GameHistoryAction::Intercept,
+            "reveal" => GameHistoryAction::Reveal,
+            "confirm" => GameHistoryAction::Confirm,
+            "skip" => GameHistoryAction::Skip,
+            "claim" => GameHistoryAction::Claim,
+            "timeout" => GameHistoryAction::Timeout,
+            _ => panic!("invalid game history action: {value}"),
+        }
+    }
+}
+
+impl From<GameHistoryAction> for &'static str {
+    fn from(value: GameHistoryAction) -> Self {
+        match value {
+            GameHistoryAction::Join => "join",
+            GameHistoryAction::Leave => "leave",
+            GameHistoryAction::Start => "start",
+            GameHistoryAction::Stop => "stop",
+            GameHistoryAction::Guess => "guess",
+            GameHistoryAction::Intercept => "intercept",
+            GameHistoryAction::Reveal => "reveal",
+            GameHistoryAction::Confirm => "confirm",
+            GameHistoryAction::Skip => "skip",
+            GameHistoryAction::Claim => "claim",
+            GameHistoryAction::Timeout => "timeout",
+        }
+    }
+}
+
+/// a single entry within the history of a game
+
+#[derive(Serialize, JsonSchema, Clone, Eq, PartialEq, Debug)]
+pub struct GameHistoryEntry {
+    #[serde(skip)]
+    pub game_id: String,
+    /// what happened
+    pub action: GameHistoryAction,
+    /// the player who caused this entry
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub player_id: Option<Uuid>,
+    /// the name of the player at that time
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub player_name: Option<String>,
+    /// the hit involved
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hit_id: Option<Uuid>,
+    /// the slot the player guessed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub slot: Option<Slot>,
+    /// the amount of tokens the player had afterwards
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tokens: Option<u8>,
+    /// wether the guess got confirmed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub confirmed: Option<bool>,
+    /// when this happened
+    #[serde(with = "time::serde::rfc3339")]
+    #[schemars(with = "String")]
+    pub created_at: OffsetDateTime,
+}
+
+impl GameHistoryEntry {
+    pub fn new(game_id: &str, action: GameHistoryAction, player: Option<&Player>) -> Self {
+        Self {
+            game_id: game_id.into(),
+            action,
+            player_id: player.map(|p| p.id),
+            player_name: player.map(|p| p.name.clone()),
+            hit_id: None,
+            slot: None,
+            tokens: player.map(|p| p.tokens),
+            confirmed: None,
+            created_at: OffsetDateTime::now_utc(),
+        }
+    }
+}
+
+/// the hits of a game which aren't in the hands of any player, in order
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct GameDeck {
+    pub remaining: Vec<Uuid>,
+    pub remembered: Vec<Uuid>,
+}
+
+impl GameDeck {
+    /// wether the deck still equals the one of the game
+    pub fn matches(&self, game: &Game) -> bool {
+        self.remaining
+            .iter()
+            .eq(game.hits_remaining.iter().map(|h| &h.id))
+            && self
+                .remembered
+                .iter()
+                .eq(game.remembered_hits.iter().map(|h| &h.id))
+    }
+}
+
+impl From<&Game> for GameDeck {
+    fn from(game: &Game) -> Self {
+        Self {
+            remaining: game.hits_remaining.iter().map(|h| h.id).collect(),
+            remembered: game.remembered_hits.iter().map(|h| h.id).collect(),
+        }
+    }
+}
+
+/// a change to a game that needs to be reflected within the database
+
+#[derive(Clone, Debug)]
+pub enum GameUpdate {
+    /// the game without its deck, the deck is only part of the update if it changed
+    Save(Box<Game>, Option<GameDeck>),
+    Remove(String),
+}
+
+#[derive(FromRow)]
+struct GameRow {
+    id: String,
+    state: String,
+    mode: String,
+    hit_duration: u8,
+    start_tokens: u8,
+    goal: u8,
+    last_scored_id: Option<Uuid>,
+    victory_condition: String,
+    rounds: u8,
+    time_limit: u16,
+    tie_breakers: String,
+    turns: u16,
+    rounds_played: u16,
+    started_at: Option<OffsetDateTime>,
+    name_hits: bool,
+    name_tolerance: u8,
+    name_guess_title: Option<String>,
+    name_guess_artist: Option<String>,
+    name_guess_judgement: Option<String>,
+    teams: bool,
+    guess_timeout: u16,
+    intercept_timeout: u16,
+    confirm_timeout: u16,
+    deadline: Option<OffsetDateTime>,
+}
+
+#[derive(FromRow)]
+struct GamePackRow {
+    game_id: String,
+    pack_id: Uuid,
+}
+
+#[derive(FromRow)]
+struct GamePlayerRow {
+    game_id: String,
+    id: Uuid,
+    name: String,
+    state: String,
+    creator: bool,
+    tokens: u8,
+    turn_player: bool,
+    guess_id: Option<u8>,
+    guess_from_year: Option<u32>,
+    guess_to_year: Option<u32>,
+    #[sqlx(rename = "virtual")]
+    r#virtual: bool,
+    wrong_guesses: u8,
+}
+
+#[derive(FromRow)]
+struct GamePlayerMemberRow {
+    game_id: String,
+    player_id: Uuid,
+    id: Uuid,
+    name: String,
+    #[sqlx(rename = "virtual")]
+    r#virtual: bool,
+    creator: bool,
+}
+
+#[derive(FromRow)]
+struct GameSpectatorRow {
+    game_id: String,
+    id: Uuid,
+    name: String,
+}
+
+#[derive(FromRow)]
+struct GameBannedUserRow {
+    game_id: String,
+    id: Uuid,
+    name: String,
+}
+
+#[derive(FromRow)]
+struct GameHitRow {
+    game_id: String,
+    location: String,
+    player_id: Option<Uuid>,
+    id: Uuid,
+    title: String,
+    artist: String,
+    yt_id: String,
+    source: String,
+    hit_location: String,
+    belongs_to: String,
+    year: u32,
+    playback_offset: u16,
+    playback_length: Option<u16>,
+    snippets: String,
+    last_modified: OffsetDateTime,
+    downloaded: bool,
+}
+
+#[derive(FromRow)]
+struct HitPackRow {
+    hit_id: Uuid,
+    pack_id: Uuid,
+}
+
+#[derive(FromRow)]
+struct VirtualUserRow {
+    id: Uuid,
+    name: String,
+    tokens: String,
+}
+
+#[derive(FromRow)]
+struct GameHistoryRow {
+    game_id: String,
+    action: String,
+    player_id: Option<Uuid>,
+    player_name: Option<String>,
+    hit_id: Option<Uuid>,
+    slot_id: Option<u8>,
+    slot_from_year: Option<u32>,
+    slot_to_year: Option<u32>,
+    tokens: Option<u8>,
+    confirmed: Option<bool>,
+    created_at: OffsetDateTime,
+}
+
+impl From<GameHistoryRow> for GameHistoryEntry {
+    fn from(row: GameHistoryRow) -> Self {
+        Self {
+            game_id: row.game_id,
+            action: row.action.into(),
+            player_id: row.player_id,
+            player_name: row.player_name,
+            hit_id: row.hit_id,
+            slot: row.slot_id.map(|id| Slot {
+                id,
+                from_year: row.slot_from_year.unwrap_or_default(),
+                to_year: row.slot_to_year.unwrap_or_default(),
+            }),
+            tokens: row.tokens,
+            confirmed: row.confirmed,
+            created_at: row.created_at,
+        }
+    }
+}
+
+pub async fn get_history(
+    db: &mut SqliteConnection,
+    game_id: &str,
+) -> Result<Vec<GameHistoryEntry>, sqlx::Error> {
+    sqlx::query_as::<_, GameHistoryRow>(
+        r#"
+SELECT
+    game_id,
+    action,
+    player_id,
+    player_name,
+    hit_id,
+    slot_id,
+    slot_from_year,
+    slot_to_year,
+    tokens,
+    confirmed,
+    created_at
+FROM games_history
+WHERE game_id = ?
+ORDER BY id ASC"#,
+    )
+    .bind(game_id)
+    .fetch_all(db)
+    .await
+    .map(|rows| rows.into_iter().map(|row| row.into()).collect::<Vec<_>>())
+}
+
+async fn save_history(db: &SqlitePool, entries: &[GameHistoryEntry]) -> Result<(), sqlx::Error> {
+    // sqlite limits the amount of bound parameters per statement
+    for chunk in entries.chunks(75) {
+        let mut qb = QueryBuilder::<Sqlite>::new(
+            r#"
+INSERT INTO games_history (
+    game_id,
+    action,
+    player_id,
+    player_name,
+    hit_id,
+    slot_id,
+    slot_from_year,
+    slot_to_year,
+    tokens,
+    confirmed,
+    created_at) "#,
+        );
+        qb.push_values(chunk, |mut b, entry| {
+            b.push_bind(&entry.game_id)
+                .push_bind(<&'static str>::from(entry.action))
+                .push_bind(entry.player_id)
+                .push_bind(&entry.player_name)
+                .push_bind(entry.hit_id)
+                .push_bind(entry.slot.as_ref().map(|s| s.id))
+                .push_bind(entry.slot.as_ref().map(|s| s.from_year))
+                .push_bind(entry.slot.as_ref().map(|s| s.to_year))
+                .push_bind(entry.tokens)
+                .push_bind(entry.confirmed)
+                .push_bind(entry.created_at);
+        });
+        qb.build().execute(db).await?;
+    }
+
+    Ok(())
+}
+
+async fn load_games(db: &SqlitePool) -> Result<(Vec<Game>, Vec<User>), sqlx::Error> {
+    let users = sqlx::query_as::<_, VirtualUserRow>("SELECT id, name, tokens FROM virtual_users")
+        .fetch_all(db)
+        .await?
+        .into_iter()
+        .map(|row| User {
+            id: row.id,
+            name: row.name,
+            password: "".into(),
+            tokens: serde_json::from_str::<Vec<Token>>(&row.tokens).unwrap_or_default(),
+            r#virtual: true,
+            permissions: Permissions::default(),
+        })
+        .collect::<Vec<_>>();
+
+    let mut packs = sqlx::query_as::<_, GamePackRow>("SELECT game_id, pack_id FROM games_packs")
+        .fetch_all(db)
+        .await?
+        .into_iter()
+        .fold(HashMap::<String, Vec<Uuid>>::new(), |mut m, row| {
+            m.entry(row.game_id).or_default().push(row.pack_id);
+            m
+        });
+
+    let mut players = sqlx::query_as::<_, GamePlayerRow>(
+        r#"
+SELECT
+    game_id,
+    id,
+    name,
+    state,
+    creator,
+    tokens,
+    turn_player,
+    guess_id,
+    guess_from_year,
+    guess_to_year,
+    virtual,
+    wrong_guesses
+FROM games_players ORDER BY game_id, position"#,
+    )
+    .fetch_all(db)
+    .await?
+    .into_iter()
+    .fold(HashMap::<String, Vec<Player>>::new(), |mut m, row| {
+        m.entry(row.game_id).or_default().push(Player {
+            id: row.id,
+            name: row.name,
+            state: row.state.into(),
+            creator: row.creator,
+            hits: vec![],
+            tokens: row.tokens,
+            slots: vec![],
+            turn_player: row.turn_player,
+            guess: row.guess_id.map(|id| Slot {
+                id,
+                from_year: row.guess_from_year.unwrap_or(0),
+                to_year: row.guess_to_year.unwrap_or(0),
+            }),
+            r#virtual: row.r#virtual,
+            wrong_guesses: row.wrong_guesses,
+            members: vec![],
+        });
+        m
+    });
+
+    for row in sqlx::query_as::<_, GamePlayerMemberRow>(
+        "SELECT game_id, player_id, id, name, virtual, creator FROM games_players_members ORDER BY game_id, player_id, position",
+    )
+    .fetch_all(db)
+    .await?
+    .into_iter()
+    {
+        if let Some(player) = players
+            .get_mut(&row.game_id)
+            .and_then(|p| p.iter_mut().find(|p| p.id == row.player_id))
+        {
+            player.members.push(TeamMember {
+                id: row.id,
+                name: row.name,
+                r#virtual: row.r#virtual,
+                creator: row.creator,
+            });
+        }
+    }
+
+    let mut spectators = sqlx::query_as::<_, GameSpectatorRow>(
+        "SELECT game_id, id, name FROM games_spectators ORDER BY game_id, position",
+    )
+    .fetch_all(db)
+    .await?
+    .into_iter()
+    .fold(HashMap::<String, Vec<Spectator>>::new(), |mut m, row| {
+        m.entry(row.game_id).or_default().push(Spectator {
+            id: row.id,
+            name: row.name,
+        });
+        m
+    });
+
+    let mut banned = sqlx::query_as::<_, GameBannedUserRow>(
+        "SELECT game_id, id, name FROM games_banned_users ORDER BY game_id, position",
+    )
+    .fetch_all(db)
+    .await?
+    .into_iter()
+    .fold(HashMap::<String, Vec<BannedUser>>::new(), |mut m, row| {
+        m.entry(row.game_id).or_default().push(BannedUser {
+            id: row.id,
+            name: row.name,
+        });
+        m
+    });
+
+    let hits_packs = sqlx::query_as::<_, HitPackRow>(
+        "SELECT hit_id, pack_id FROM hits_packs WHERE marked_for_deletion = ?",
+    )
+    .bind(false)
+    .fetch_all(db)
+    .await?
+    .into_iter()
+    .fold(HashMap::<Uuid, Vec<Uuid>>::new(), |mut m, row| {
+        m.entry(row.hit_id).or_default().push(row.pack_id);
+        m
+    });
+
+    let mut hits = sqlx::query_as::<_, GameHitRow>(
+        r#"
+SELECT
+    games_hits.game_id,
+    games_hits.location,
+    games_hits.player_id,
+    hits.id,
+    hits.title,
+    hits.artist,
+    hits.yt_id,
+    hits.source,
+    hits.location AS hit_location,
+    hits.belongs_to,
+    hits.year,
+    hits.playback_offset,
+    hits.playback_length,
+    hits.snippets,
+    hits.last_modified,
+    hits.downloaded
+FROM games_hits INNER JOIN hits ON hits.id = games_hits.hit_id
+ORDER BY games_hits.game_id, games_hits.location, games_hits.position"#,
+    )
+    .fetch_all(db)
+    .await?
+    .into_iter()
+    .fold(HashMap::<String, Vec<GameHitRow>>::new(), |mut m, row| {
+        m.entry(row.game_id.clone()).or_default().push(row);
+        m
+    });
+
+    let games = sqlx::query_as::<_, GameRow>(
+        r#"
+SELECT
+    id,
+    state,
+    mode,
+    hit_duration,
+    start_tokens,
+    goal,
+    last_scored_id,
+    victory_condition,
+    rounds,
+    time_limit,
+    tie_breakers,
+    turns,
+    rounds_played,
+    started_at,
+    name_hits,
+    name_tolerance,
+    name_guess_title,
+    name_guess_artist,
+    name_guess_judgement,
+    teams,
+    guess_timeout,
+    intercept_timeout,
+    confirm_timeout,
+    deadline
+FROM games"#,
+    )
+    .fetch_all(db)
+    .await?
+    .into_iter()
+    .map(|row| {
+        let mut game = Game {
+            id: row.id.clone(),
+            players: players.remove(&row.id).unwrap_or_default(),
+            state: row.state.into(),
+            hits_remaining: VecDeque::new(),
+            hit_duration: row.hit_duration,
+            start_tokens: row.start_tokens,
+            goal: row.goal,
+            hit: None,
+            packs: packs.remove(&row.id).unwrap_or_default(),
+            mode: row.mode.into(),
+            remembered_hits: vec![],
             last_scored: None,
+            victory_condition: row.victory_condition.into(),
+            rounds: row.rounds,
+            time_limit: row.time_limit,
+            tie_breakers: serde_json::from_str::<Vec<TieBreaker>>(&row.tie_breakers)
+                .unwrap_or_default(),
+            turns: row.turns,
+            rounds_played: row.rounds_played,
+            started_at: row.started_at,
+            name_hits: row.name_hits,
+            name_tolerance: row.name_tolerance,
+            teams: row.teams,
+            guess_timeout: row.guess_timeout,
+            intercept_timeout: row.intercept_timeout,
+            confirm_timeout: row.confirm_timeout,
+            deadline: row.deadline,
+            spectators: spectators.remove(&row.id).unwrap_or_default(),
+            banned: banned.remove(&row.id).unwrap_or_default(),
+            name_guess: row
+                .name_guess_title
+                .zip(row.name_guess_artist)
+                .map(|(title, artist)| NameGuess {
+                    title,
+                    artist,
+                    judgement: row.name_guess_judgement.map(|j| j.into()),
+                }),
+        };
+
+        for row in hits.remove(&row.id).unwrap_or_default().into_iter() {
+            let hit = Hit {
+                artist: row.artist,
+                title: row.title,
+                belongs_to: row.belongs_to,
+                year: row.year,
+                packs: hits_packs.get(&row.id).cloned().unwrap_or_default(),
+                playback_offset: row.playback_offset,
+                playback_length: row.playback_length,
+                snippets: serde_json::from_str(&row.snippets).unwrap_or_default(),
+                id: row.id,
+                yt_id: row.yt_id,
+                source: row.source.into(),
+                location: row.hit_location,
+                last_modified: row.last_modified,
+                downloaded: row.downloaded,
+            };
+
+            match row.location.as_str() {
+                "remaining" => game.hits_remaining.push_back(hit),
+                "remembered" => game.remembered_hits.push(hit),
+                "revealed" => game.hit = Some(hit),
+                _ => {
+                    if let Some(player) = game
+                        .players
+                        .iter_mut()
+                        .find(|p| Some(p.id) == row.player_id)
+                    {
+                        player.hits.push(hit);
+                    }
+                }
+            }
+        }
+
+        game.last_scored = row
+            .last_scored_id
+            .and_then(|id| game.players.iter().find(|p| p.id == id).cloned());
+
+        game
+    })
+    .collect::<Vec<_>>();
+
+    Ok((games, users))
+}
+
+async fn save_game(
+    db: &SqlitePool,
+    game: &Game,
+    deck: Option<&GameDeck>,
+    users: &[User],
+) -> Result<(), sqlx::Error> {
+    let mut tx = db.begin().await?;
+
+    sqlx::query(
+        r#"
+INSERT INTO games (
+    id,
+    state,
+    mode,
+    hit_duration,
+    start_tokens,
+    goal,
+    last_scored_id,
+    victory_condition,
+    rounds,
+    time_limit,
+    tie_breakers,
+    turns,
+    rounds_played,
+    started_at,
+    name_hits,
+    name_tolerance,
+    name_guess_title,
+    name_guess_artist,
+    name_guess_judgement,
+    teams,
+    guess_timeout,
+    intercept_timeout,
+    confirm_timeout,
+    deadline,
+    last_modified) VALUES (
+    ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+ON CONFLICT (id) DO UPDATE SET
+    state = excluded.state,
+    mode = excluded.mode,
+    hit_duration = excluded.hit_duration,
+    start_tokens = excluded.start_tokens,
+    goal = excluded.goal,
+    last_scored_id = excluded.last_scored_id,
+    victory_condition = excluded.victory_condition,
+    rounds = excluded.rounds,
+    time_limit = excluded.time_limit,
+    tie_breakers = excluded.tie_breakers,
+    turns = excluded.turns,
+    rounds_played = excluded.rounds_played,
+    started_at = excluded.started_at,
+    name_hits = excluded.name_hits,
+    name_tolerance = excluded.name_tolerance,
+    name_guess_title = excluded.name_guess_title,
+    name_guess_artist = excluded.name_guess_artist,
+    name_guess_judgement = excluded.name_guess_judgement,
+    teams = excluded.teams,
+    guess_timeout = excluded.guess_timeout,
+    intercept_timeout = excluded.intercept_timeout,
+    confirm_timeout = excluded.confirm_timeout,
+    deadline = excluded.deadline,
+    last_modified = excluded.last_modified"#,
+    )
+    .bind(&game.id)
+    .bind(<&str>::from(game.state))
+    .bind(<&str>::from(game.mode))
+    .bind(game.hit_duration)
+    .bind(game.start_tokens)
+    .bind(game.goal)
+    .bind(game.last_scored.as_ref().map(|p| p.id))
+    .bind(<&str>::from(game.victory_condition))
+    .bind(game.rounds)
+    .bind(game.time_limit)
+    .bind(serde_json::to_string(&game.tie_breakers).unwrap())
+    .bind(game.turns)
+    .bind(game.rounds_played)
+    .bind(game.started_at)
+    .bind(game.name_hits)
+    .bind(game.name_tolerance)
+    .bind(game.name_guess.as_ref().map(|n| &n.title))
+    .bind(game.name_guess.as_ref().map(|n| &n.artist))
+    .bind(
+        game.name_guess
+            .as_ref()
+            .and_then(|n| n.judgement)
+            .map(<&str>::from),
+    )
+    .bind(game.teams)
+    .bind(game.guess_timeout)
+    .bind(game.intercept_timeout)
+    .bind(game.confirm_timeout)
+    .bind(game.deadline)
+    .bind(OffsetDateTime::now_utc())
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query("DELETE FROM games_packs WHERE game_id = ?")
+        .bind(&game.id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("DELETE FROM games_players WHERE game_id = ?")
+        .bind(&game.id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("DELETE FROM games_players_members WHERE game_id = ?")
+        .bind(&game.id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("DELETE FROM games_spectators WHERE game_id = ?")
+        .bind(&game.id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("DELETE FROM games_banned_users WHERE game_id = ?")
+        .bind(&game.id)
+        .execute(&mut *tx)
+        .await?;
+    // the deck stays as it is if it didn't change
+    if deck.is_some() {
+        sqlx::query("DELETE FROM games_hits WHERE game_id = ?")
+            .bind(&game.id)
+            .execute(&mut *tx)
+            .await?;
+    } else {
+        sqlx::query("DELETE FROM games_hits WHERE game_id = ? AND location IN (?, ?)")
+            .bind(&game.id)
+            .bind("player")
+            .bind("revealed")
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    for pack in game.packs.iter() {
+        sqlx::query("INSERT INTO games_packs (game_id, pack_id) VALUES (?, ?)")
+            .bind(&game.id)
+            .bind(pack)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    for (position, player) in game.players.iter().enumerate() {
+        sqlx::query(
+            r#"
+INSERT INTO games_players (
+    game_id,
+    id,
+    position,
+    name,
+    state,
+    creator,
+    tokens,
+    turn_player,
+    guess_id,
+    guess_from_year,
+    guess_to_year,
+    virtual,
+    wrong_guesses) VALUES (
+    ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"#,
+        )
+        .bind(&game.id)
+        .bind(player.id)
+        .bind(position as u32)
+        .bind(&player.name)
+        .bind(<&str>::from(player.state))
+        .bind(player.creator)
+        .bind(player.tokens)
+        .bind(player.turn_player)
+        .bind(player.guess.as_ref().map(|s| s.id))
+        .bind(player.guess.as_ref().map(|s| s.from_year))
+        .bind(player.guess.as_ref().map(|s| s.to_year))
+        .bind(player.r#virtual)
+        .bind(player.wrong_guesses)
+        .execute(&mut *tx)
+        .await?;
+
+        for (position, member) in player.members.iter().enumerate() {
+            sqlx::query(
+                r#"
+INSERT INTO games_players_members (
+    game_id,
+    player_id,
+    id,
+    position,
+    name,
+    virtual,
+    creator) VALUES (
+    ?, ?, ?, ?, ?, ?, ?)"#,
+            )
+            .bind(&game.id)
+            .bind(player.id)
+            .bind(member.id)
+            .bind(position as u32)
+            .bind(&member.name)
+            .bind(member.r#virtual)
+            .bind(member.creator)
+            .execute(&mut *tx)
+            .await?;
+        }
+    }
+
+    for (position, spectator) in game.spectators.iter().enumerate() {
+        sqlx::query(
+            "INSERT INTO games_spectators (game_id, id, position, name) VALUES (?, ?, ?, ?)",
+        )
+        .bind(&game.id)
+        .bind(spectator.id)
+        .bind(position as u32)
+        .bind(&spectator.name)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    for (position, user) in game.banned.iter().enumerate() {
+        sqlx::query(
+            "INSERT INTO games_banned_users (game_id, id, position, name) VALUES (?, ?, ?, ?)",
+        )
+        .bind(&game.id)
+        .bind(user.id)
+        .bind(position as u32)
+        .bind(&user.name)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    let hits = deck
+        .iter()
+        .flat_map(|d| {
+            d.remaining
+                .iter()
+                .enumerate()
+                .map(|(i, h)| ("remaining", None, i, *h))
+                .chain(
+                    d.remembered
+                        .iter()
+                        .enumerate()
+                        .map(|(i, h)| ("remembered", None, i, *h)),
+                )
+        })
+        .chain(game.players.iter().flat_map(|p| {
+            p.hits
+                .iter()
+                .enumerate()
+                .map(|(i, h)| ("player", Some(p.id), i, h.id))
+        }))
+        .chain(game.hit.iter().map(|h| ("revealed", None, 0, h.id)))
+        .collect::<Vec<_>>();
+
+    // sqlite limits the amount of bound parameters per statement
+    for chunk in hits.chunks(150) {
+        let mut qb = QueryBuilder::<Sqlite>::new(
+            "INSERT INTO games_hits (game_id, hit_id, location, player_id, position) ",
+        );
+        qb.push_values(chunk, |mut b, (location, player_id, position, hit_id)| {
+            b.push_bind(&game.id)
+                .push_bind(*hit_id)
+                .push_bind(*location)
+                .push_bind(*player_id)
+                .push_bind(*position as u32);
+        });
+        qb.build().execute(&mut *tx).await?;
+    }
+
+    for user in users.iter() {
+        sqlx::query(
+            r#"
+INSERT INTO virtual_users (id, name, tokens) VALUES (?, ?, ?)
+ON CONFLICT (id) DO UPDATE SET
+    name = excluded.name,
+    tokens = excluded.tokens"#,
+        )
+        .bind(user.id)
+        .bind(&user.name)
+        .bind(serde_json::to_string(&user.tokens).unwrap())
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await
+}
+
+async fn remove_game(db: &SqlitePool, game_id: &str) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM games WHERE id = ?")
+        .bind(game_id)
+        .execute(db)
+        .await
+        .map(|_| ())
+}
+
+#[derive(Default)]
+pub struct GamePersistenceService {}
+
+#[rocket::async_trait]
+impl Fairing for GamePersistenceService {
+    fn info(&self) -> Info {
+        Info {
+            name: "Persist games to the database",
+            kind: Kind::Ignite | Kind::Liftoff,
         }
     }
+
+    async fn on_ignite(&self, rocket: Rocket<Build>) -> fairing::Result {
+        let db = HitsterConfig::fetch(&rocket).unwrap();
+        let svc = rocket.state::<ServiceStore>().unwrap();
+
+        match load_games(&db.0).await {
+            Ok((games, users)) => {
+                rocket::info!("Restoring {} games from db", games.len());
+
+                for user in users.into_iter() {
+                    svc.user_service().lock().add(user);
+                }
+
+                let gs = svc.game_service();
+                let gsl = gs.lock();
+
+                for game in games.into_iter() {
+                    gsl.restore(game);
+                }
+            }
+            Err(e) => {
+                rocket::warn!("Failed to restore games from db: {}", e);
+            }
+        }
+
+        Ok(rocket)
+    }
+
+    async fn on_liftoff(&self, rocket: &Rocket<Orbit>) {
+        let db = HitsterConfig::fetch(rocket).unwrap().0.clone();
+        let svc = rocket.state::<ServiceStore>().unwrap();
+        let game_service = svc.game_service();
+        let user_service = svc.user_service();
+        let (sender, mut rx) = unbounded_channel::<GameUpdate>();
+        // unlike games, the history can't be collapsed into its latest state, so it gets written on its own
+        let (history_sender, mut history_rx) = unbounded_channel::<GameHistoryEntry>();
+
+        game_service.lock().set_persistence_sender(sender);
+        game_service.lock().set_history_sender(history_sender);
+        game_service
+            .lock()
+            .set_event_queue(rocket.state::<GameEventQueue>().unwrap().clone());
+
+        let history_db = db.clone();
+
+        rocket::tokio::spawn(async move {
+            let mut entries = Vec::<GameHistoryEntry>::new();
+
+            // entries which piled up in the meantime get written at once
+            while history_rx.recv_many(&mut entries, 1024).await > 0 {
+                if let Err(e) = save_history(&history_db, &entries).await {
+                    rocket::warn!("Failed to persist game history: {}", e);
+                }
+
+                entries.clear();
+            }
+        });
+
+        rocket::tokio::spawn(async move {
+            while let Some(update) = rx.recv().await {
+                let mut pending = HashMap::<String, Option<(Game, Option<GameDeck>)>>::new();
+
+                // collect everything that piled up in the meantime
+                // so that only the latest state of each game gets written
+                let mut next = Some(update);
+
+                while let Some(update) = next {
+                    match update {
+                        GameUpdate::Save(game, deck) => {
+                            // a deck which changed earlier on still needs to be written
+                            let deck = deck.or_else(|| match pending.remove(&game.id) {
+                                Some(Some((_, deck))) => deck,
+                                _ => None,
+                            });
+                            pending.insert(game.id.clone(), Some((*game, deck)));
+                        }
+                        GameUpdate::Remove(game_id) => {
+                            pending.insert(game_id, None);
+                        }
+                    }
+
+                    next = rx.try_recv().ok();
+                }
+
+                for (game_id, game) in pending.into_iter() {
+                    let res = if let Some((game, deck)) = game {
+                        let users = {
+                            let usl = user_service.lock();
+                            game.players
+                                .iter()
+                                .filter(|p| !p.r#virtual)
+                                .map(|p| p.id)
+                                .chain(
+                                    game.players
+                                        .iter()
+                                        .flat_map(|p| &p.members)
+                                        .filter(|m| !m.r#virtual)
+                                        .map(|m| m.id),
+                                )
+                                .chain(game.spectators.iter().map(|s| s.id))
+                                .filter_map(|id| usl.get_by_id(id))
+                                .filter(|u| u.r#virtual)
+                                .collect::<Vec<_>>()
+                        };
+                        save_game(&db, &game, deck.as_ref(), &users).await
+                    } else {
+                        remove_game(&db, &game_id).await
+                    };
+
+                    if let Err(e) = res {
+                        rocket::warn!("Failed to persist game {}: {}", game_id, e);
+                        game_service.lock().forget_deck(&game_id);
+                    }
+                }
+
+                let _ = sqlx::query(
+                    "DELETE FROM virtual_users WHERE id NOT IN (SELECT id FROM games_players UNION SELECT id FROM games_players_members UNION SELECT id FROM games_spectators)",
+                )
+                .execute(&db)
+                .await;
+            }
+        });
+    }
+}
+
+/// stops the game and announces the winners if the game is over
+pub fn finish_game(game: &Game, game_service: &ServiceHandle<GameService>, queue: &GameEventQueue) {
+    let winners = game_service.lock().get_winners(game);
+
+    if winners.is_empty() {
+        return;
+    }
+
+    if let Ok(game) = game_service.lock().stop(&game.id, None) {
+        let _ = queue.send(GameEvent {
+            game_id: game.id.clone(),
+            event: "change_state".into(),
+            state: Some(game.state),
+            players: Some(game.players.iter().map(|p| p.into()).collect::<Vec<_>>()),
+            winner: if winners.len() == 1 {
+                winners.first().map(|p| p.into())
+            } else {
+                None
+            },
+            winners: Some(winners.iter().map(|p| p.into()).collect::<Vec<_>>()),
+            ..Default::default()
+        });
+    }
+}
+
+#[derive(Default)]
+pub struct GameTimerService {}
+
+#[rocket::async_trait]
+impl Fairing for GameTimerService {
+    fn info(&self) -> Info {
+        Info {
+            name: "Enforce turn timeouts",
+            kind: Kind::Liftoff,
+        }
+    }
+
+    async fn on_liftoff(&self, rocket: &Rocket<Orbit>) {
+        let game_service = rocket.state::<ServiceStore>().unwrap().game_service();
+        let queue = rocket.state::<GameEventQueue>().unwrap().clone();
+
+        rocket::tokio::spawn(async move {
+            let mut ticks = interval(Duration::from_secs(1));
+
+            loop {
+                ticks.tick().await;
+
+                let expired = game_service.lock().expire();
+
+                for (game, players) in expired.into_iter() {
+                    let _ = queue.send(GameEvent {
+                        game_id: game.id.clone(),
+                        event: "timeout".into(),
+                        players: Some(players.iter().map(|p| p.into()).collect::<Vec<_>>()),
+                        ..Default::default()
+                    });
+
+                    let _ = queue.send(GameEvent {
+                        game_id: game.id.clone(),
+                        event: "change_state".into(),
+                        state: Some(game.state),
+                        players: Some(game.players.iter().map(|p| p.into()).collect::<Vec<_>>()),
+                        hit: game
+                            .hit
+                            .as_ref()
+                            .filter(|_| game.state == GameState::Confirming)
+                            .map(|h| h.into()),
+                        last_scored: game.last_scored.as_ref().map(|p| p.into()),
+                        name_guess: game
+                            .name_guess
+                            .clone()
+                            .filter(|_| game.state == GameState::Confirming),
+                        remaining_time: game.remaining_time(),
+                        ..Default::default()
+                    });
+
+                    finish_game(&game, &game_service, &queue);
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(game_id: &str) -> GameEvent {
+        GameEvent {
+            game_id: game_id.into(),
+            event: "test".into(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn event_replay_returns_missed_events() {
+        let queue = GameEventQueue::default();
+
+        for _ in 0..5 {
+            queue.send(event("a"));
+        }
+        queue.send(event("b"));
+
+        assert_eq!(queue.last_id("a"), 5);
+        assert_eq!(queue.last_id("b"), 1);
+        assert_eq!(
+            queue
+                .replay("a", 2)
+                .unwrap()
+                .iter()
+                .map(|e| e.id)
+                .collect::<Vec<_>>(),
+            vec![3, 4, 5]
+        );
+        assert!(queue.replay("a", 5).unwrap().is_empty());
+        // ids from the future or of unknown games can't be replayed
+        assert!(queue.replay("a", 6).is_none());
+        assert!(queue.replay("c", 0).is_none());
+    }
+
+    #[test]
+    fn event_replay_fails_once_events_got_dropped() {
+        let queue = GameEventQueue::default();
+
+        for _ in 0..GAME_EVENT_LOG_SIZE + 10 {
+            queue.send(event("a"));
+        }
+
+        assert!(queue.replay("a", 0).is_none());
+        assert!(queue.replay("a", 9).is_none());
+        assert_eq!(queue.replay("a", 10).unwrap().len(), GAME_EVENT_LOG_SIZE);
+    }
+
+    #[test]
+    fn event_ids_keep_increasing_after_removal() {
+        let queue = GameEventQueue::default();
+
+        queue.send(event("a"));
+        queue.send(event("a"));
+        queue.remove("a");
+        queue.send(event("a"));
+
+        assert_eq!(queue.last_id("a"), 3);
+    }
 }
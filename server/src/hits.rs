@@ -1,32 +1,48 @@
-use crate::{GlobalEvent, HitsterConfig, services::ServiceStore};
+use crate::{
+    GlobalEvent, HitsterConfig,
+    games::PackPayload,
+    services::{GameService, HitService, ServiceHandle, ServiceStore},
+    transcoding,
+};
 use async_process::Command;
-use hitster_core::{Hit, HitId, HitIssue, HitIssueType, HitsterData, Pack};
+use hitster_core::{
+    AudioSource, Hit, HitId, HitIssue, HitIssueState, HitIssueType, HitsterData, Pack,
+};
 use rocket::{
-    Orbit, Rocket,
+    Data, Orbit, Request, Rocket,
+    data::{self, FromData, ToByteUnit},
     fairing::{Fairing, Info, Kind},
+    http::Status,
     tokio::{
+        fs::File,
+        io::AsyncWriteExt,
         select,
         sync::{
-            Mutex, Semaphore,
+            Mutex, Notify, Semaphore,
             broadcast::{Sender, channel, error::RecvError},
+            mpsc::{UnboundedSender, unbounded_channel},
         },
+        time::sleep,
     },
 };
-use rocket_db_pools::Database;
-use rocket_okapi::okapi::schemars::JsonSchema;
+use rocket_db_pools::{Database, sqlx::SqlitePool};
+use rocket_okapi::{
+    r#gen::OpenApiGenerator,
+    okapi::{openapi3::RequestBody, schemars::JsonSchema},
+    request::OpenApiFromData,
+};
 use serde::{Deserialize, Deserializer, Serialize};
-use sqlx::FromRow;
+use sqlx::{Executor, FromRow, Sqlite};
 use std::{
     collections::{HashMap, HashSet},
     convert::From,
     env,
     fs::{create_dir_all, read_dir, remove_file},
+    io,
     path::{Path, PathBuf},
-    sync::{Arc, OnceLock},
+    sync::Arc,
 };
-#[cfg(feature = "yt_dl")]
-use time::Duration;
-use time::OffsetDateTime;
+use time::{Duration, OffsetDateTime};
 use uuid::Uuid;
 
 #[derive(FromRow)]
@@ -42,9 +58,13 @@ struct HitRow {
     title: String,
     artist: String,
     yt_id: String,
+    source: String,
+    location: String,
     belongs_to: String,
     year: u32,
     playback_offset: u16,
+    playback_length: Option<u16>,
+    snippets: String,
     last_modified: OffsetDateTime,
     downloaded: bool,
 }
@@ -108,13 +128,26 @@ pub struct FullHitPayload {
     pub year: u32,
     /// the packs the song lives in
     pub packs: Vec<Uuid>,
-    /// the YouTube video time offset at which the song starts playing
+    /// the time offset within the audio at which the song starts playing
     pub playback_offset: u16,
+    /// how many seconds of the song get played, until the end of the audio if not set
+    #[serde(default)]
+    pub playback_length: Option<u16>,
+    /// alternative playback offsets, games rotate between them and the playback_offset
+    #[serde(default)]
+    pub snippets: Vec<u16>,
     /// the unique hit id
     #[serde(skip_serializing_if = "Option::is_none")]
     pub id: Option<Uuid>,
-    /// the YouTube video ID
+    /// the YouTube video ID, empty for other sources
+    #[serde(default)]
     pub yt_id: String,
+    /// where the audio of the hit gets fetched from, YouTube by default
+    #[serde(default)]
+    pub source: AudioSource,
+    /// the file path or URL of the audio, empty for YouTube hits
+    #[serde(default)]
+    pub location: String,
     /// whether the hit has been downloaded
     #[serde(skip_serializing_if = "Option::is_none")]
     pub downloaded: Option<bool>,
@@ -133,7 +166,11 @@ impl From<&Hit> for FullHitPayload {
             packs: hit.packs.clone(),
             id: Some(hit.id),
             yt_id: hit.yt_id.clone(),
+            source: hit.source,
+            location: hit.location.clone(),
             playback_offset: hit.playback_offset,
+            playback_length: hit.playback_length,
+            snippets: hit.snippets.clone(),
             downloaded: None,
             issues: None,
         }
@@ -149,11 +186,195 @@ pub enum HitQueryPart {
     Downloaded,
 }
 
-pub fn get_hitster_data() -> &'static HitsterData {
-    static DATA: OnceLock<HitsterData> = OnceLock::new();
-    DATA.get_or_init(|| {
-        serde_yml::from_str::<HitsterData>(include_str!("../../etc/hits.yml")).unwrap()
-    })
+/// the hits and packs bundled with the server
+const BUNDLED_HITS: &str = include_str!("../../etc/hits.yml");
+
+/// the contents of a hits file as written by the export, before merging them into a HitsterData
+#[derive(Deserialize)]
+struct HitsFile {
+    hits: Vec<Hit>,
+    packs: Vec<Pack>,
+}
+
+/// the sources listed in HITS_SOURCES, separated like the PATH variable.
+/// Every source is either a YAML file, a directory containing YAML files or bundled for the hits bundled with the server
+pub fn hits_sources() -> Vec<PathBuf> {
+    env::var_os("HITS_SOURCES")
+        .map(|s| env::split_paths(&s).collect::<Vec<_>>())
+        .unwrap_or_else(|| vec![PathBuf::from("bundled")])
+}
+
+/// the reference dataset to verify the years of hits against, if configured
+pub fn year_reference() -> Option<PathBuf> {
+    env::var_os("YEAR_REFERENCE")
+        .filter(|p| !p.is_empty())
+        .map(PathBuf::from)
+}
+
+/// how many years the hits may differ from the year reference, configured via YEAR_TOLERANCE
+pub fn year_tolerance() -> u32 {
+    env::var("YEAR_TOLERANCE")
+        .ok()
+        .and_then(|t| t.parse::<u32>().ok())
+        .unwrap_or(0)
+}
+
+/// read the contents of all files within the hits sources
+fn read_hits_sources() -> (Vec<(String, String)>, Vec<String>) {
+    let sources = hits_sources();
+    let mut files = vec![];
+    let mut errors = vec![];
+
+    for source in sources.into_iter() {
+        if source.as_os_str() == "bundled" {
+            files.push(("bundled hits".to_string(), BUNDLED_HITS.to_string()));
+            continue;
+        }
+
+        let paths = if source.is_dir() {
+            match read_dir(&source) {
+                Ok(entries) => {
+                    let mut paths = entries
+                        .flatten()
+                        .map(|e| e.path())
+                        .filter(|p| {
+                            p.is_file()
+                                && matches!(
+                                    p.extension().and_then(|e| e.to_str()),
+                                    Some("yml" | "yaml")
+                                )
+                        })
+                        .collect::<Vec<_>>();
+                    paths.sort();
+                    paths
+                }
+                Err(e) => {
+                    errors.push(format!("{}: {}", source.display(), e));
+                    continue;
+                }
+            }
+        } else {
+            vec![source]
+        };
+
+        for path in paths.into_iter() {
+            match std::fs::read_to_string(&path) {
+                Ok(content) => files.push((path.display().to_string(), content)),
+                Err(e) => errors.push(format!("{}: {}", path.display(), e)),
+            }
+        }
+    }
+
+    (files, errors)
+}
+
+/// load and merge the static hits and packs from all configured sources.
+/// All problems found within the sources get returned, each naming the source and entry at fault
+pub fn load_hitster_data() -> Result<HitsterData, Vec<String>> {
+    let (files, mut errors) = read_hits_sources();
+    let mut packs = HashMap::<Uuid, (Pack, String)>::new();
+    let mut hits = HashMap::<Uuid, (Hit, String)>::new();
+    let mut source_ids = HashMap::<HitId, Uuid>::new();
+
+    for (source, content) in files.into_iter() {
+        let file = match serde_yml::from_str::<HitsFile>(&content) {
+            Ok(file) => file,
+            Err(e) => {
+                errors.push(format!("{source}: {e}"));
+                continue;
+            }
+        };
+
+        for pack in file.packs.into_iter() {
+            match packs.get(&pack.id) {
+                Some((p, s)) if p.name != pack.name => errors.push(format!(
+                    "{}: pack {} ({}) is already defined as {} in {}",
+                    source, pack.name, pack.id, p.name, s
+                )),
+                Some(_) => {}
+                None => {
+                    packs.insert(pack.id, (pack, source.clone()));
+                }
+            }
+        }
+
+        for hit in file.hits.into_iter() {
+            let entry = format!("hit {}: {} ({})", hit.artist, hit.title, hit.id);
+
+            if hit.artist.is_empty() || hit.title.is_empty() {
+                errors.push(format!("{source}: {entry} is missing an artist or title"));
+            }
+
+            if hit.year == 0 {
+                errors.push(format!("{source}: {entry} is missing a year"));
+            }
+
+            if hit.source == AudioSource::File && hit.source_file().is_none() {
+                errors.push(format!(
+                    "{source}: {entry} refers to a file outside of the library directory"
+                ));
+            }
+
+            if let Some(id) = source_ids.get(&hit.source_id())
+                && *id != hit.id
+            {
+                let (h, s) = hits.get(id).unwrap();
+                errors.push(format!(
+                    "{}: {} uses the same YouTube ID, file or URL as hit {}: {} ({}) in {}",
+                    source, entry, h.artist, h.title, h.id, s
+                ));
+                continue;
+            }
+
+            match hits.get_mut(&hit.id) {
+                // the same hit may be listed in multiple sources to add it to more packs
+                Some((h, _))
+                    if h.artist == hit.artist
+                        && h.title == hit.title
+                        && h.year == hit.year
+                        && h.belongs_to == hit.belongs_to
+                        && h.yt_id == hit.yt_id
+                        && h.source == hit.source
+                        && h.location == hit.location
+                        && h.playback_offset == hit.playback_offset
+                        && h.playback_length == hit.playback_length
+                        && h.snippets == hit.snippets =>
+                {
+                    for pack in hit.packs.into_iter() {
+                        if !h.packs.contains(&pack) {
+                            h.packs.push(pack);
+                        }
+                    }
+                    h.last_modified = h.last_modified.max(hit.last_modified);
+                }
+                Some((_, s)) => errors.push(format!(
+                    "{source}: {entry} is already defined differently in {s}"
+                )),
+                None => {
+                    source_ids.insert(hit.source_id(), hit.id);
+                    hits.insert(hit.id, (hit, source.clone()));
+                }
+            }
+        }
+    }
+
+    for (hit, source) in hits.values() {
+        for pack in hit.packs.iter().filter(|p| !packs.contains_key(p)) {
+            errors.push(format!(
+                "{}: hit {}: {} ({}) belongs to pack {} which isn't defined in any source",
+                source, hit.artist, hit.title, hit.id, pack
+            ));
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(HitsterData::new(
+            hits.into_values().map(|(h, _)| h).collect::<Vec<_>>(),
+            packs.into_values().map(|(p, _)| p).collect::<Vec<_>>(),
+        ))
+    } else {
+        Err(errors)
+    }
 }
 
 #[derive(Copy, Clone, Deserialize, Eq, JsonSchema, PartialEq, FromFormField)]
@@ -278,6 +499,722 @@ pub struct HitPartsQuery {
     pub parts: Option<Vec<HitQueryPart>>,
 }
 
+/// the formats hits can be imported from
+
+#[derive(Copy, Clone, Default, Deserialize, Eq, JsonSchema, PartialEq, FromFormField, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportFormat {
+    /// the YAML format the export and etc/hits.yml use
+    #[default]
+    #[field(value = "yaml")]
+    Yaml,
+    /// the same structure as the YAML format, just in JSON
+    #[field(value = "json")]
+    Json,
+    /// one hit per row with a header row naming the columns artist, title, year and optionally id, belongs_to, yt_id, source, location, playback_offset, playback_length, snippets (offsets separated by |) and packs (pack names separated by |)
+    #[field(value = "csv")]
+    Csv,
+}
+
+#[derive(Deserialize, JsonSchema, FromForm)]
+pub struct ImportHitsQuery {
+    /// the format of the uploaded data (default yaml)
+    pub format: Option<ImportFormat>,
+    /// only compare the uploaded hits with the ones on this server, without changing anything
+    pub dry_run: Option<bool>,
+}
+
+/// the raw data uploaded for an import, limited by the hits_import limit (32 MiB by default)
+pub struct ImportData(pub String);
+
+#[rocket::async_trait]
+impl<'r> FromData<'r> for ImportData {
+    type Error = io::Error;
+
+    async fn from_data(req: &'r Request<'_>, data: Data<'r>) -> data::Outcome<'r, Self> {
+        let limit = req.limits().get("hits_import").unwrap_or(32.mebibytes());
+
+        match data.open(limit).into_string().await {
+            Ok(s) if s.is_complete() => data::Outcome::Success(ImportData(s.into_inner())),
+            Ok(_) => data::Outcome::Error((
+                Status::PayloadTooLarge,
+                io::Error::other("import data too large"),
+            )),
+            Err(e) => data::Outcome::Error((Status::BadRequest, e)),
+        }
+    }
+}
+
+impl<'r> OpenApiFromData<'r> for ImportData {
+    fn request_body(generator: &mut OpenApiGenerator) -> rocket_okapi::Result<RequestBody> {
+        String::request_body(generator)
+    }
+}
+
+#[derive(Deserialize)]
+struct ImportCsvRow {
+    #[serde(default)]
+    id: Option<Uuid>,
+    artist: String,
+    title: String,
+    year: u32,
+    #[serde(default)]
+    belongs_to: Option<String>,
+    #[serde(default)]
+    yt_id: String,
+    #[serde(default)]
+    source: Option<AudioSource>,
+    #[serde(default)]
+    location: String,
+    #[serde(default)]
+    playback_offset: Option<u16>,
+    #[serde(default)]
+    playback_length: Option<u16>,
+    #[serde(default)]
+    snippets: Option<String>,
+    #[serde(default)]
+    packs: Option<String>,
+}
+
+/// a hit which couldn't be imported
+#[derive(Serialize, JsonSchema)]
+pub struct ImportConflictPayload {
+    /// the hit as found within the import
+    pub hit: FullHitPayload,
+    /// why the hit couldn't be imported
+    pub message: String,
+}
+
+/// the differences between an import and the hits on this server
+
+#[derive(Serialize, JsonSchema)]
+pub struct ImportHitsPayload {
+    /// whether the changes got written, false for dry runs
+    pub applied: bool,
+    /// packs which don't exist on this server yet
+    pub new_packs: Vec<PackPayload>,
+    /// existing packs which got renamed
+    pub changed_packs: Vec<PackPayload>,
+    /// hits which don't exist on this server yet
+    pub new_hits: Vec<FullHitPayload>,
+    /// existing hits with changed info
+    pub changed_hits: Vec<FullHitPayload>,
+    /// the amount of hits which are identical to the ones on this server
+    pub unchanged_hits: usize,
+    /// hits which can't be imported, nothing is applied as long as there are any
+    pub conflicts: Vec<ImportConflictPayload>,
+}
+
+/// a hit which got changed within both the codebase and the database since the last merge
+
+#[derive(Serialize, JsonSchema)]
+pub struct HitConflictPayload {
+    /// the hit as it is currently stored within the database
+    pub database: FullHitPayload,
+    /// the hit as it is defined within the codebase
+    pub codebase: FullHitPayload,
+    /// when the conflict was found
+    #[serde(with = "time::serde::rfc3339")]
+    #[schemars(with = "String")]
+    pub created_at: OffsetDateTime,
+}
+
+/// which version of a conflicting hit to keep
+#[derive(Deserialize, JsonSchema, Clone, Copy, Eq, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum HitConflictSide {
+    /// keep the changes made within the database
+    Database,
+    /// overwrite the hit with the version from the codebase
+    Codebase,
+}
+
+/// information necessary to resolve a conflict
+
+#[derive(Deserialize, JsonSchema, Clone, Eq, PartialEq, Debug)]
+pub struct ResolveHitConflictPayload {
+    /// the version to keep
+    pub keep: HitConflictSide,
+}
+
+/// what happened to a hit or pack within its history
+#[derive(Serialize, JsonSchema, Clone, Copy, Eq, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum HistoryAction {
+    /// the state before the first recorded change
+    Initial,
+    /// created via the API
+    Create,
+    /// changed via the API
+    Update,
+    /// deleted via the API
+    Delete,
+    /// created or changed by an import
+    Import,
+    /// restored to a previous version
+    Revert,
+}
+
+impl From<String> for HistoryAction {
+    fn from(value: String) -> Self {
+        match value.as_str() {
+            "initial" => HistoryAction::Initial,
+            "create" => HistoryAction::Create,
+            "update" => HistoryAction::Update,
+            "delete" => HistoryAction::Delete,
+            "import" => HistoryAction::Import,
+            "revert" => HistoryAction::Revert,
+            _ => panic!("invalid history action: {value}"),
+        }
+    }
+}
+
+impl From<HistoryAction> for &'static str {
+    fn from(value: HistoryAction) -> Self {
+        match value {
+            HistoryAction::Initial => "initial",
+            HistoryAction::Create => "create",
+            HistoryAction::Update => "update",
+            HistoryAction::Delete => "delete",
+            HistoryAction::Import => "import",
+            HistoryAction::Revert => "revert",
+        }
+    }
+}
+
+/// a version of a hit within its history
+
+#[derive(Serialize, JsonSchema)]
+pub struct HitHistoryPayload {
+    /// the version of the hit, counting up from 1
+    pub version: u32,
+    /// what happened to the hit
+    pub action: HistoryAction,
+    /// the user who made the change, not known for the initial version
+    pub user_id: Option<Uuid>,
+    /// the name of the user at the time of the change
+    pub user_name: Option<String>,
+    /// the hit after the change, or right before it got deleted
+    pub hit: FullHitPayload,
+    /// when the change was made
+    #[serde(with = "time::serde::rfc3339")]
+    #[schemars(with = "String")]
+    pub created_at: OffsetDateTime,
+}
+
+/// a version of a pack within its history
+
+#[derive(Serialize, JsonSchema)]
+pub struct PackHistoryPayload {
+    /// the version of the pack, counting up from 1
+    pub version: u32,
+    /// what happened to the pack
+    pub action: HistoryAction,
+    /// the user who made the change, not known for the initial version
+    pub user_id: Option<Uuid>,
+    /// the name of the user at the time of the change
+    pub user_name: Option<String>,
+    /// the name of the pack after the change, or right before it got deleted
+    pub name: String,
+    /// when the change was made
+    #[serde(with = "time::serde::rfc3339")]
+    #[schemars(with = "String")]
+    pub created_at: OffsetDateTime,
+}
+
+/// two hits which likely are the same song
+
+#[derive(Serialize, JsonSchema)]
+pub struct HitDuplicatePayload {
+    /// the hit released earlier
+    pub hit: FullHitPayload,
+    /// the hit which likely is a duplicate of it
+    pub duplicate: FullHitPayload,
+}
+
+/// information necessary to merge a duplicate into a hit
+
+#[derive(Deserialize, JsonSchema, Clone, Eq, PartialEq, Debug)]
+pub struct MergeHitPayload {
+    /// the hit to merge, it gets deleted afterwards
+    pub duplicate: Uuid,
+}
+
+/// optional filters for listing hit issues.
+/// Issues need to match one of the given states and one of the given types, if any are given
+
+#[derive(Copy, Clone, Deserialize, Eq, JsonSchema, PartialEq, FromFormField, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum HitIssueFilter {
+    #[field(value = "open")]
+    Open,
+    #[field(value = "in_progress")]
+    InProgress,
+    #[field(value = "resolved")]
+    Resolved,
+    #[field(value = "wont_fix")]
+    WontFix,
+    #[field(value = "auto")]
+    Auto,
+    #[field(value = "custom")]
+    Custom,
+    #[field(value = "unassigned")]
+    Unassigned,
+}
+
+impl HitIssueFilter {
+    pub fn state(&self) -> Option<HitIssueState> {
+        match self {
+            Self::Open => Some(HitIssueState::Open),
+            Self::InProgress => Some(HitIssueState::InProgress),
+            Self::Resolved => Some(HitIssueState::Resolved),
+            Self::WontFix => Some(HitIssueState::WontFix),
+            _ => None,
+        }
+    }
+
+    pub fn issue_type(&self) -> Option<&'static str> {
+        match self {
+            Self::Auto => Some("auto"),
+            Self::Custom => Some("custom"),
+            _ => None,
+        }
+    }
+}
+
+/// a query for listing the issues of all hits
+
+#[derive(Deserialize, JsonSchema, FromForm)]
+pub struct HitIssuesQuery {
+    /// optional filters to apply before pagination
+    pub filters: Option<Vec<HitIssueFilter>>,
+    /// only list issues assigned to this user
+    pub assignee: Option<Uuid>,
+    /// the start of the pagination (default 1)
+    pub start: Option<usize>,
+    /// amount of issues you want to get (default 50)
+    pub amount: Option<usize>,
+}
+
+/// an issue alongside the hit it was reported for
+
+#[derive(Clone, Serialize, JsonSchema)]
+pub struct HitIssuePayload {
+    pub issue: HitIssue,
+    pub hit: HitPayload,
+}
+
+/// a comment written on a hit issue
+
+#[derive(Clone, Serialize, JsonSchema, FromRow, Debug, Eq, PartialEq)]
+pub struct HitIssueComment {
+    pub id: Uuid,
+    pub issue_id: Uuid,
+    /// the user who wrote the comment
+    pub user_id: Uuid,
+    /// the name of the user at the time the comment was written
+    pub user_name: String,
+    pub message: String,
+    #[serde(with = "time::serde::rfc3339")]
+    #[schemars(with = "String")]
+    pub created_at: OffsetDateTime,
+}
+
+/// the state of a hit within the download queue
+
+#[derive(Copy, Clone, Deserialize, Serialize, Eq, JsonSchema, PartialEq, FromFormField, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum HitDownloadStatus {
+    /// waiting to be downloaded, possibly until the back-off is over
+    #[field(value = "queued")]
+    Queued,
+    #[field(value = "downloading")]
+    Downloading,
+    /// the download succeeded and the audio gets transcoded
+    #[field(value = "processing")]
+    Processing,
+    /// all attempts to download the hit failed
+    #[field(value = "failed")]
+    Failed,
+    #[field(value = "cancelled")]
+    Cancelled,
+}
+
+impl From<String> for HitDownloadStatus {
+    fn from(value: String) -> Self {
+        match value.as_str() {
+            "queued" => HitDownloadStatus::Queued,
+            "downloading" => HitDownloadStatus::Downloading,
+            "processing" => HitDownloadStatus::Processing,
+            "failed" => HitDownloadStatus::Failed,
+            "cancelled" => HitDownloadStatus::Cancelled,
+            _ => panic!("invalid hit download status: {value}"),
+        }
+    }
+}
+
+impl From<HitDownloadStatus> for &'static str {
+    fn from(value: HitDownloadStatus) -> Self {
+        match value {
+            HitDownloadStatus::Queued => "queued",
+            HitDownloadStatus::Downloading => "downloading",
+            HitDownloadStatus::Processing => "processing",
+            HitDownloadStatus::Failed => "failed",
+            HitDownloadStatus::Cancelled => "cancelled",
+        }
+    }
+}
+
+/// a hit within the download queue
+
+#[derive(Clone, Serialize, JsonSchema, FromRow, Debug, Eq, PartialEq)]
+pub struct HitDownload {
+    pub hit_id: Uuid,
+    #[sqlx(try_from = "String")]
+    pub status: HitDownloadStatus,
+    /// downloads with a higher priority get processed first,
+    /// hits from packs selected by games which didn't start yet get processed before all others
+    pub priority: i32,
+    /// how often the download was attempted already
+    pub attempts: u32,
+    /// failed downloads get retried with an exponential back-off
+    #[serde(with = "time::serde::rfc3339")]
+    #[schemars(with = "String")]
+    pub next_attempt_at: OffsetDateTime,
+    pub last_error: Option<String>,
+    #[serde(with = "time::serde::rfc3339")]
+    #[schemars(with = "String")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    #[schemars(with = "String")]
+    pub last_modified: OffsetDateTime,
+}
+
+/// a download alongside the hit it downloads
+
+#[derive(Clone, Serialize, JsonSchema)]
+pub struct HitDownloadPayload {
+    pub download: HitDownload,
+    pub hit: HitPayload,
+}
+
+/// a query for listing the download queue
+
+#[derive(Deserialize, JsonSchema, FromForm)]
+pub struct HitDownloadsQuery {
+    /// only list downloads in one of these states
+    pub status: Option<Vec<HitDownloadStatus>>,
+    /// the start of the pagination (default 1)
+    pub start: Option<usize>,
+    /// amount of downloads you want to get (default 50)
+    pub amount: Option<usize>,
+}
+
+/// information necessary for reprioritising a download
+
+#[derive(Deserialize, JsonSchema, Clone, Eq, PartialEq, Debug)]
+pub struct UpdateHitDownloadPayload {
+    /// downloads with a higher priority get processed first
+    pub priority: i32,
+}
+
+/// sort downloads in the order they get processed in,
+/// hits from the given packs first, followed by the priority and the age of the download
+pub fn sort_hit_downloads(downloads: &mut [(HitDownload, Hit)], packs: &HashSet<Uuid>) {
+    downloads.sort_by_key(|(download, hit)| {
+        (
+            !hit.packs.iter().any(|p| packs.contains(p)),
+            -(download.priority as i64),
+            download.created_at,
+        )
+    });
+}
+
+/// a hit from an import, alongside the id it was imported with (CSV imports don't need one)
+type ImportedHit = (Option<Uuid>, Hit);
+
+/// the result of comparing uploaded hits and packs with the ones known to the hit service
+pub struct HitsImport {
+    pub new_packs: Vec<Pack>,
+    pub changed_packs: Vec<Pack>,
+    pub new_hits: Vec<Hit>,
+    pub changed_hits: Vec<Hit>,
+    pub unchanged_hits: usize,
+    pub conflicts: Vec<(Hit, String)>,
+}
+
+impl HitsImport {
+    /// parse the uploaded data and compare it with the current state of the hit service
+    pub fn new(data: &str, format: ImportFormat, hs: &HitService) -> Result<Self, String> {
+        let (packs, hits) = match format {
+            ImportFormat::Yaml | ImportFormat::Json => {
+                let file = if format == ImportFormat::Yaml {
+                    serde_yml::from_str::<HitsFile>(data).map_err(|e| e.to_string())?
+                } else {
+                    serde_json::from_str::<HitsFile>(data).map_err(|e| e.to_string())?
+                };
+                (
+                    file.packs,
+                    file.hits
+                        .into_iter()
+                        .map(|h| (Some(h.id), h))
+                        .collect::<Vec<_>>(),
+                )
+            }
+            ImportFormat::Csv => Self::parse_csv(data, hs)?,
+        };
+
+        Ok(Self::diff(packs, hits, hs))
+    }
+
+    fn parse_csv(data: &str, hs: &HitService) -> Result<(Vec<Pack>, Vec<ImportedHit>), String> {
+        let mut packs = HashMap::<String, Pack>::new();
+        let mut hits = vec![];
+        let mut reader = csv::ReaderBuilder::new()
+            .trim(csv::Trim::All)
+            .from_reader(data.as_bytes());
+
+        for row in reader.deserialize::<ImportCsvRow>() {
+            let row = row.map_err(|e| e.to_string())?;
+            let mut hit_packs = vec![];
+
+            for name in row
+                .packs
+                .as_deref()
+                .unwrap_or_default()
+                .split('|')
+                .map(|p| p.trim())
+                .filter(|p| !p.is_empty())
+            {
+                let pack = packs.entry(name.to_string()).or_insert_with(|| {
+                    hs.get_packs()
+                        .into_iter()
+                        .find(|p| p.name == name)
+                        .cloned()
+                        .unwrap_or_else(|| Pack {
+                            id: Uuid::new_v4(),
+                            name: name.to_string(),
+                            last_modified: OffsetDateTime::now_utc(),
+                        })
+                });
+                hit_packs.push(pack.id);
+            }
+
+            let snippets = row
+                .snippets
+                .as_deref()
+                .unwrap_or_default()
+                .split('|')
+                .map(|o| o.trim())
+                .filter(|o| !o.is_empty())
+                .map(|o| {
+                    o.parse::<u16>()
+                        .map_err(|_| format!("invalid snippet offset {} for {}", o, row.title))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+
+            hits.push((
+                row.id,
+                Hit {
+                    id: row.id.unwrap_or_else(Uuid::new_v4),
+                    artist: row.artist,
+                    title: row.title,
+                    year: row.year,
+                    belongs_to: row.belongs_to.unwrap_or_default(),
+                    yt_id: row.yt_id,
+                    source: row.source.unwrap_or_default(),
+                    location: row.location,
+                    playback_offset: row.playback_offset.unwrap_or(0),
+                    playback_length: row.playback_length,
+                    snippets,
+                    packs: hit_packs,
+                    last_modified: OffsetDateTime::now_utc(),
+                    downloaded: false,
+                },
+            ));
+        }
+
+        Ok((packs.into_values().collect::<Vec<_>>(), hits))
+    }
+
+    fn diff(packs: Vec<Pack>, hits: Vec<ImportedHit>, hs: &HitService) -> Self {
+        let mut import = HitsImport {
+            new_packs: vec![],
+            changed_packs: vec![],
+            new_hits: vec![],
+            changed_hits: vec![],
+            unchanged_hits: 0,
+            conflicts: vec![],
+        };
+        // packs which exist under a different id on this server get merged into those
+        let mut pack_ids = HashMap::<Uuid, Uuid>::new();
+
+        for pack in packs.into_iter() {
+            if let Some(p) = hs.get_pack(pack.id) {
+                pack_ids.insert(pack.id, p.id);
+
+                if p.name != pack.name {
+                    import.changed_packs.push(Pack {
+                        last_modified: OffsetDateTime::now_utc(),
+                        ..pack
+                    });
+                }
+            } else if let Some(p) = hs.get_packs().into_iter().find(|p| p.name == pack.name) {
+                pack_ids.insert(pack.id, p.id);
+            } else {
+                pack_ids.insert(pack.id, pack.id);
+                import.new_packs.push(Pack {
+                    last_modified: OffsetDateTime::now_utc(),
+                    ..pack
+                });
+            }
+        }
+
+        let mut seen_ids = HashSet::<Uuid>::new();
+        let mut seen_source_ids = HashSet::<HitId>::new();
+
+        for (id, mut hit) in hits.into_iter() {
+            let mut packs = vec![];
+
+            for pack in hit.packs.iter() {
+                match pack_ids.get(pack) {
+                    Some(p) => packs.push(*p),
+                    None if hs.get_pack(*pack).is_some() => packs.push(*pack),
+                    None => {
+                        packs.clear();
+                        break;
+                    }
+                }
+            }
+
+            if packs.len() != hit.packs.len() {
+                import
+                    .conflicts
+                    .push((hit, "the hit belongs to a pack which doesn't exist".into()));
+                continue;
+            }
+
+            packs.sort();
+            packs.dedup();
+            hit.packs = packs;
+
+            if !seen_source_ids.insert(hit.source_id()) || !seen_ids.insert(hit.id) {
+                import.conflicts.push((
+                    hit,
+                    "another hit within the import uses the same id, YouTube ID, file or URL"
+                        .into(),
+                ));
+                continue;
+            }
+
+            if hit.source == AudioSource::File && hit.source_file().is_none() {
+                import.conflicts.push((
+                    hit,
+                    "the file needs to be located within the library directory".into(),
+                ));
+                continue;
+            }
+
+            let by_id = id.and_then(|id| hs.get_hit(&HitId::Id(id)));
+            let by_source_id = hs.get_hit(&hit.source_id());
+
+            let existing = match (by_id, by_source_id) {
+                (Some(a), Some(b)) if a.id != b.id => {
+                    import.conflicts.push((
+                        hit,
+                        "a different hit with that YouTube ID, file or URL already exists".into(),
+                    ));
+                    continue;
+                }
+                (Some(a), _) => Some(a),
+                (None, Some(b)) if id.is_none() => Some(b),
+                (None, Some(_)) => {
+                    import.conflicts.push((
+                        hit,
+                        "a different hit with that YouTube ID, file or URL already exists".into(),
+                    ));
+                    continue;
+                }
+                (None, None) => None,
+            };
+
+            match existing {
+                Some(e) => {
+                    let mut packs = e.packs.clone();
+                    packs.sort();
+
+                    if e.artist != hit.artist
+                        || e.title != hit.title
+                        || e.year != hit.year
+                        || e.belongs_to != hit.belongs_to
+                        || e.yt_id != hit.yt_id
+                        || e.source != hit.source
+                        || e.location != hit.location
+                        || e.playback_offset != hit.playback_offset
+                        || e.playback_length != hit.playback_length
+                        || e.snippets != hit.snippets
+                        || packs != hit.packs
+                    {
+                        hit.id = e.id;
+                        hit.last_modified = OffsetDateTime::now_utc();
+                        import.changed_hits.push(hit);
+                    } else {
+                        import.unchanged_hits += 1;
+                    }
+                }
+                None => {
+                    hit.last_modified = OffsetDateTime::now_utc();
+                    import.new_hits.push(hit);
+                }
+            }
+        }
+
+        import
+    }
+
+    /// count how many of the imported hits belong to the given pack
+    fn hits_in_pack(&self, pack: Uuid) -> usize {
+        self.new_hits
+            .iter()
+            .chain(self.changed_hits.iter())
+            .filter(|h| h.packs.contains(&pack))
+            .count()
+    }
+
+    pub fn to_payload(&self, applied: bool) -> ImportHitsPayload {
+        let pack_payload = |p: &Pack| PackPayload {
+            id: p.id,
+            name: p.name.clone(),
+            hits: self.hits_in_pack(p.id),
+        };
+
+        ImportHitsPayload {
+            applied,
+            new_packs: self.new_packs.iter().map(pack_payload).collect::<Vec<_>>(),
+            changed_packs: self
+                .changed_packs
+                .iter()
+                .map(pack_payload)
+                .collect::<Vec<_>>(),
+            new_hits: self.new_hits.iter().map(|h| h.into()).collect::<Vec<_>>(),
+            changed_hits: self
+                .changed_hits
+                .iter()
+                .map(|h| h.into())
+                .collect::<Vec<_>>(),
+            unchanged_hits: self.unchanged_hits,
+            conflicts: self
+                .conflicts
+                .iter()
+                .map(|(h, m)| ImportConflictPayload {
+                    hit: h.into(),
+                    message: m.clone(),
+                })
+                .collect::<Vec<_>>(),
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct DownloadHitData {
     in_file: PathBuf,
@@ -285,13 +1222,125 @@ pub struct DownloadHitData {
 }
 
 const UNAVAILABLE_ISSUE_MESSAGE: &str = "youtube video is unavailable";
+const SOURCE_UNAVAILABLE_ISSUE_MESSAGE: &str = "audio source is unavailable";
 const DOWNLOAD_FAILED_ISSUE_MESSAGE: &str = "hit failed to download";
+const DUPLICATE_ISSUE_MESSAGE: &str = "possible duplicate of";
+const YEAR_MISMATCH_ISSUE_MESSAGE: &str = "year differs from the reference, suggested year:";
+
+pub const HIT_ISSUE_COLUMNS: &str =
+    "id, hit_id, type, message, state, assignee_id, assignee_name, created_at, last_modified";
+
+/// how often the years get verified again while the server is running
+const YEAR_VERIFICATION_INTERVAL: std::time::Duration =
+    std::time::Duration::from_secs(24 * 60 * 60);
+
+/// the years of duplicates may differ by this much, e.g. for the single and the album release
+pub const DUPLICATE_MAX_YEAR_DISTANCE: u32 = 1;
+
+/// a download is given up and reported as an issue after this many failed attempts
+const DOWNLOAD_MAX_ATTEMPTS: u32 = 5;
+
+/// the delay before retrying a failed download, it doubles with every further attempt
+const DOWNLOAD_RETRY_DELAY: Duration = Duration::minutes(1);
+
+/// the download queue gets checked for due downloads at least this often
+const DOWNLOAD_QUEUE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// read all hits and packs which aren't marked for deletion from the database
+pub async fn read_hits_from_db(db: &SqlitePool) -> (Vec<Pack>, Vec<Hit>) {
+    let packs = sqlx::query_as!(
+        PackRow,
+        r#"
+SELECT
+    id AS "id: Uuid",
+    name,
+    last_modified AS "last_modified: OffsetDateTime"
+FROM packs WHERE marked_for_deletion = ?"#,
+        false
+    )
+    .fetch_all(db)
+    .await
+    .unwrap();
+
+    let hits = sqlx::query_as::<_, HitRow>(
+        r#"
+SELECT
+    id,
+    title,
+    artist,
+    yt_id,
+    source,
+    location,
+    belongs_to,
+    year,
+    playback_offset,
+    playback_length,
+    snippets,
+    last_modified,
+    downloaded
+FROM hits WHERE marked_for_deletion = ?"#,
+    )
+    .bind(false)
+    .fetch_all(db)
+    .await
+    .unwrap()
+    .into_iter()
+    .map(|h| (h.id, h))
+    .collect::<HashMap<Uuid, HitRow>>();
+
+    let hits_packs = sqlx::query_as!(
+        HitPackRow,
+        r#"
+SELECT
+    hit_id AS "hit_id: Uuid",
+    pack_id AS "pack_id: Uuid"
+FROM hits_packs WHERE marked_for_deletion = ?"#,
+        false
+    )
+    .fetch_all(db)
+    .await
+    .unwrap()
+    .into_iter()
+    .fold(HashMap::<Uuid, Vec<Uuid>>::new(), |mut m, h| {
+        m.entry(h.hit_id).or_default().push(h.pack_id);
+        m
+    });
+
+    (
+        packs
+            .into_iter()
+            .map(|pack| Pack {
+                id: pack.id,
+                name: pack.name,
+                last_modified: pack.last_modified,
+            })
+            .collect::<Vec<_>>(),
+        hits.values()
+            .map(|h| Hit {
+                title: h.title.clone(),
+                artist: h.artist.clone(),
+                id: h.id,
+                yt_id: h.yt_id.clone(),
+                source: h.source.clone().into(),
+                location: h.location.clone(),
+                year: h.year,
+                playback_offset: h.playback_offset,
+                playback_length: h.playback_length,
+                snippets: serde_json::from_str(&h.snippets).unwrap_or_default(),
+                last_modified: h.last_modified,
+                belongs_to: h.belongs_to.clone(),
+                packs: hits_packs.get(&h.id).cloned().unwrap_or_default(),
+                downloaded: h.downloaded,
+            })
+            .collect::<Vec<_>>(),
+    )
+}
 
 #[derive(Default)]
 pub struct HitDownloadService {}
 
 #[cfg(feature = "yt_dl")]
-async fn check_hit_availability(hit: &Hit) -> Result<bool, String> {
+async fn check_youtube_availability(hit: &Hit) -> Result<bool, String> {
     let mut command = Command::new("yt-dlp");
     command
         .current_dir(env::current_dir().unwrap())
@@ -320,56 +1369,486 @@ async fn check_hit_availability(hit: &Hit) -> Result<bool, String> {
                 }
             }
         }
-        Err(err) => Err(err.to_string()),
-    }
-}
+        Err(err) => Err(err.to_string()),
+    }
+}
+
+#[cfg(feature = "yt_dl")]
+async fn ensure_yt_dlp_is_updated(update_time: &Arc<Mutex<OffsetDateTime>>) {
+    let mut last_update_time = update_time.lock().await;
+    let now = OffsetDateTime::now_utc();
+
+    if *last_update_time + Duration::hours(12) >= now {
+        return;
+    }
+
+    let mut command = Command::new("yt-dlp");
+    command.current_dir(env::current_dir().unwrap()).arg("-U");
+
+    let output = command.output().await;
+
+    if let Ok(ref output_res) = output
+        && !output_res.status.success()
+    {
+        rocket::warn!(
+            "Error updating yt-dlp. error: {error}",
+            error = String::from_utf8_lossy(&output_res.stderr)
+        );
+    } else if output.is_err() {
+        rocket::warn!("error when trying to run yt-dlp. Maybe it isn't installed?");
+    }
+
+    *last_update_time = OffsetDateTime::now_utc();
+}
+
+#[cfg(all(not(feature = "yt_dl"), feature = "native_dl"))]
+async fn check_youtube_availability(hit: &Hit) -> Result<bool, String> {
+    use rusty_ytdl::{Video, VideoError};
+
+    let video = Video::new(hit.yt_id.as_str()).map_err(|e| e.to_string())?;
+
+    match video.get_basic_info().await {
+        Ok(_) => Ok(true),
+        Err(VideoError::VideoNotFound)
+        | Err(VideoError::VideoSourceNotFound)
+        | Err(VideoError::VideoIsPrivate) => Ok(false),
+        Err(err) => Err(err.to_string()),
+    }
+}
+
+#[cfg(all(not(feature = "yt_dl"), not(feature = "native_dl")))]
+async fn check_youtube_availability(_hit: &Hit) -> Result<bool, String> {
+    Err("no youtube availability checker configured".to_string())
+}
+
+async fn check_hit_availability(hit: &Hit) -> Result<bool, String> {
+    match hit.source {
+        AudioSource::YouTube => check_youtube_availability(hit).await,
+        AudioSource::File => Ok(hit.source_file().is_some_and(|f| f.is_file())),
+        AudioSource::Url => check_url_availability(&hit.location).await,
+    }
+}
+
+/// check whether the audio behind a URL can be downloaded without fetching all of it.
+/// Servers which don't support HEAD requests get asked for the first byte only
+async fn check_url_availability(url: &str) -> Result<bool, String> {
+    let client = reqwest::Client::new();
+    let mut response = client.head(url).send().await.map_err(|e| e.to_string())?;
+
+    if matches!(
+        response.status(),
+        reqwest::StatusCode::METHOD_NOT_ALLOWED | reqwest::StatusCode::NOT_IMPLEMENTED
+    ) {
+        // servers ignoring the range answer with the whole file,
+        // dropping the response without reading the body aborts that transfer
+        response = client
+            .get(url)
+            .header(reqwest::header::RANGE, "bytes=0-0")
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+
+    match response.status() {
+        status if status.is_success() => Ok(true),
+        status if status.is_client_error() => Ok(false),
+        status => Err(format!("unexpected status code {}", status)),
+    }
+}
+
+/// fetch the audio of a hit which doesn't come from YouTube into a file that can be processed
+async fn fetch_hit_audio(hit: &Hit) -> Result<PathBuf, String> {
+    if hit.source == AudioSource::File {
+        return hit
+            .source_file()
+            .filter(|f| f.is_file())
+            .ok_or_else(|| format!("audio file {} doesn't exist in the library", hit.location));
+    }
+
+    let in_file = Path::new(&Hit::download_dir()).join(format!("{}.audio", hit.id));
+    let download = async {
+        let mut response = reqwest::get(hit.location.as_str())
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| e.to_string())?;
+        let mut file = File::create(&in_file).await.map_err(|e| e.to_string())?;
+
+        while let Some(chunk) = response.chunk().await.map_err(|e| e.to_string())? {
+            file.write_all(&chunk).await.map_err(|e| e.to_string())?;
+        }
+
+        file.flush().await.map_err(|e| e.to_string())
+    };
+
+    match download.await {
+        Ok(_) => Ok(in_file),
+        Err(err) => {
+            if in_file.is_file() {
+                let _ = remove_file(&in_file);
+            }
+            Err(err)
+        }
+    }
+}
+
+/// download the audio of a hit, library files are used in place
+async fn download_hit_audio(
+    hit: &Hit,
+    #[cfg(feature = "yt_dl")] yt_dlp_update_time: &Arc<Mutex<OffsetDateTime>>,
+) -> Result<PathBuf, String> {
+    if hit.source != AudioSource::YouTube {
+        return fetch_hit_audio(hit).await;
+    }
+
+    #[allow(unused_mut)]
+    let mut errors: Vec<String> = vec![];
+
+    #[cfg(feature = "native_dl")]
+    {
+        use filesize::PathExt;
+        use rusty_ytdl::{Video, VideoOptions, VideoQuality, VideoSearchOptions};
+
+        let in_file = Path::new(&Hit::download_dir()).join(format!("{}.opus", hit.yt_id));
+
+        let options = VideoOptions {
+            quality: VideoQuality::HighestAudio,
+            filter: VideoSearchOptions::Audio,
+            ..Default::default()
+        };
+
+        match Video::new_with_options(hit.yt_id.as_str(), options) {
+            Ok(video) => {
+                let in_dl = video.download(&in_file).await;
+
+                if in_dl.is_ok() && in_file.is_file() && in_file.size_on_disk().unwrap_or(0) > 0 {
+                    return Ok(in_file);
+                }
+
+                if in_file.is_file() {
+                    let _ = remove_file(&in_file);
+                }
+
+                errors.push(match in_dl {
+                    Err(e) => format!("rusty_ytdl: {}", e),
+                    Ok(_) => "rusty_ytdl: the downloaded file is empty".into(),
+                });
+            }
+            Err(e) => errors.push(format!("unable to initialize rusty_ytdl: {}", e)),
+        }
+    }
+
+    #[cfg(feature = "yt_dl")]
+    {
+        ensure_yt_dlp_is_updated(yt_dlp_update_time).await;
+        let in_file = Path::new(&Hit::download_dir()).join(format!("{}.m4a", hit.yt_id));
+
+        let mut command = Command::new("yt-dlp");
+        command
+            .current_dir(env::current_dir().unwrap())
+            .args(["-f", "bestaudio[ext=m4a]"])
+            .args(["-o", in_file.to_str().unwrap()])
+            .args(["--extractor-args", "youtube:player-client=default,mweb"])
+            .arg(format!("https://www.youtube.com/watch?v={}", hit.yt_id));
+
+        match command.output().await {
+            Ok(output) if output.status.success() => return Ok(in_file),
+            Ok(output) => errors.push(format!(
+                "yt-dlp: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            )),
+            Err(_) => errors.push("unable to run yt-dlp, maybe it isn't installed?".into()),
+        }
+    }
+
+    if errors.is_empty() {
+        Err("no downloader for youtube videos is enabled".into())
+    } else {
+        Err(errors.join(", "))
+    }
+}
+
+/// cut all snippets of a downloaded hit in all transcoding profiles
+async fn process_hit_audio(hit_data: &DownloadHitData) -> Result<(), String> {
+    // all snippets and profiles get cut from the same download
+    for offset in hit_data.hit.snippet_offsets().into_iter() {
+        for profile in transcoding::profiles().iter() {
+            let out_file = profile.file(&hit_data.hit, offset);
+
+            if out_file.is_file() {
+                continue;
+            }
+
+            // tags would give away the song before it got revealed
+            let extra_args = match hit_data.hit.playback_length {
+                Some(length) => {
+                    format!("-ss {} -t {} -map_metadata -1", offset, length)
+                }
+                None => format!("-ss {} -map_metadata -1", offset),
+            };
+
+            let mut command = Command::new("ffmpeg-normalize");
+            command
+                .current_dir(env::current_dir().unwrap())
+                .arg(&hit_data.in_file)
+                .args(profile.ffmpeg_normalize_args())
+                .args(["-e", &extra_args])
+                .args(["-o", out_file.to_str().unwrap()])
+                .arg("-sn")
+                .arg("-vn");
+
+            let output = command
+                .output()
+                .await
+                .map_err(|e| format!("unable to execute ffmpeg-normalize: {}", e))?;
+
+            if !output.status.success() {
+                // a partially written file would count as processed
+                let _ = remove_file(&out_file);
+
+                return Err(format!(
+                    "ffmpeg-normalize failed: {}",
+                    String::from_utf8_lossy(&output.stderr)
+                        .lines()
+                        .last()
+                        .unwrap_or_default()
+                ));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// add a hit to the download queue, downloads which were queued already start over
+pub async fn queue_hit_download(db: &SqlitePool, hit_id: Uuid) {
+    let now = OffsetDateTime::now_utc();
+
+    if let Err(err) = sqlx::query(
+        "INSERT INTO hit_downloads (hit_id, status, next_attempt_at, created_at, last_modified) VALUES (?, ?, ?, ?, ?)
+        ON CONFLICT (hit_id) DO UPDATE SET status = excluded.status, attempts = 0, next_attempt_at = excluded.next_attempt_at, last_error = NULL, last_modified = excluded.last_modified",
+    )
+    .bind(hit_id)
+    .bind(<&'static str>::from(HitDownloadStatus::Queued))
+    .bind(now)
+    .bind(now)
+    .bind(now)
+    .execute(db)
+    .await
+    {
+        rocket::warn!(
+            "Failed to queue the download of {hit_id}: {err}",
+            hit_id = hit_id,
+            err = err
+        );
+    }
+}
+
+/// update the amount of hits which still need to be downloaded
+pub async fn update_download_count<'c, E: Executor<'c, Database = Sqlite>>(
+    db: E,
+    hit_service: &ServiceHandle<HitService>,
+) {
+    let downloading =
+        sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM hit_downloads WHERE status IN (?, ?)")
+            .bind(<&'static str>::from(HitDownloadStatus::Queued))
+            .bind(<&'static str>::from(HitDownloadStatus::Downloading))
+            .fetch_one(db)
+            .await
+            .unwrap_or(0);
+
+    hit_service.lock().set_downloading(downloading as usize);
+}
+
+pub fn send_process_hits(
+    hit_service: &ServiceHandle<HitService>,
+    event_sender: &Sender<GlobalEvent>,
+) {
+    let hs = hit_service.lock();
+    let available = hs.get_hits().iter().filter(|h| h.downloaded).count();
+    let downloading = hs.downloading();
+    let processing = hs.processing();
+
+    drop(hs);
+
+    let _ = event_sender.send(GlobalEvent::ProcessHits {
+        available,
+        downloading,
+        processing,
+    });
+}
+
+/// download the queued hits one after another and hand them over for processing
+async fn process_download_queue(
+    db: SqlitePool,
+    hit_service: Arc<ServiceHandle<HitService>>,
+    game_service: ServiceHandle<GameService>,
+    event_sender: Arc<Sender<GlobalEvent>>,
+    process_sender: UnboundedSender<DownloadHitData>,
+    queue_notify: Arc<Notify>,
+    #[cfg(feature = "yt_dl")] yt_dlp_update_time: Arc<Mutex<OffsetDateTime>>,
+) {
+    rocket::info!("Starting background download of hits");
+
+    loop {
+        update_download_count(&db, &hit_service).await;
+        send_process_hits(&hit_service, &event_sender);
+
+        let now = OffsetDateTime::now_utc();
+        let queued =
+            sqlx::query_as::<_, HitDownload>("SELECT * FROM hit_downloads WHERE status = ?")
+                .bind(<&'static str>::from(HitDownloadStatus::Queued))
+                .fetch_all(&db)
+                .await
+                .unwrap_or_default();
+        let next_attempt_at = queued.iter().map(|d| d.next_attempt_at).min();
+        let mut due = vec![];
+        let mut removed = vec![];
+
+        {
+            let hs = hit_service.lock();
+
+            for download in queued.into_iter().filter(|d| d.next_attempt_at <= now) {
+                match hs.get_hit(&HitId::Id(download.hit_id)) {
+                    Some(hit) => due.push((download, hit.clone())),
+                    None => removed.push(download.hit_id),
+                }
+            }
+        }
 
-#[cfg(feature = "yt_dl")]
-async fn ensure_yt_dlp_is_updated(update_time: &Arc<Mutex<OffsetDateTime>>) {
-    let mut last_update_time = update_time.lock().await;
-    let now = OffsetDateTime::now_utc();
+        // hits which got deleted in the meantime
+        for hit_id in removed.into_iter() {
+            let _ = sqlx::query("DELETE FROM hit_downloads WHERE hit_id = ?")
+                .bind(hit_id)
+                .execute(&db)
+                .await;
+        }
 
-    if *last_update_time + Duration::hours(12) >= now {
-        return;
-    }
+        let packs = game_service.lock().open_packs();
 
-    let mut command = Command::new("yt-dlp");
-    command.current_dir(env::current_dir().unwrap()).arg("-U");
+        sort_hit_downloads(&mut due, &packs);
 
-    let output = command.output().await;
+        let Some((download, hit)) = due.into_iter().next() else {
+            // nothing to do until another hit gets queued or the back-off of a failed download is over
+            let timeout = next_attempt_at
+                .map(|t| (t - now).unsigned_abs())
+                .unwrap_or(DOWNLOAD_QUEUE_INTERVAL)
+                .min(DOWNLOAD_QUEUE_INTERVAL);
 
-    if let Ok(ref output_res) = output
-        && !output_res.status.success()
-    {
-        rocket::warn!(
-            "Error updating yt-dlp. error: {error}",
-            error = String::from_utf8_lossy(&output_res.stderr)
-        );
-    } else if output.is_err() {
-        rocket::warn!("error when trying to run yt-dlp. Maybe it isn't installed?");
-    }
+            select! {
+                _ = queue_notify.notified() => {},
+                _ = sleep(timeout) => {},
+            }
 
-    *last_update_time = OffsetDateTime::now_utc();
-}
+            continue;
+        };
 
-#[cfg(all(not(feature = "yt_dl"), feature = "native_dl"))]
-async fn check_hit_availability(hit: &Hit) -> Result<bool, String> {
-    use rusty_ytdl::{Video, VideoError};
+        if hit.exists(&transcoding::extensions()) {
+            let _ = sqlx::query("DELETE FROM hit_downloads WHERE hit_id = ?")
+                .bind(hit.id)
+                .execute(&db)
+                .await;
+            continue;
+        }
 
-    let video = Video::new(hit.yt_id.as_str()).map_err(|e| e.to_string())?;
+        let attempts = download.attempts + 1;
+
+        // the download might've been cancelled since it got picked
+        let downloading = sqlx::query(
+            "UPDATE hit_downloads SET status = ?, attempts = ?, last_modified = ? WHERE hit_id = ? AND status = ?",
+        )
+        .bind(<&'static str>::from(HitDownloadStatus::Downloading))
+        .bind(attempts)
+        .bind(now)
+        .bind(hit.id)
+        .bind(<&'static str>::from(HitDownloadStatus::Queued))
+        .execute(&db)
+        .await
+        .is_ok_and(|r| r.rows_affected() > 0);
+
+        if !downloading {
+            continue;
+        }
 
-    match video.get_basic_info().await {
-        Ok(_) => Ok(true),
-        Err(VideoError::VideoNotFound)
-        | Err(VideoError::VideoSourceNotFound)
-        | Err(VideoError::VideoIsPrivate) => Ok(false),
-        Err(err) => Err(err.to_string()),
+        let result = download_hit_audio(
+            &hit,
+            #[cfg(feature = "yt_dl")]
+            &yt_dlp_update_time,
+        )
+        .await;
+        let now = OffsetDateTime::now_utc();
+
+        match result {
+            Ok(in_file) => {
+                // the download might've been cancelled or queued again while it was running
+                let processing = sqlx::query(
+                    "UPDATE hit_downloads SET status = ?, last_error = NULL, last_modified = ? WHERE hit_id = ? AND status = ?",
+                )
+                .bind(<&'static str>::from(HitDownloadStatus::Processing))
+                .bind(now)
+                .bind(hit.id)
+                .bind(<&'static str>::from(HitDownloadStatus::Downloading))
+                .execute(&db)
+                .await
+                .is_ok_and(|r| r.rows_affected() > 0);
+
+                if processing {
+                    let mut hs = hit_service.lock();
+                    let processing = hs.processing();
+                    hs.set_processing(processing + 1);
+                    drop(hs);
+                    let _ = process_sender.send(DownloadHitData { in_file, hit });
+                } else if hit.source != AudioSource::File {
+                    let _ = remove_file(in_file);
+                }
+            }
+            Err(err) => {
+                rocket::warn!(
+                    "Error downloading hit {artist}: {title} (attempt {attempts} of {max_attempts}), error: {error}",
+                    artist = &hit.artist,
+                    title = &hit.title,
+                    attempts = attempts,
+                    max_attempts = DOWNLOAD_MAX_ATTEMPTS,
+                    error = &err
+                );
+
+                let status = if attempts >= DOWNLOAD_MAX_ATTEMPTS {
+                    HitDownloadStatus::Failed
+                } else {
+                    HitDownloadStatus::Queued
+                };
+
+                let updated = sqlx::query(
+                    "UPDATE hit_downloads SET status = ?, next_attempt_at = ?, last_error = ?, last_modified = ? WHERE hit_id = ? AND status = ?",
+                )
+                .bind(<&'static str>::from(status))
+                .bind(now + download_retry_delay(attempts))
+                .bind(&err)
+                .bind(now)
+                .bind(hit.id)
+                .bind(<&'static str>::from(HitDownloadStatus::Downloading))
+                .execute(&db)
+                .await
+                .is_ok_and(|r| r.rows_affected() > 0);
+
+                if updated && status == HitDownloadStatus::Failed {
+                    upsert_download_failed_issue(&db, event_sender.as_ref(), hit.id).await;
+                }
+            }
+        }
     }
 }
 
-#[cfg(all(not(feature = "yt_dl"), not(feature = "native_dl")))]
-async fn check_hit_availability(_hit: &Hit) -> Result<bool, String> {
-    Err("no youtube availability checker configured".to_string())
+/// the delay before the next attempt after the given number of failed attempts
+fn download_retry_delay(attempts: u32) -> Duration {
+    DOWNLOAD_RETRY_DELAY * 2_i32.pow(attempts.saturating_sub(1))
+}
+
+fn unavailable_issue_message(hit: &Hit) -> &'static str {
+    if hit.source.is_youtube() {
+        UNAVAILABLE_ISSUE_MESSAGE
+    } else {
+        SOURCE_UNAVAILABLE_ISSUE_MESSAGE
+    }
 }
 
 async fn upsert_auto_issue(
@@ -379,27 +1858,40 @@ async fn upsert_auto_issue(
     message: &str,
 ) {
     let now = OffsetDateTime::now_utc();
-    let existing = sqlx::query_scalar::<_, String>(
-        "SELECT id FROM hit_issues WHERE hit_id = ? AND type = 'auto' AND message = ?",
-    )
+    let existing = sqlx::query_as::<_, HitIssue>(&format!(
+        "SELECT {HIT_ISSUE_COLUMNS} FROM hit_issues WHERE hit_id = ? AND type = 'auto' AND message = ?"
+    ))
     .bind(hit_id)
     .bind(message)
     .fetch_optional(db)
     .await;
 
     match existing {
-        Ok(Some(issue_id)) => {
-            if let Err(err) = sqlx::query("UPDATE hit_issues SET last_modified = ? WHERE id = ?")
-                .bind(now)
-                .bind(issue_id)
-                .execute(db)
-                .await
+        Ok(Some(mut issue)) => {
+            // issues which got resolved when the problem disappeared are reopened once it reappears
+            let reopen = issue.state == HitIssueState::Resolved;
+
+            if reopen {
+                issue.state = HitIssueState::Open;
+            }
+
+            issue.last_modified = now;
+
+            if let Err(err) =
+                sqlx::query("UPDATE hit_issues SET state = ?, last_modified = ? WHERE id = ?")
+                    .bind(<&'static str>::from(issue.state))
+                    .bind(now)
+                    .bind(issue.id)
+                    .execute(db)
+                    .await
             {
                 rocket::warn!(
                     "Failed to update auto hit issue for {hit_id}: {err}",
                     hit_id = hit_id,
                     err = err
                 );
+            } else if reopen {
+                let _ = event_sender.send(GlobalEvent::UpdateHitIssue(issue));
             }
         }
         Ok(None) => {
@@ -408,6 +1900,9 @@ async fn upsert_auto_issue(
                 hit_id,
                 r#type: HitIssueType::Auto,
                 message: message.to_string(),
+                state: HitIssueState::Open,
+                assignee_id: None,
+                assignee_name: None,
                 created_at: now,
                 last_modified: now,
             };
@@ -442,32 +1937,72 @@ async fn upsert_auto_issue(
     }
 }
 
+/// clear the auto issues of a hit with the given message.
+/// Issues which curators already worked on or commented get resolved instead, so that their state and comments are kept
 async fn clear_auto_issue(
     db: &sqlx::SqlitePool,
     event_sender: &Sender<GlobalEvent>,
     hit_id: Uuid,
     message: &str,
 ) {
-    match sqlx::query_scalar::<_, Uuid>(
-        "SELECT id FROM hit_issues WHERE hit_id = ? AND type = 'auto' AND message = ?",
-    )
+    match sqlx::query_as::<_, HitIssue>(&format!(
+        "SELECT {HIT_ISSUE_COLUMNS} FROM hit_issues WHERE hit_id = ? AND type = 'auto' AND message = ? AND state != 'resolved'"
+    ))
     .bind(hit_id)
     .bind(message)
     .fetch_all(db)
     .await
     {
-        Ok(issue_ids) => {
-            for issue_id in issue_ids {
-                match sqlx::query("DELETE FROM hit_issues WHERE hit_id = ? AND id = ?")
-                    .bind(hit_id)
-                    .bind(issue_id)
-                    .execute(db)
-                    .await
-                {
-                    Ok(result) if result.rows_affected() > 0 => {
-                        let _ = event_sender.send(GlobalEvent::DeleteHitIssue { hit_id, issue_id });
+        Ok(issues) => {
+            for mut issue in issues {
+                let issue_id = issue.id;
+                let commented = sqlx::query_scalar::<_, i64>(
+                    "SELECT COUNT(*) FROM hit_issues_comments WHERE issue_id = ?",
+                )
+                .bind(issue_id)
+                .fetch_one(db)
+                .await
+                .map(|c| c > 0);
+
+                let res = match commented {
+                    Ok(false) if issue.state == HitIssueState::Open => {
+                        sqlx::query("DELETE FROM hit_issues WHERE hit_id = ? AND id = ?")
+                            .bind(hit_id)
+                            .bind(issue_id)
+                            .execute(db)
+                            .await
+                            .map(|r| {
+                                (r.rows_affected() > 0).then_some(GlobalEvent::DeleteHitIssue {
+                                    hit_id,
+                                    issue_id,
+                                })
+                            })
+                    }
+                    Ok(_) => {
+                        issue.state = HitIssueState::Resolved;
+                        issue.last_modified = OffsetDateTime::now_utc();
+
+                        sqlx::query(
+                            "UPDATE hit_issues SET state = ?, last_modified = ? WHERE hit_id = ? AND id = ?",
+                        )
+                        .bind(<&'static str>::from(issue.state))
+                        .bind(issue.last_modified)
+                        .bind(hit_id)
+                        .bind(issue_id)
+                        .execute(db)
+                        .await
+                        .map(|r| {
+                            (r.rows_affected() > 0).then_some(GlobalEvent::UpdateHitIssue(issue))
+                        })
                     }
-                    Ok(_) => {}
+                    Err(err) => Err(err),
+                };
+
+                match res {
+                    Ok(Some(event)) => {
+                        let _ = event_sender.send(event);
+                    }
+                    Ok(None) => {}
                     Err(err) => {
                         rocket::warn!(
                             "Failed to clear auto hit issue {issue_id} for {hit_id}: {err}",
@@ -489,20 +2024,114 @@ async fn clear_auto_issue(
     }
 }
 
+fn duplicate_issue_message(duplicate: &Hit) -> String {
+    format!(
+        "{} {} - {} ({})",
+        DUPLICATE_ISSUE_MESSAGE, duplicate.artist, duplicate.title, duplicate.id
+    )
+}
+
+/// file issues for all hits which likely are duplicates of each other
+/// and clear the ones which aren't duplicates anymore
+pub async fn flag_duplicate_hits(
+    db: &SqlitePool,
+    hit_service: &ServiceHandle<HitService>,
+    event_sender: &Sender<GlobalEvent>,
+) {
+    let issues = hit_service
+        .lock()
+        .find_duplicates(DUPLICATE_MAX_YEAR_DISTANCE)
+        .into_iter()
+        .flat_map(|(hit, duplicate)| {
+            [
+                (hit.id, duplicate_issue_message(duplicate)),
+                (duplicate.id, duplicate_issue_message(hit)),
+            ]
+        })
+        .collect::<HashSet<_>>();
+
+    sync_auto_issues(db, event_sender, DUPLICATE_ISSUE_MESSAGE, issues).await;
+}
+
+/// file issues for all hits whose year doesn't match the year reference
+/// and clear the ones which got corrected in the meantime
+pub async fn verify_hit_years(
+    db: &SqlitePool,
+    hit_service: &ServiceHandle<HitService>,
+    event_sender: &Sender<GlobalEvent>,
+) {
+    let Some(reference) = year_reference() else {
+        return;
+    };
+
+    let hits = HitsterData::new(hit_service.lock().copy_hits(), vec![]);
+
+    // the reference is usually large, so read it without blocking the runtime
+    let mismatches = rocket::tokio::task::spawn_blocking(move || {
+        std::fs::File::open(&reference)
+            .and_then(|f| hits.verify_years(io::BufReader::new(f), year_tolerance()))
+            .map(|mismatches| {
+                mismatches
+                    .into_iter()
+                    .map(|(hit, year)| {
+                        (hit.id, format!("{} {}", YEAR_MISMATCH_ISSUE_MESSAGE, year))
+                    })
+                    .collect::<HashSet<_>>()
+            })
+            .map_err(|e| format!("{}: {}", reference.display(), e))
+    })
+    .await;
+
+    match mismatches {
+        Ok(Ok(issues)) => {
+            sync_auto_issues(db, event_sender, YEAR_MISMATCH_ISSUE_MESSAGE, issues).await
+        }
+        Ok(Err(e)) => rocket::warn!("Unable to read the year reference {}", e),
+        Err(e) => rocket::warn!("Unable to verify the years of hits: {}", e),
+    }
+}
+
+/// replace all auto issues starting with prefix with the given ones,
+/// issues which exist already are kept as they are and resolved ones get reopened
+async fn sync_auto_issues(
+    db: &SqlitePool,
+    event_sender: &Sender<GlobalEvent>,
+    prefix: &str,
+    issues: HashSet<(Uuid, String)>,
+) {
+    let existing = sqlx::query_as::<_, (Uuid, String)>(
+        "SELECT hit_id, message FROM hit_issues WHERE type = 'auto' AND state != 'resolved' AND message LIKE ?",
+    )
+    .bind(format!("{} %", prefix))
+    .fetch_all(db)
+    .await
+    .unwrap_or_default()
+    .into_iter()
+    .collect::<HashSet<_>>();
+
+    for (hit_id, message) in existing.difference(&issues) {
+        clear_auto_issue(db, event_sender, *hit_id, message).await;
+    }
+
+    for (hit_id, message) in issues.difference(&existing) {
+        upsert_auto_issue(db, event_sender, *hit_id, message).await;
+    }
+}
+
 async fn upsert_unavailable_issue(
     db: &sqlx::SqlitePool,
     event_sender: &Sender<GlobalEvent>,
-    hit_id: Uuid,
+    hit: &Hit,
 ) {
-    upsert_auto_issue(db, event_sender, hit_id, UNAVAILABLE_ISSUE_MESSAGE).await;
+    upsert_auto_issue(db, event_sender, hit.id, unavailable_issue_message(hit)).await;
 }
 
 async fn clear_unavailable_issue(
     db: &sqlx::SqlitePool,
     event_sender: &Sender<GlobalEvent>,
-    hit_id: Uuid,
+    hit: &Hit,
 ) {
-    clear_auto_issue(db, event_sender, hit_id, UNAVAILABLE_ISSUE_MESSAGE).await;
+    clear_auto_issue(db, event_sender, hit.id, unavailable_issue_message(hit)).await;
 }
 
 async fn upsert_download_failed_issue(
@@ -533,9 +2162,10 @@ impl Fairing for HitDownloadService {
     async fn on_liftoff(&self, rocket: &Rocket<Orbit>) {
         let db = HitsterConfig::fetch(rocket).unwrap().0.clone();
         let hit_service = Arc::new(rocket.state::<ServiceStore>().unwrap().hit_service());
-        let dl_sender = channel::<Hit>(100000).0;
-        let process_sender = channel::<DownloadHitData>(100000).0;
+        let game_service = rocket.state::<ServiceStore>().unwrap().game_service();
+        let (process_sender, mut process_receiver) = unbounded_channel::<DownloadHitData>();
         let availability_sender = channel::<Hit>(100000).0;
+        let queue_notify = Arc::new(Notify::new());
         let event_sender = Arc::new(rocket.state::<Sender<GlobalEvent>>().unwrap().clone());
         #[cfg(feature = "yt_dl")]
         let yt_dlp_update_time = Arc::new(Mutex::new(OffsetDateTime::UNIX_EPOCH));
@@ -543,16 +2173,18 @@ impl Fairing for HitDownloadService {
 
         hit_service
             .lock()
-            .set_download_info(dl_sender.clone(), process_sender.clone());
+            .set_download_info(db.clone(), Arc::clone(&queue_notify));
         hit_service
             .lock()
             .set_availability_sender(availability_sender.clone());
 
         rocket::tokio::spawn({
             let db = db.clone();
-            let dl_sender = dl_sender.clone();
             let event_sender = Arc::clone(&event_sender);
             let hit_service = Arc::clone(&hit_service);
+            let queue_notify = Arc::clone(&queue_notify);
+            #[cfg(feature = "yt_dl")]
+            let yt_dlp_update_time = Arc::clone(&yt_dlp_update_time);
             async move {
                 let paths = read_dir(Hit::download_dir()).unwrap();
                 let mut files: HashSet<String> = HashSet::new();
@@ -561,83 +2193,36 @@ impl Fairing for HitDownloadService {
                     files.insert(p.file_name().into_string().unwrap());
                 }
 
-                let packs = sqlx::query_as!(
-                    PackRow,
-                    r#"
-SELECT
-    id AS "id: Uuid",
-    name,
-    last_modified AS "last_modified: OffsetDateTime"
-FROM packs WHERE marked_for_deletion = ?"#,
-                    false
-                )
-                .fetch_all(&db)
-                .await
-                .unwrap();
+                let (packs, hits) = read_hits_from_db(&db).await;
 
-                for pack in packs.iter() {
-                    hit_service.lock().insert_pack(Pack {
-                        id: pack.id,
-                        name: pack.name.clone(),
-                        last_modified: pack.last_modified,
-                    });
+                // downloads which got interrupted by the last shutdown start over
+                let _ = sqlx::query("UPDATE hit_downloads SET status = ? WHERE status IN (?, ?)")
+                    .bind(<&'static str>::from(HitDownloadStatus::Queued))
+                    .bind(<&'static str>::from(HitDownloadStatus::Downloading))
+                    .bind(<&'static str>::from(HitDownloadStatus::Processing))
+                    .execute(&db)
+                    .await;
+
+                for pack in packs.into_iter() {
+                    hit_service.lock().insert_pack(pack);
                 }
 
-                let hits = sqlx::query_as!(
-                    HitRow,
-                    r#"
-SELECT
-    id AS "id: Uuid",
-    title,
-    artist,
-    yt_id,
-    belongs_to,
-    year AS "year: u32",
-    playback_offset AS "playback_offset: u16",
-    last_modified AS "last_modified: OffsetDateTime",
-    downloaded
-FROM hits WHERE marked_for_deletion = ?"#,
-                    false
-                )
-                .fetch_all(&db)
-                .await
-                .unwrap()
-                .into_iter()
-                .map(|h| (h.id, h))
-                .collect::<HashMap<Uuid, HitRow>>();
-
-                let hits_packs = sqlx::query_as!(
-                    HitPackRow,
-                    r#"
-SELECT
-    hit_id AS "hit_id: Uuid",
-    pack_id AS "pack_id: Uuid"
-FROM hits_packs WHERE marked_for_deletion = ?"#,
-                    false
-                )
-                .fetch_all(&db)
-                .await
-                .unwrap()
-                .into_iter()
-                .fold(HashMap::<Uuid, Vec<Uuid>>::new(), |mut m, h| {
-                    m.entry(h.hit_id).or_default().push(h.pack_id);
-                    m
-                });
+                for mut hit in hits.into_iter() {
+                    // snippets which got cut already are kept, even if others are still missing
+                    for offset in hit.snippet_offsets().into_iter() {
+                        for profile in transcoding::profiles().iter() {
+                            files.remove(
+                                &profile
+                                    .file(&hit, offset)
+                                    .file_name()
+                                    .unwrap()
+                                    .to_string_lossy()
+                                    .into_owned(),
+                            );
+                        }
+                    }
 
-                for mut hit in hits.values().map(|h| Hit {
-                    title: h.title.clone(),
-                    artist: h.artist.clone(),
-                    id: h.id,
-                    yt_id: h.yt_id.clone(),
-                    year: h.year,
-                    playback_offset: h.playback_offset,
-                    last_modified: h.last_modified,
-                    belongs_to: h.belongs_to.clone(),
-                    packs: hits_packs.get(&h.id).cloned().unwrap_or_default(),
-                    downloaded: h.downloaded,
-                }) {
-                    if hit.exists() {
-                        files.remove(&format!("{}_{}.mp3", hit.yt_id, hit.playback_offset));
+                    if hit.exists(&transcoding::extensions()) {
                         if !hit.downloaded {
                             let _ = sqlx::query!(
                                 "UPDATE hits SET downloaded = ? WHERE id = ?",
@@ -659,20 +2244,18 @@ FROM hits_packs WHERE marked_for_deletion = ?"#,
                             .await;
                             hit.downloaded = false;
                         }
-                        let _ = dl_sender.send(hit.clone());
-                        let available = hit_service
-                            .lock()
-                            .get_hits()
-                            .iter()
-                            .filter(|h| h.downloaded)
-                            .count();
-                        let downloading = hit_service.lock().downloading();
-                        let processing = hit_service.lock().processing();
-                        let _ = event_sender.send(GlobalEvent::ProcessHits {
-                            available,
-                            downloading,
-                            processing,
-                        });
+                        // failed and cancelled downloads stay as they are
+                        let now = OffsetDateTime::now_utc();
+                        let _ = sqlx::query(
+                            "INSERT INTO hit_downloads (hit_id, status, next_attempt_at, created_at, last_modified) VALUES (?, ?, ?, ?, ?) ON CONFLICT (hit_id) DO NOTHING",
+                        )
+                        .bind(hit.id)
+                        .bind(<&'static str>::from(HitDownloadStatus::Queued))
+                        .bind(now)
+                        .bind(now)
+                        .bind(now)
+                        .execute(&db)
+                        .await;
                     }
                     hit_service.lock().insert_hit(hit);
                 }
@@ -684,6 +2267,27 @@ FROM hits_packs WHERE marked_for_deletion = ?"#,
                         file.as_str()
                     ));
                 }
+
+                // the queue only gets worked on once all hits are known and leftovers got cleaned up
+                rocket::tokio::spawn(process_download_queue(
+                    db.clone(),
+                    Arc::clone(&hit_service),
+                    game_service,
+                    Arc::clone(&event_sender),
+                    process_sender,
+                    queue_notify,
+                    #[cfg(feature = "yt_dl")]
+                    yt_dlp_update_time,
+                ));
+
+                flag_duplicate_hits(&db, &hit_service, &event_sender).await;
+
+                if year_reference().is_some() {
+                    loop {
+                        verify_hit_years(&db, &hit_service, &event_sender).await;
+                        sleep(YEAR_VERIFICATION_INTERVAL).await;
+                    }
+                }
             }
         });
 
@@ -732,10 +2336,10 @@ FROM hits_packs WHERE marked_for_deletion = ?"#,
 
                         match result {
                             Ok(true) => {
-                                clear_unavailable_issue(&db, event_sender.as_ref(), hit_id).await;
+                                clear_unavailable_issue(&db, event_sender.as_ref(), &hit).await;
                             }
                             Ok(false) => {
-                                upsert_unavailable_issue(&db, event_sender.as_ref(), hit_id).await;
+                                upsert_unavailable_issue(&db, event_sender.as_ref(), &hit).await;
                             }
                             Err(err) => {
                                 rocket::warn!(
@@ -755,26 +2359,10 @@ FROM hits_packs WHERE marked_for_deletion = ?"#,
 
         rocket::tokio::spawn({
             let db = db.clone();
-            let dl_sender = dl_sender.clone();
             let event_sender = Arc::clone(&event_sender);
             let hit_service = Arc::clone(&hit_service);
-            let process_sender = process_sender.clone();
-            #[cfg(feature = "yt_dl")]
-            let yt_dlp_update_time = Arc::clone(&yt_dlp_update_time);
             async move {
-                rocket::info!("Starting background download of hits");
-                let mut rx = dl_sender.subscribe();
-
-                loop {
-                    let hit = select! {
-                        hit = rx.recv() => match hit {
-                            Ok(hit) => hit,
-                            Err(RecvError::Closed) => break,
-                            Err(RecvError::Lagged(_)) => continue,
-                        },
-                    };
-
-                    hit_service.lock().set_downloading(true);
+                while let Some(mut hit_data) = process_receiver.recv().await {
                     let available = hit_service
                         .lock()
                         .get_hits()
@@ -788,186 +2376,52 @@ FROM hits_packs WHERE marked_for_deletion = ?"#,
                         downloading,
                         processing,
                     });
-                    #[cfg(feature = "native_dl")]
-                    {
-                        use filesize::PathExt;
-                        use rusty_ytdl::{Video, VideoOptions, VideoQuality, VideoSearchOptions};
-
-                        let in_file =
-                            Path::new(&Hit::download_dir()).join(format!("{}.opus", hit.yt_id));
-
-                        let options = VideoOptions {
-                            quality: VideoQuality::HighestAudio,
-                            filter: VideoSearchOptions::Audio,
-                            ..Default::default()
-                        };
-                        if let Ok(video) = Video::new_with_options(hit.yt_id.as_str(), options) {
-                            let in_dl = video
-                                .download(format!(
-                                    "{}/{}.opus",
-                                    Hit::download_dir().as_str(),
-                                    hit.yt_id
-                                ))
-                                .await;
+                    if !hit_data.hit.exists(&transcoding::extensions()) {
+                        let result = process_hit_audio(&hit_data).await;
 
-                            if in_dl.is_err()
-                                || !in_file.is_file()
-                                || in_file.size_on_disk().unwrap_or(0) == 0
-                            {
-                                if let Err(in_dl) = in_dl {
-                                    rocket::warn!(
-                                        "Error downloading hit with rusty_ytdl: {artist}: {title}, error: {error}",
-                                        artist = &hit.artist,
-                                        title = &hit.title,
-                                        error = in_dl
-                                    );
-                                }
-                                upsert_download_failed_issue(&db, event_sender.as_ref(), hit.id)
-                                    .await;
-                                if in_file.is_file() {
-                                    remove_file(&in_file).unwrap();
-                                }
-                            } else {
-                                let _ = process_sender.send(DownloadHitData { in_file, hit });
-                                hit_service.lock().set_downloading(!dl_sender.is_empty());
-                                let available = hit_service
-                                    .lock()
-                                    .get_hits()
-                                    .iter()
-                                    .filter(|h| h.downloaded)
-                                    .count();
-                                let downloading = hit_service.lock().downloading();
-                                let processing = hit_service.lock().processing();
-                                let _ = event_sender.send(GlobalEvent::ProcessHits {
-                                    available,
-                                    downloading,
-                                    processing,
-                                });
-                                continue;
-                            }
-                        } else {
-                            rocket::warn!(
-                                "Error initializing rusty_ytdl for {artist}: {title}",
-                                artist = &hit.artist,
-                                title = &hit.title
-                            );
-                            upsert_download_failed_issue(&db, event_sender.as_ref(), hit.id).await;
+                        // files from the library belong to the user
+                        if hit_data.hit.source != AudioSource::File {
+                            let _ = remove_file(&hit_data.in_file);
                         }
-                    }
-
-                    #[cfg(feature = "yt_dl")]
-                    {
-                        ensure_yt_dlp_is_updated(&yt_dlp_update_time).await;
-                        let in_file =
-                            Path::new(&Hit::download_dir()).join(format!("{}.m4a", hit.yt_id));
 
-                        let mut command = Command::new("yt-dlp");
-                        command
-                            .current_dir(env::current_dir().unwrap())
-                            .args(["-f", "bestaudio[ext=m4a]"])
-                            .args(["-o", in_file.to_str().unwrap()])
-                            .args(["--extractor-args", "youtube:player-client=default,mweb"])
-                            .arg(format!("https://www.youtube.com/watch?v={}", hit.yt_id));
-
-                        let output = command.output().await;
-
-                        if let Ok(ref output_res) = output {
-                            if !output_res.status.success() {
-                                rocket::warn!(
-                                    "Error downloading hit with yt-dlp: {artist}: {title}, error: {error}",
-                                    artist = &hit.artist,
-                                    title = &hit.title,
-                                    error = String::from_utf8_lossy(&output_res.stderr)
-                                );
-                                upsert_download_failed_issue(&db, event_sender.as_ref(), hit.id)
-                                    .await;
-                            } else {
-                                process_sender
-                                    .send(DownloadHitData { in_file, hit })
-                                    .unwrap();
-                            }
-                        } else {
+                        if let Err(err) = result {
                             rocket::warn!(
-                                "error when trying to run yt-dlp. Maybe it isn't installed?"
+                                "Error processing hit {artist}: {title}, error: {error}",
+                                artist = &hit_data.hit.artist,
+                                title = &hit_data.hit.title,
+                                error = &err
                             );
-                            upsert_download_failed_issue(&db, event_sender.as_ref(), hit.id).await;
-                        }
-                    }
-                    hit_service.lock().set_downloading(!dl_sender.is_empty());
-                    let available = hit_service
-                        .lock()
-                        .get_hits()
-                        .iter()
-                        .filter(|h| h.downloaded)
-                        .count();
-                    let downloading = hit_service.lock().downloading();
-                    let processing = hit_service.lock().processing();
-                    let _ = event_sender.send(GlobalEvent::ProcessHits {
-                        available,
-                        downloading,
-                        processing,
-                    });
-                }
-            }
-        });
-
-        rocket::tokio::spawn({
-            let db = db.clone();
-            let event_sender = Arc::clone(&event_sender);
-            let hit_service = Arc::clone(&hit_service);
-            let process_sender = process_sender.clone();
-            async move {
-                let mut rx = process_sender.subscribe();
-
-                loop {
-                    let mut hit_data = select! {
-                        hit_data = rx.recv() => match hit_data {
-                            Ok(hit_data) => hit_data,
-                            Err(RecvError::Closed) => break,
-                            Err(RecvError::Lagged(_)) => continue,
-                        },
-                    };
 
-                    hit_service.lock().set_processing(true);
-                    let available = hit_service
-                        .lock()
-                        .get_hits()
-                        .iter()
-                        .filter(|h| h.downloaded)
-                        .count();
-                    let downloading = hit_service.lock().downloading();
-                    let processing = hit_service.lock().processing();
-                    let _ = event_sender.send(GlobalEvent::ProcessHits {
-                        available,
-                        downloading,
-                        processing,
-                    });
-                    if !hit_data.hit.exists() {
-                        let out_file = Path::new(&Hit::download_dir()).join(format!(
-                            "{}_{}.mp3",
-                            hit_data.hit.yt_id, hit_data.hit.playback_offset
-                        ));
-
-                        let mut command = Command::new("ffmpeg-normalize");
-                        command
-                            .current_dir(env::current_dir().unwrap())
-                            .arg(&hit_data.in_file)
-                            .args(["--preset", "streaming-video"])
-                            .args(["-ar", "44100"])
-                            .args(["-b:a", "128k"])
-                            .args(["-c:a", "libmp3lame"])
-                            .args(["-e", &format!("-ss {}", hit_data.hit.playback_offset)])
-                            .args(["--extension", "mp3"])
-                            .args(["-o", out_file.to_str().unwrap()])
-                            .arg("-sn")
-                            .arg("-vn");
-
-                        let _ = command
-                            .output()
+                            // the download might've been cancelled or queued again in the meantime
+                            let failed = sqlx::query(
+                                "UPDATE hit_downloads SET status = ?, last_error = ?, last_modified = ? WHERE hit_id = ? AND status = ?",
+                            )
+                            .bind(<&'static str>::from(HitDownloadStatus::Failed))
+                            .bind(&err)
+                            .bind(OffsetDateTime::now_utc())
+                            .bind(hit_data.hit.id)
+                            .bind(<&'static str>::from(HitDownloadStatus::Processing))
+                            .execute(&db)
                             .await
-                            .expect("Failed to execute ffmpeg process!");
+                            .is_ok_and(|r| r.rows_affected() > 0);
+
+                            if failed {
+                                upsert_download_failed_issue(
+                                    &db,
+                                    event_sender.as_ref(),
+                                    hit_data.hit.id,
+                                )
+                                .await;
+                            }
 
-                        remove_file(hit_data.in_file).unwrap();
+                            let mut hs = hit_service.lock();
+                            let processing = hs.processing();
+                            hs.set_processing(processing.saturating_sub(1));
+                            drop(hs);
+                            send_process_hits(&hit_service, &event_sender);
+
+                            continue;
+                        }
                     }
                     let _ = sqlx::query!(
                         "UPDATE hits SET downloaded = ? WHERE id = ?",
@@ -976,8 +2430,14 @@ FROM hits_packs WHERE marked_for_deletion = ?"#,
                     )
                     .execute(&db)
                     .await;
-                    clear_unavailable_issue(&db, event_sender.as_ref(), hit_data.hit.id).await;
+                    clear_unavailable_issue(&db, event_sender.as_ref(), &hit_data.hit).await;
                     clear_download_failed_issue(&db, event_sender.as_ref(), hit_data.hit.id).await;
+                    let _ =
+                        sqlx::query("DELETE FROM hit_downloads WHERE hit_id = ? AND status = ?")
+                            .bind(hit_data.hit.id)
+                            .bind(<&'static str>::from(HitDownloadStatus::Processing))
+                            .execute(&db)
+                            .await;
                     hit_data.hit.downloaded = true;
                     let mut hs = hit_service.lock();
                     if hs.remove_hit(&HitId::Id(hit_data.hit.id)) {
@@ -987,7 +2447,8 @@ FROM hits_packs WHERE marked_for_deletion = ?"#,
                         // and we thus won't insert it here anymore either
                         hs.insert_hit(hit_data.hit);
                     }
-                    hs.set_processing(!process_sender.is_empty());
+                    let processing = hs.processing();
+                    hs.set_processing(processing.saturating_sub(1));
                     let available = hs.get_hits().iter().filter(|h| h.downloaded).count();
                     let downloading = hs.downloading();
                     let processing = hs.processing();
@@ -1012,3 +2473,24 @@ pub struct CreatePackPayload {
     /// the name of the new pack
     pub name: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn download_retry_delay_doubles_with_every_attempt() {
+        assert_eq!(download_retry_delay(1), Duration::minutes(1));
+        assert_eq!(download_retry_delay(2), Duration::minutes(2));
+        assert_eq!(download_retry_delay(3), Duration::minutes(4));
+        assert_eq!(
+            download_retry_delay(DOWNLOAD_MAX_ATTEMPTS),
+            Duration::minutes(16)
+        );
+    }
+
+    #[test]
+    fn download_retry_delay_without_attempts_is_the_base_delay() {
+        assert_eq!(download_retry_delay(0), DOWNLOAD_RETRY_DELAY);
+    }
+}
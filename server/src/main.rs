@@ -1,16 +1,19 @@
+mod audio;
 mod games;
 mod hits;
 mod merge_db;
 mod responses;
 mod routes;
 mod services;
+mod transcoding;
 mod users;
+mod websocket;
 
 use dotenvy::dotenv;
-use games::{GameEvent, GamePayload};
-use hits::HitDownloadService;
+use games::{GameEventQueue, GamePayload, GamePersistenceService, GameTimerService, PackPayload};
+use hits::{HitDownloadService, HitIssueComment};
 use hitster_core::HitIssue;
-use merge_db::MergeDbService;
+use merge_db::{HitsWatchService, MergeDbService};
 use rocket::{
     Build, Config, Rocket,
     fairing::{self, AdHoc},
@@ -46,8 +49,9 @@ extern crate rocket;
 #[derive(Serialize, JsonSchema, Clone, Eq, PartialEq, Debug)]
 #[serde(rename_all = "snake_case")]
 pub enum GlobalEvent {
-    CreateGame(GamePayload),
+    CreateGame(Box<GamePayload>),
     CreateHitIssue(HitIssue),
+    CreateHitIssueComment(HitIssueComment),
     ProcessHits {
         available: usize,
         downloading: usize,
@@ -58,6 +62,8 @@ pub enum GlobalEvent {
         issue_id: uuid::Uuid,
     },
     RemoveGame(String),
+    UpdateHitIssue(HitIssue),
+    UpdatePacks(Vec<PackPayload>),
 }
 
 impl GlobalEvent {
@@ -65,9 +71,12 @@ impl GlobalEvent {
         match self {
             Self::CreateGame(_) => String::from("create_game"),
             Self::CreateHitIssue(_) => String::from("create_hit_issue"),
+            Self::CreateHitIssueComment(_) => String::from("create_hit_issue_comment"),
             Self::ProcessHits { .. } => String::from("process_hits"),
             Self::DeleteHitIssue { .. } => String::from("delete_hit_issue"),
             Self::RemoveGame(_) => String::from("remove_game"),
+            Self::UpdateHitIssue(_) => String::from("update_hit_issue"),
+            Self::UpdatePacks(_) => String::from("update_packs"),
         }
     }
 }
@@ -116,12 +125,18 @@ async fn files(file: PathBuf) -> std::io::Result<NamedFile> {
 
 fn rocket_from_config(figment: Figment) -> Rocket<Build> {
     let migrations_fairing = AdHoc::try_on_ignite("SQLx Migrations", run_migrations);
+    let transcoding_fairing =
+        AdHoc::try_on_ignite("Transcoding Profiles", transcoding::load_profiles);
 
     rocket::custom(figment)
         .attach(HitsterConfig::init())
         .attach(migrations_fairing)
+        .attach(transcoding_fairing)
         .attach(MergeDbService::default())
+        .attach(GamePersistenceService::default())
+        .attach(GameTimerService::default())
         .attach(HitDownloadService::default())
+        .attach(HitsWatchService::default())
         .attach(CachedCompression::path_suffix_fairing(
             CachedCompression::static_paths(vec![".js", ".html", ".htm", ".json", ".opus"]),
         ))
@@ -141,27 +156,52 @@ fn rocket_from_config(figment: Figment) -> Rocket<Build> {
                 users_routes::logout,
                 users_routes::register,
                 //users_routes::get_user,
+                games_routes::audio,
                 games_routes::claim_hit,
                 games_routes::confirm_slot,
+                games_routes::create_audio_token,
                 games_routes::create_game,
                 games_routes::events,
                 games_routes::get_all_games,
                 games_routes::get_game,
+                games_routes::get_game_history,
                 games_routes::guess_slot,
                 games_routes::hit,
                 games_routes::join_game,
+                games_routes::kick_player,
                 games_routes::leave_game,
                 games_routes::skip_hit,
+                games_routes::socket,
+                games_routes::spectate_game,
                 games_routes::start_game,
                 games_routes::stop_game,
+                games_routes::unban_user,
                 games_routes::update_game,
                 hits_routes::create_hit,
                 hits_routes::create_hit_issue,
                 hits_routes::create_pack,
                 hits_routes::delete_hit,
                 hits_routes::delete_hit_issue,
+                hits_routes::get_hit_issues,
+                hits_routes::update_hit_issue,
+                hits_routes::get_hit_issue_comments,
+                hits_routes::create_hit_issue_comment,
                 hits_routes::delete_pack,
                 hits_routes::export_hits,
+                hits_routes::import_hits,
+                hits_routes::reload_hits,
+                hits_routes::get_hit_conflicts,
+                hits_routes::resolve_hit_conflict,
+                hits_routes::get_hit_history,
+                hits_routes::revert_hit,
+                hits_routes::get_pack_history,
+                hits_routes::revert_pack,
+                hits_routes::get_hit_duplicates,
+                hits_routes::merge_hits,
+                hits_routes::get_hit_downloads,
+                hits_routes::retry_hit_download,
+                hits_routes::cancel_hit_download,
+                hits_routes::update_hit_download,
                 hits_routes::get_all_packs,
                 hits_routes::get_hit,
                 hits_routes::search_hits,
@@ -193,12 +233,12 @@ fn rocket_from_config(figment: Figment) -> Rocket<Build> {
             }),
         )
         .manage(ServiceStore::default())
-        .manage(channel::<GameEvent>(1024).0)
+        .manage(GameEventQueue::default())
         .manage(channel::<GlobalEvent>(1024).0)
 }
 
 #[rocket::main]
-async fn main() -> Result<(), rocket::Error> {
+async fn main() -> Result<(), Box<rocket::Error>> {
     let _ = dotenv();
 
     rocket_from_config(Config::figment().merge((
@@ -210,7 +250,8 @@ async fn main() -> Result<(), rocket::Error> {
             ],
     )))
     .launch()
-    .await?;
+    .await
+    .map_err(Box::new)?;
 
     Ok(())
 }
@@ -1,30 +1,56 @@
-use crate::{HitsterConfig, hits::get_hitster_data};
-use hitster_core::{HitId, Pack};
+use crate::{
+    GlobalEvent, HitsterConfig,
+    games::PackPayload,
+    hits::{
+        flag_duplicate_hits, hits_sources, load_hitster_data, read_hits_from_db, verify_hit_years,
+    },
+    responses::ReloadHitsError,
+    services::ServiceStore,
+    transcoding,
+};
+use hitster_core::{AudioSource, Hit, HitId, HitsterData, Pack};
 use multi_key_map::MultiKeyMap;
+use notify::{Event, RecursiveMode, Watcher};
 use rocket::{
-    Build, Rocket,
+    Build, Orbit, Rocket,
     fairing::{self, Fairing, Info, Kind},
+    tokio::{
+        sync::{Mutex, broadcast::Sender, mpsc::unbounded_channel},
+        time::{Duration, sleep},
+    },
 };
 use rocket_db_pools::{
     Database,
-    sqlx::{self, FromRow},
+    sqlx::{self, Executor, FromRow, Sqlite, SqliteConnection, SqlitePool},
+};
+use rocket_okapi::okapi::schemars::JsonSchema;
+use serde::Serialize;
+use std::{
+    collections::{HashMap, HashSet},
+    env,
+    path::PathBuf,
 };
-use std::collections::{HashMap, HashSet};
 use time::OffsetDateTime;
 use uuid::Uuid;
 
+/// only one reload of the static hits may run at a time
+static RELOAD_LOCK: Mutex<()> = Mutex::const_new(());
+
 #[derive(FromRow)]
 struct PackRow {
     id: Uuid,
     name: String,
     last_modified: OffsetDateTime,
     custom: bool,
+    marked_for_deletion: bool,
 }
 
 #[derive(FromRow)]
 struct HitRow {
     id: Uuid,
     yt_id: String,
+    source: String,
+    location: String,
     last_modified: OffsetDateTime,
     custom: bool,
     marked_for_deletion: bool,
@@ -38,6 +64,268 @@ struct HitPackRow {
     marked_for_deletion: bool,
 }
 
+/// the fields of a hit which get compared while merging
+
+#[derive(Clone, PartialEq, Eq, Debug, FromRow)]
+pub struct HitVersion {
+    pub title: String,
+    pub artist: String,
+    pub yt_id: String,
+    pub source: String,
+    pub location: String,
+    pub year: u32,
+    pub playback_offset: u16,
+    pub belongs_to: String,
+    pub playback_length: Option<u16>,
+    /// JSON array of the snippet offsets
+    pub snippets: String,
+}
+
+impl From<&Hit> for HitVersion {
+    fn from(hit: &Hit) -> Self {
+        Self {
+            title: hit.title.clone(),
+            artist: hit.artist.clone(),
+            yt_id: hit.yt_id.clone(),
+            source: <&'static str>::from(hit.source).into(),
+            location: hit.location.clone(),
+            year: hit.year,
+            playback_offset: hit.playback_offset,
+            belongs_to: hit.belongs_to.clone(),
+            playback_length: hit.playback_length,
+            snippets: serde_json::to_string(&hit.snippets).unwrap(),
+        }
+    }
+}
+
+impl HitVersion {
+    /// overwrite the fields of the hit with this version
+    pub fn apply(&self, hit: &mut Hit) {
+        hit.title = self.title.clone();
+        hit.artist = self.artist.clone();
+        hit.yt_id = self.yt_id.clone();
+        hit.source = AudioSource::from(self.source.clone());
+        hit.location = self.location.clone();
+        hit.year = self.year;
+        hit.playback_offset = self.playback_offset;
+        hit.belongs_to = self.belongs_to.clone();
+        hit.playback_length = self.playback_length;
+        hit.snippets = serde_json::from_str(&self.snippets).unwrap_or_default();
+    }
+}
+
+#[derive(FromRow)]
+struct HitVersionRow {
+    id: Uuid,
+    #[sqlx(flatten)]
+    version: HitVersion,
+}
+
+/// remember the version of a hit which got merged from the codebase
+pub async fn store_merge_base<'c, E: Executor<'c, Database = Sqlite>>(
+    executor: E,
+    hit_id: Uuid,
+    version: &HitVersion,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "
+INSERT INTO hits_merge_base (
+    hit_id,
+    title,
+    artist,
+    yt_id,
+    source,
+    location,
+    year,
+    playback_offset,
+    belongs_to,
+    playback_length,
+    snippets) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+ON CONFLICT (hit_id) DO UPDATE SET
+    title = excluded.title,
+    artist = excluded.artist,
+    yt_id = excluded.yt_id,
+    source = excluded.source,
+    location = excluded.location,
+    year = excluded.year,
+    playback_offset = excluded.playback_offset,
+    belongs_to = excluded.belongs_to,
+    playback_length = excluded.playback_length,
+    snippets = excluded.snippets",
+    )
+    .bind(hit_id)
+    .bind(&version.title)
+    .bind(&version.artist)
+    .bind(&version.yt_id)
+    .bind(&version.source)
+    .bind(&version.location)
+    .bind(version.year)
+    .bind(version.playback_offset)
+    .bind(&version.belongs_to)
+    .bind(version.playback_length)
+    .bind(&version.snippets)
+    .execute(executor)
+    .await
+    .map(|_| ())
+}
+
+/// remove a conflict, e.g. after it got resolved
+pub async fn remove_merge_conflict<'c, E: Executor<'c, Database = Sqlite>>(
+    executor: E,
+    hit_id: Uuid,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM hits_merge_conflicts WHERE hit_id = ?")
+        .bind(hit_id)
+        .execute(executor)
+        .await
+        .map(|_| ())
+}
+
+/// everything needed to find out which side changed a hit since the last merge
+struct MergeBases {
+    /// the hits as they are stored within the database
+    versions: HashMap<Uuid, HitVersion>,
+    /// the hits as they got merged from the codebase last
+    bases: HashMap<Uuid, HitVersion>,
+    /// hits with unresolved conflicts
+    conflicts: HashSet<Uuid>,
+    /// merge bases to write once the merge is done
+    pending: Vec<(Uuid, HitVersion)>,
+}
+
+impl MergeBases {
+    /// three-way merge of a hit, the version merged from the codebase last is the common ancestor.
+    /// Returns whether the hit within the database needs to be overwritten by the codebase version.
+    /// Hits which got changed on both sides stay untouched and are recorded as conflicts instead
+    async fn merge_hit(
+        &mut self,
+        conn: &mut SqliteConnection,
+        hit: &HitRow,
+        static_hit: &Hit,
+        summary: &mut MergeSummary,
+    ) -> bool {
+        let theirs = HitVersion::from(static_hit);
+        let ours = self.versions.get(&hit.id);
+        let base = self.bases.get(&hit.id);
+
+        let update = match base {
+            // the codebase didn't change the hit
+            Some(base) if *base == theirs => false,
+            // only the codebase changed the hit
+            Some(base) if ours == Some(base) => true,
+            // both sides changed the hit in the same way
+            Some(_) if ours == Some(&theirs) => false,
+            Some(_) => {
+                rocket::warn!(
+                    "Conflicting changes to hit {}: {} ({}), keeping the database version",
+                    static_hit.artist,
+                    static_hit.title,
+                    static_hit.id
+                );
+                let res = sqlx::query(
+                    "
+INSERT INTO hits_merge_conflicts (
+    hit_id,
+    title,
+    artist,
+    yt_id,
+    source,
+    location,
+    year,
+    playback_offset,
+    belongs_to,
+    playback_length,
+    snippets,
+    created_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+ON CONFLICT (hit_id) DO UPDATE SET
+    title = excluded.title,
+    artist = excluded.artist,
+    yt_id = excluded.yt_id,
+    source = excluded.source,
+    location = excluded.location,
+    year = excluded.year,
+    playback_offset = excluded.playback_offset,
+    belongs_to = excluded.belongs_to,
+    playback_length = excluded.playback_length,
+    snippets = excluded.snippets",
+                )
+                .bind(hit.id)
+                .bind(&theirs.title)
+                .bind(&theirs.artist)
+                .bind(&theirs.yt_id)
+                .bind(&theirs.source)
+                .bind(&theirs.location)
+                .bind(theirs.year)
+                .bind(theirs.playback_offset)
+                .bind(&theirs.belongs_to)
+                .bind(theirs.playback_length)
+                .bind(&theirs.snippets)
+                .bind(OffsetDateTime::now_utc())
+                .execute(&mut *conn)
+                .await;
+
+                if let Err(e) = res {
+                    summary.failed_hits += 1;
+                    rocket::error!(
+                        "Unable to record the conflict of hit {}: {} ({}), the codebase version got lost: {}",
+                        static_hit.artist,
+                        static_hit.title,
+                        static_hit.id,
+                        e
+                    );
+                } else {
+                    summary.conflicts += 1;
+                }
+
+                return false;
+            }
+            // the hit got merged before the merge bases were recorded
+            None => hit.last_modified < static_hit.last_modified,
+        };
+
+        if self.conflicts.contains(&hit.id) {
+            let _ = remove_merge_conflict(&mut *conn, hit.id).await;
+        }
+
+        if base != Some(&theirs) {
+            self.pending.push((hit.id, theirs));
+        }
+
+        update
+    }
+
+    /// write the pending merge bases
+    async fn store(self, conn: &mut SqliteConnection) -> Result<(), sqlx::Error> {
+        for (hit_id, version) in self.pending.iter() {
+            store_merge_base(&mut *conn, *hit_id, version).await?;
+        }
+
+        Ok(())
+    }
+}
+
+/// what changed while merging the static hits into the database
+
+#[derive(Default, Debug, Serialize, JsonSchema)]
+pub struct MergeSummary {
+    /// packs which were added to the sources
+    pub inserted_packs: usize,
+    /// packs which changed within the sources
+    pub updated_packs: usize,
+    /// packs which were removed from the sources
+    pub deleted_packs: usize,
+    /// hits which were added to the sources
+    pub inserted_hits: usize,
+    /// hits which changed within the sources
+    pub updated_hits: usize,
+    /// hits which were removed from the sources
+    pub deleted_hits: usize,
+    /// hits which changed within both the sources and the database
+    pub conflicts: usize,
+    /// conflicting hits which couldn't be recorded as conflicts
+    pub failed_hits: usize,
+}
+
 #[derive(Default)]
 pub struct MergeDbService {}
 
@@ -52,69 +340,157 @@ impl Fairing for MergeDbService {
 
     async fn on_ignite(&self, rocket: Rocket<Build>) -> fairing::Result {
         let db = HitsterConfig::fetch(&rocket).unwrap();
-        let static_hits = get_hitster_data();
+        let static_hits = match load_hitster_data() {
+            Ok(data) => data,
+            Err(errors) => {
+                for error in errors.iter() {
+                    rocket::error!("{}", error);
+                }
+                rocket::error!("Unable to load the static hits, please fix the errors above.");
+                return Err(rocket);
+            }
+        };
 
-        rocket::info!("Start merging database...");
+        if let Err(e) = merge_hits(&db.0, &static_hits).await {
+            rocket::error!("Unable to merge the static hits into the database: {}", e);
+            return Err(rocket);
+        }
 
-        rocket::info!("Loaded {} static packs", static_hits.get_packs().len());
-        rocket::info!("Loaded {} static hits", static_hits.get_hits().len());
+        Ok(rocket)
+    }
+}
 
-        let packs = sqlx::query_as!(
-            PackRow,
-            r#"
-SELECT
-    id AS "id: Uuid",
-    name,
-    last_modified AS "last_modified: OffsetDateTime",
-    custom
-FROM packs"#
-        )
-        .fetch_all(&db.0)
-        .await
-        .unwrap()
-        .into_iter()
-        .fold(HashMap::<Uuid, PackRow>::new(), |mut m, row| {
-            m.insert(row.id, row);
-            m
-        });
+/// merge the static hits and packs into the database.
+/// Hits and packs which were created via the API are kept
+pub async fn merge_hits(
+    db: &SqlitePool,
+    static_hits: &HitsterData,
+) -> Result<MergeSummary, sqlx::Error> {
+    let mut conn = db.acquire().await?;
+
+    // changes made via the API in the meantime mustn't interleave with a partially applied merge.
+    // sqlite can't turn a reading transaction into a writing one once others wrote in the meantime,
+    // so the write lock gets taken right away
+    sqlx::query("BEGIN IMMEDIATE").execute(&mut *conn).await?;
 
-        rocket::info!("Loaded {} packs from db", packs.len());
+    let result = match apply_merge(&mut conn, static_hits).await {
+        Ok(summary) => sqlx::query("COMMIT")
+            .execute(&mut *conn)
+            .await
+            .map(|_| summary),
+        Err(e) => Err(e),
+    };
+
+    if result.is_err() {
+        let _ = sqlx::query("ROLLBACK").execute(&mut *conn).await;
+    }
 
-        let hits = sqlx::query_as!(
-            HitRow,
-            r#"
+    result
+}
+
+/// merge the static hits and packs into the database, the caller takes care of the transaction
+async fn apply_merge(
+    conn: &mut SqliteConnection,
+    static_hits: &HitsterData,
+) -> Result<MergeSummary, sqlx::Error> {
+    let mut summary = MergeSummary::default();
+
+    rocket::info!("Start merging database...");
+
+    rocket::info!("Loaded {} static packs", static_hits.get_packs().len());
+    rocket::info!("Loaded {} static hits", static_hits.get_hits().len());
+
+    let packs = sqlx::query_as::<_, PackRow>(
+        "SELECT id, name, last_modified, custom, marked_for_deletion FROM packs",
+    )
+    .fetch_all(&mut *conn)
+    .await?
+    .into_iter()
+    .fold(HashMap::<Uuid, PackRow>::new(), |mut m, row| {
+        m.insert(row.id, row);
+        m
+    });
+
+    rocket::info!("Loaded {} packs from db", packs.len());
+
+    let hits = sqlx::query_as::<_, HitRow>(
+        "SELECT id, yt_id, source, location, last_modified, custom, marked_for_deletion FROM hits",
+    )
+    .fetch_all(&mut *conn)
+    .await?
+    .into_iter()
+    .map(|row| {
+        let source_id = match AudioSource::from(row.source.clone()) {
+            AudioSource::YouTube => HitId::YtId(row.yt_id.clone()),
+            AudioSource::File | AudioSource::Url => HitId::Location(row.location.clone()),
+        };
+
+        (vec![HitId::Id(row.id), source_id], row)
+    })
+    .collect::<MultiKeyMap<HitId, HitRow>>();
+
+    rocket::info!("Loaded {} hits from db", hits.values().count());
+
+    let versions = sqlx::query_as::<_, HitVersionRow>(
+        "SELECT id, title, artist, yt_id, source, location, year, playback_offset, belongs_to, playback_length, snippets FROM hits",
+    )
+    .fetch_all(&mut *conn)
+    .await?
+    .into_iter()
+    .map(|row| (row.id, row.version))
+    .collect::<HashMap<Uuid, HitVersion>>();
+
+    let bases = sqlx::query_as::<_, HitVersionRow>(
+        "
 SELECT
-    id AS "id: Uuid",
+    hit_id AS id,
+    title,
+    artist,
     yt_id,
-    last_modified AS "last_modified: OffsetDateTime",
-    custom,
-    marked_for_deletion
-FROM hits"#
-        )
-        .fetch_all(&db.0)
-        .await
-        .unwrap()
+    source,
+    location,
+    year,
+    playback_offset,
+    belongs_to,
+    playback_length,
+    snippets
+FROM hits_merge_base",
+    )
+    .fetch_all(&mut *conn)
+    .await?
+    .into_iter()
+    .map(|row| (row.id, row.version))
+    .collect::<HashMap<Uuid, HitVersion>>();
+
+    let conflicts = sqlx::query_scalar::<_, Uuid>("SELECT hit_id FROM hits_merge_conflicts")
+        .fetch_all(&mut *conn)
+        .await?
         .into_iter()
-        .map(|row| (vec![HitId::Id(row.id), HitId::YtId(row.yt_id.clone())], row))
-        .collect::<MultiKeyMap<HitId, HitRow>>();
+        .collect::<HashSet<Uuid>>();
 
-        rocket::info!("Loaded {} hits from db", hits.values().count());
+    let mut merge_bases = MergeBases {
+        versions,
+        bases,
+        conflicts,
+        pending: vec![],
+    };
 
-        let static_packs = static_hits
-            .get_packs()
-            .into_iter()
-            .map(|p| (p.id, p))
-            .collect::<HashMap<Uuid, &Pack>>();
+    let static_packs = static_hits
+        .get_packs()
+        .into_iter()
+        .map(|p| (p.id, p))
+        .collect::<HashMap<Uuid, &Pack>>();
 
-        for static_pack in static_packs.values() {
-            if !packs.contains_key(&static_pack.id) {
-                rocket::info!(
-                    "Inserting new pack {} ({})",
-                    static_pack.name,
-                    static_pack.id
-                );
-                let _ = sqlx::query!(
-                    "
+    for static_pack in static_packs.values() {
+        if !packs.contains_key(&static_pack.id) {
+            summary.inserted_packs += 1;
+            rocket::info!(
+                "Inserting new pack {} ({})",
+                static_pack.name,
+                static_pack.id
+            );
+            let _ = sqlx::query!(
+                "
 INSERT INTO packs (
     id,
     name,
@@ -126,112 +502,139 @@ INSERT INTO packs (
     $3,
     $4,
     $5)",
-                    static_pack.id,
-                    static_pack.name,
-                    static_pack.last_modified,
-                    false,
-                    false
-                )
-                .execute(&db.0)
-                .await;
-            } else if packs.get(&static_pack.id).unwrap().last_modified < static_pack.last_modified
-            {
-                rocket::info!(
-                    "Updating pack {} ({}), (old {}, new {})",
-                    static_pack.name,
-                    static_pack.id,
-                    packs.get(&static_pack.id).unwrap().last_modified,
-                    static_pack.last_modified
-                );
-                let _ = sqlx::query!(
-                    "
+                static_pack.id,
+                static_pack.name,
+                static_pack.last_modified,
+                false,
+                false
+            )
+            .execute(&mut *conn)
+            .await;
+        } else if packs.get(&static_pack.id).unwrap().last_modified < static_pack.last_modified {
+            summary.updated_packs += 1;
+            rocket::info!(
+                "Updating pack {} ({}), (old {}, new {})",
+                static_pack.name,
+                static_pack.id,
+                packs.get(&static_pack.id).unwrap().last_modified,
+                static_pack.last_modified
+            );
+            let _ = sqlx::query!(
+                "
 UPDATE packs
 SET
     name = $1,
     last_modified = $2
 WHERE id = $3",
-                    static_pack.name,
-                    static_pack.last_modified,
-                    static_pack.id
-                )
-                .execute(&db.0)
-                .await;
-            }
+                static_pack.name,
+                static_pack.last_modified,
+                static_pack.id
+            )
+            .execute(&mut *conn)
+            .await;
         }
+    }
 
-        for pack in packs.values() {
-            if !static_packs.contains_key(&pack.id) && !pack.custom {
-                rocket::info!("Deleting old pack {} ({})", pack.name, pack.id);
-                let _ = sqlx::query!("DELETE FROM packs WHERE id = $1", pack.id)
-                    .execute(&db.0)
-                    .await;
-            }
+    for pack in packs.values() {
+        if !static_packs.contains_key(&pack.id) && !pack.custom && !pack.marked_for_deletion {
+            summary.deleted_packs += 1;
+            rocket::info!("Marking old pack {} ({}) as deleted", pack.name, pack.id);
+            let _ = sqlx::query("UPDATE packs SET marked_for_deletion = ? WHERE id = ?")
+                .bind(true)
+                .bind(pack.id)
+                .execute(&mut *conn)
+                .await;
         }
+    }
 
-        let hits_packs = sqlx::query_as!(
-            HitPackRow,
-            r#"
+    let hits_packs = sqlx::query_as!(
+        HitPackRow,
+        r#"
 SELECT
     hit_id AS "hit_id: Uuid",
     pack_id AS "pack_id: Uuid",
     custom,
     marked_for_deletion
 FROM hits_packs"#
-        )
-        .fetch_all(&db.0)
-        .await
-        .unwrap()
-        .into_iter()
-        .fold(HashMap::<HitId, Vec<HitPackRow>>::new(), |mut m, row| {
-            m.entry(HitId::Id(row.hit_id)).or_default().push(row);
-            m
-        });
+    )
+    .fetch_all(&mut *conn)
+    .await?
+    .into_iter()
+    .fold(HashMap::<HitId, Vec<HitPackRow>>::new(), |mut m, row| {
+        m.entry(HitId::Id(row.hit_id)).or_default().push(row);
+        m
+    });
 
-        rocket::info!(
-            "Loaded {} hits to packs associations from db",
-            hits_packs.values().map(|h| h.len()).sum::<usize>()
-        );
+    rocket::info!(
+        "Loaded {} hits to packs associations from db",
+        hits_packs.values().map(|h| h.len()).sum::<usize>()
+    );
 
-        for static_hit in static_hits.get_hits().into_iter() {
-            if !hits.contains_key(&HitId::Id(static_hit.id)) {
-                let hit = hits.get(&HitId::YtId(static_hit.yt_id.clone()));
-                if let Some(hit) = hit {
-                    // the link is already in use, but not under this id
-                    // delete the entry in the db
-                    rocket::info!(
-                        "Delete accidental duplicate {} (same yt id as {}: {} ({}))",
-                        hit.id,
+    for static_hit in static_hits.get_hits().into_iter() {
+        if !hits.contains_key(&HitId::Id(static_hit.id)) {
+            let hit = hits.get(&static_hit.source_id());
+            if let Some(hit) = hit {
+                if hit.custom {
+                    // hits which were created or imported via the API are kept
+                    rocket::warn!(
+                        "Skipping new hit {}: {} ({}), its audio source is used by hit {} already",
                         static_hit.artist,
                         static_hit.title,
-                        static_hit.id
+                        static_hit.id,
+                        hit.id
                     );
-                    let _ = sqlx::query!("DELETE FROM hits WHERE yt_id = $1", static_hit.yt_id)
-                        .execute(&db.0)
-                        .await;
+                    continue;
                 }
-                // the hit is entirely new and needs to be created
-                let exists = static_hit.exists();
-                let marked_for_deletion = hit.map(|h| h.marked_for_deletion).unwrap_or(false);
+                // the link is already in use, but not under this id
+                // delete the entry in the db
                 rocket::info!(
-                    "Insert new hit {}: {} ({})",
+                    "Delete accidental duplicate {} (same audio source as {}: {} ({}))",
+                    hit.id,
                     static_hit.artist,
                     static_hit.title,
                     static_hit.id
                 );
-                let _ = sqlx::query!(
-                    "
+                let _ = sqlx::query("DELETE FROM hits_packs WHERE hit_id = ?")
+                    .bind(hit.id)
+                    .execute(&mut *conn)
+                    .await;
+                let _ = sqlx::query("DELETE FROM hits WHERE id = ?")
+                    .bind(hit.id)
+                    .execute(&mut *conn)
+                    .await;
+            }
+            // the hit is entirely new and needs to be created
+            let exists = static_hit.exists(&transcoding::extensions());
+            let marked_for_deletion = hit.map(|h| h.marked_for_deletion).unwrap_or(false);
+            summary.inserted_hits += 1;
+            rocket::info!(
+                "Insert new hit {}: {} ({})",
+                static_hit.artist,
+                static_hit.title,
+                static_hit.id
+            );
+            let _ = sqlx::query(
+                "
 INSERT INTO hits (
     id,
     title,
     artist,
     yt_id,
+    source,
+    location,
     year,
     playback_offset,
     belongs_to,
     last_modified,
     downloaded,
     custom,
-    marked_for_deletion) VALUES (
+    marked_for_deletion,
+    playback_length,
+    snippets) VALUES (
+    ?,
+    ?,
+    ?,
+    ?,
     ?,
     ?,
     ?,
@@ -243,67 +646,74 @@ INSERT INTO hits (
     ?,
     ?,
     ?)",
-                    static_hit.id,
-                    static_hit.title,
-                    static_hit.artist,
-                    static_hit.yt_id,
-                    static_hit.year,
-                    static_hit.playback_offset,
-                    static_hit.belongs_to,
-                    static_hit.last_modified,
-                    exists,
-                    false,
-                    marked_for_deletion
+            )
+            .bind(static_hit.id)
+            .bind(&static_hit.title)
+            .bind(&static_hit.artist)
+            .bind(&static_hit.yt_id)
+            .bind(<&'static str>::from(static_hit.source))
+            .bind(&static_hit.location)
+            .bind(static_hit.year)
+            .bind(static_hit.playback_offset)
+            .bind(&static_hit.belongs_to)
+            .bind(static_hit.last_modified)
+            .bind(exists)
+            .bind(false)
+            .bind(marked_for_deletion)
+            .bind(static_hit.playback_length)
+            .bind(serde_json::to_string(&static_hit.snippets).unwrap())
+            .execute(&mut *conn)
+            .await;
+            merge_bases
+                .pending
+                .push((static_hit.id, HitVersion::from(static_hit)));
+            // and insert the packs associated
+            for pack in hit
+                .and_then(|hit| hits_packs.get(&HitId::Id(hit.id)))
+                .map(|p| {
+                    p.iter()
+                        .map(|row| {
+                            let pack = packs.get(&row.pack_id).unwrap();
+                            Pack {
+                                id: row.pack_id,
+                                name: pack.name.clone(),
+                                last_modified: pack.last_modified,
+                            }
+                        })
+                        .collect::<HashSet<_>>()
+                })
+                .unwrap_or(HashSet::new())
+                .union(
+                    &static_hit
+                        .packs
+                        .iter()
+                        .map(|p| (*static_packs.get(p).unwrap()).clone())
+                        .collect::<HashSet<_>>(),
                 )
-                .execute(&db.0)
-                .await;
-                // and insert the packs associated
-                for pack in hit
-                    .and_then(|hit| hits_packs.get(&HitId::Id(hit.id)))
-                    .map(|p| {
+            {
+                let custom = hits_packs
+                    .get(&HitId::Id(static_hit.id))
+                    .and_then(|p| p.iter().find(|p| p.pack_id == pack.id).map(|p| p.custom))
+                    .unwrap_or(false);
+                let marked_for_deletion = hits_packs
+                    .get(&HitId::Id(static_hit.id))
+                    .and_then(|p| {
                         p.iter()
-                            .map(|row| {
-                                let pack = packs.get(&row.pack_id).unwrap();
-                                Pack {
-                                    id: row.pack_id,
-                                    name: pack.name.clone(),
-                                    last_modified: pack.last_modified,
-                                }
-                            })
-                            .collect::<HashSet<_>>()
+                            .find(|p| p.pack_id == pack.id)
+                            .map(|p| p.marked_for_deletion)
                     })
-                    .unwrap_or(HashSet::new())
-                    .union(
-                        &static_hit
-                            .packs
-                            .iter()
-                            .map(|p| (*static_packs.get(p).unwrap()).clone())
-                            .collect::<HashSet<_>>(),
-                    )
-                {
-                    let custom = hits_packs
-                        .get(&HitId::Id(static_hit.id))
-                        .and_then(|p| p.iter().find(|p| p.pack_id == pack.id).map(|p| p.custom))
-                        .unwrap_or(false);
-                    let marked_for_deletion = hits_packs
-                        .get(&HitId::Id(static_hit.id))
-                        .and_then(|p| {
-                            p.iter()
-                                .find(|p| p.pack_id == pack.id)
-                                .map(|p| p.marked_for_deletion)
-                        })
-                        .unwrap_or(false);
-                    rocket::info!(
-                        "Insert association of hit {}: {} ({}) with pack {} ({}) (custom: {})",
-                        static_hit.artist,
-                        static_hit.title,
-                        static_hit.id,
-                        pack.name,
-                        pack.id,
-                        custom
-                    );
-                    let _ = sqlx::query!(
-                        "
+                    .unwrap_or(false);
+                rocket::info!(
+                    "Insert association of hit {}: {} ({}) with pack {} ({}) (custom: {})",
+                    static_hit.artist,
+                    static_hit.title,
+                    static_hit.id,
+                    pack.name,
+                    pack.id,
+                    custom
+                );
+                let _ = sqlx::query!(
+                    "
 INSERT INTO hits_packs (
     hit_id, 
     pack_id, 
@@ -313,72 +723,83 @@ INSERT INTO hits_packs (
     $2,
     $3,
     $4)",
-                        static_hit.id,
-                        pack.id,
-                        custom,
-                        marked_for_deletion
-                    )
-                    .execute(&db.0)
-                    .await;
-                }
-                continue; // no need to remove unnecessary associations
-            } else if let Some(hit) = hits.get(&HitId::Id(static_hit.id))
-                && hit.last_modified < static_hit.last_modified
+                    static_hit.id,
+                    pack.id,
+                    custom,
+                    marked_for_deletion
+                )
+                .execute(&mut *conn)
+                .await;
+            }
+            continue; // no need to remove unnecessary associations
+        } else if let Some(hit) = hits.get(&HitId::Id(static_hit.id))
+            && merge_bases
+                .merge_hit(&mut *conn, hit, static_hit, &mut summary)
+                .await
+        {
+            // the hit exists and got updated in the meantime
+            summary.updated_hits += 1;
+            rocket::info!(
+                "Updating hit {}: {} ({}) (old {}, new {})",
+                static_hit.artist,
+                static_hit.title,
+                static_hit.id,
+                hit.last_modified,
+                static_hit.last_modified
+            );
+            let _ = sqlx::query(
+                "
+UPDATE hits SET
+    title = $1,
+    artist = $2,
+    yt_id = $3,
+    source = $4,
+    location = $5,
+    year = $6,
+    playback_offset = $7,
+    belongs_to = $8,
+    last_modified = $9,
+    downloaded = $10,
+    custom = $11,
+    playback_length = $12,
+    snippets = $13
+    WHERE id = $14",
+            )
+            .bind(&static_hit.title)
+            .bind(&static_hit.artist)
+            .bind(&static_hit.yt_id)
+            .bind(<&'static str>::from(static_hit.source))
+            .bind(&static_hit.location)
+            .bind(static_hit.year)
+            .bind(static_hit.playback_offset)
+            .bind(&static_hit.belongs_to)
+            .bind(static_hit.last_modified)
+            .bind(false)
+            .bind(false)
+            .bind(static_hit.playback_length)
+            .bind(serde_json::to_string(&static_hit.snippets).unwrap())
+            .bind(static_hit.id)
+            .execute(&mut *conn)
+            .await;
+        }
+        // we will check for new and old associations either if the hit was updated or inserted anew
+        // check for new associations
+        for pack in static_hit.packs.iter() {
+            if !hits_packs
+                .get(&HitId::Id(static_hit.id))
+                .map(|packs| packs.iter().any(|p| p.pack_id == *pack))
+                .unwrap_or(false)
             {
-                // the hit exists and got updated in the meantime
                 rocket::info!(
-                    "Updating hit {}: {} ({}) (old {}, new {})",
+                    "Insert new association of hit {}: {} ({}) with pack {} ({})",
                     static_hit.artist,
                     static_hit.title,
                     static_hit.id,
-                    hit.last_modified,
-                    static_hit.last_modified
+                    static_hits.get_pack(*pack).unwrap().name,
+                    pack
                 );
                 let _ = sqlx::query!(
                     "
-UPDATE hits SET
-    title = $1,
-    artist = $2,
-    yt_id = $3,
-    year = $4,
-    playback_offset = $5,
-    belongs_to = $6,
-    last_modified = $7,
-    downloaded = $8,
-    custom = $9
-    WHERE id = $10",
-                    static_hit.title,
-                    static_hit.artist,
-                    static_hit.yt_id,
-                    static_hit.year,
-                    static_hit.playback_offset,
-                    static_hit.belongs_to,
-                    static_hit.last_modified,
-                    false,
-                    false,
-                    static_hit.id,
-                )
-                .execute(&db.0)
-                .await;
-            }
-            // we will check for new and old associations either if the hit was updated or inserted anew
-            // check for new associations
-            for pack in static_hit.packs.iter() {
-                if !hits_packs
-                    .get(&HitId::Id(static_hit.id))
-                    .map(|packs| packs.iter().any(|p| p.pack_id == *pack))
-                    .unwrap_or(false)
-                {
-                    rocket::info!(
-                        "Insert new association of hit {}: {} ({}) with pack {} ({})",
-                        static_hit.artist,
-                        static_hit.title,
-                        static_hit.id,
-                        static_hits.get_pack(*pack).unwrap().name,
-                        pack
-                    );
-                    let _ = sqlx::query!(
-                        "
 INSERT INTO hits_packs (
     hit_id, 
     pack_id, 
@@ -388,60 +809,468 @@ INSERT INTO hits_packs (
     ?, 
     ?, 
     ?)",
-                        static_hit.id,
-                        pack,
-                        false,
-                        false
-                    )
-                    .execute(&db.0)
-                    .await;
-                }
+                    static_hit.id,
+                    pack,
+                    false,
+                    false
+                )
+                .execute(&mut *conn)
+                .await;
             }
-            // check for deleted associations
-            for pack in hits_packs
-                .get(&HitId::Id(static_hit.id))
-                .unwrap_or(&vec![])
-                .iter()
+        }
+        // check for deleted associations
+        for pack in hits_packs
+            .get(&HitId::Id(static_hit.id))
+            .unwrap_or(&vec![])
+            .iter()
+        {
+            if !pack.custom && !static_hit.packs.contains(&pack.pack_id) {
+                // this association has been removed in the static dataset
+                rocket::info!(
+                    "Delete dangling association from hit {}: {} ({}) to pack {} ({})",
+                    static_hit.artist,
+                    static_hit.title,
+                    static_hit.id,
+                    packs.get(&pack.pack_id).unwrap().name,
+                    pack.pack_id
+                );
+                let _ = sqlx::query!(
+                    "DELETE FROM hits_packs WHERE hit_id = ? AND pack_id = ?",
+                    pack.hit_id,
+                    pack.pack_id
+                )
+                .execute(&mut *conn)
+                .await;
+            }
+        }
+    }
+
+    for hit in hits.values() {
+        if static_hits.get_hit(&HitId::Id(hit.id)).is_none()
+            && !hit.custom
+            && !hit.marked_for_deletion
+        {
+            // removed hits are only marked, so that their history and issues stay available
+            summary.deleted_hits += 1;
+            rocket::info!("Marking hit {} as deleted", hit.id);
+            let _ = sqlx::query("UPDATE hits SET marked_for_deletion = ? WHERE id = ?")
+                .bind(true)
+                .bind(hit.id)
+                .execute(&mut *conn)
+                .await;
+        } else if hit.custom && hit.marked_for_deletion {
+            summary.deleted_hits += 1;
+            rocket::info!("Deleting custom hit {} marked for deletion", hit.id);
+            let _ = sqlx::query!("DELETE FROM hits WHERE id = $1", hit.id)
+                .execute(&mut *conn)
+                .await;
+        }
+    }
+
+    merge_bases.store(&mut *conn).await?;
+
+    rocket::info!("Finished merging database.");
+
+    Ok(summary)
+}
+
+/// load the static hits from their sources again, merge them into the database and update the hit service.
+/// Running games keep the hits they already drew, new games only get to see the reloaded hits
+pub async fn reload_hits(
+    db: &SqlitePool,
+    serv: &ServiceStore,
+    event_sender: &Sender<GlobalEvent>,
+) -> Result<MergeSummary, ReloadHitsError> {
+    let _guard = RELOAD_LOCK.lock().await;
+    let static_hits = load_hitster_data().map_err(|errors| ReloadHitsError {
+        message: "the hit sources contain errors".into(),
+        errors,
+        http_status_code: 422,
+    })?;
+    let summary = merge_hits(db, &static_hits).await.map_err(|e| {
+        rocket::error!("Unable to merge the static hits into the database: {}", e);
+        ReloadHitsError {
+            message: "failed to merge the hits into the database".into(),
+            errors: vec![],
+            http_status_code: 500,
+        }
+    })?;
+    let (packs, hits) = read_hits_from_db(db).await;
+    let hs = serv.hit_service();
+
+    let (packs, available, downloading, processing) = {
+        let mut hsl = hs.lock();
+
+        let pack_ids = packs.iter().map(|p| p.id).collect::<HashSet<_>>();
+        let hit_ids = hits.iter().map(|h| h.id).collect::<HashSet<_>>();
+
+        for pack in hsl
+            .get_packs()
+            .into_iter()
+            .filter(|p| !pack_ids.contains(&p.id))
+            .map(|p| p.id)
+            .collect::<Vec<_>>()
+        {
+            hsl.remove_pack(pack);
+        }
+
+        for pack in packs.into_iter() {
+            hsl.insert_pack(pack);
+        }
+
+        for hit in hsl
+            .get_hits()
+            .into_iter()
+            .filter(|h| !hit_ids.contains(&h.id))
+            .map(|h| h.id)
+            .collect::<Vec<_>>()
+        {
+            hsl.remove_hit(&HitId::Id(hit));
+        }
+
+        for mut hit in hits.into_iter() {
+            if let Some(h) = hsl.get_hit(&HitId::Id(hit.id))
+                && h.artist == hit.artist
+                && h.title == hit.title
+                && h.year == hit.year
+                && h.belongs_to == hit.belongs_to
+                && h.yt_id == hit.yt_id
+                && h.source == hit.source
+                && h.location == hit.location
+                && h.playback_offset == hit.playback_offset
+                && h.playback_length == hit.playback_length
+                && h.snippets == hit.snippets
+                && h.packs.iter().collect::<HashSet<_>>()
+                    == hit.packs.iter().collect::<HashSet<_>>()
             {
-                if !pack.custom && !static_hit.packs.contains(&pack.pack_id) {
-                    // this association has been removed in the static dataset
-                    rocket::info!(
-                        "Delete dangling association from hit {}: {} ({}) to pack {} ({})",
-                        static_hit.artist,
-                        static_hit.title,
-                        static_hit.id,
-                        packs.get(&pack.pack_id).unwrap().name,
-                        pack.pack_id
-                    );
-                    let _ = sqlx::query!(
-                        "DELETE FROM hits_packs WHERE hit_id = ? AND pack_id = ?",
-                        pack.hit_id,
-                        pack.pack_id
-                    )
-                    .execute(&db.0)
-                    .await;
-                }
+                continue;
+            }
+
+            hit.downloaded = hit.exists(&transcoding::extensions());
+            hsl.remove_hit(&HitId::Id(hit.id));
+
+            hsl.insert_hit(hit.clone());
+
+            if !hit.downloaded {
+                hsl.download_hit(hit);
+            }
+        }
+
+        let packs = hsl
+            .get_packs()
+            .into_iter()
+            .map(|p| PackPayload {
+                id: p.id,
+                name: p.name.clone(),
+                hits: hsl.get_hits_for_packs(&[p.id]).len(),
+            })
+            .collect::<Vec<_>>();
+        let available = hsl.get_hits().iter().filter(|h| h.downloaded).count();
+        let downloading = hsl.downloading();
+        let processing = hsl.processing();
+
+        (packs, available, downloading, processing)
+    };
+
+    flag_duplicate_hits(db, &hs, event_sender).await;
+    verify_hit_years(db, &hs, event_sender).await;
+
+    let _ = event_sender.send(GlobalEvent::UpdatePacks(packs));
+    let _ = event_sender.send(GlobalEvent::ProcessHits {
+        available,
+        downloading,
+        processing,
+    });
+
+    Ok(summary)
+}
+
+/// reloads the static hits whenever their sources change, if HITS_SOURCES_WATCH is set to true
+
+#[derive(Default)]
+pub struct HitsWatchService {}
+
+#[rocket::async_trait]
+impl Fairing for HitsWatchService {
+    fn info(&self) -> Info {
+        Info {
+            name: "Reload hits when their sources change",
+            kind: Kind::Liftoff,
+        }
+    }
+
+    async fn on_liftoff(&self, rocket: &Rocket<Orbit>) {
+        if !env::var("HITS_SOURCES_WATCH")
+            .map(|v| v == "true" || v == "1")
+            .unwrap_or(false)
+        {
+            return;
+        }
+
+        let mut files = HashSet::<PathBuf>::new();
+        let mut dirs = HashSet::<PathBuf>::new();
+
+        for source in hits_sources()
+            .into_iter()
+            .filter(|s| s.as_os_str() != "bundled")
+        {
+            let Ok(source) = source.canonicalize() else {
+                rocket::warn!("Unable to watch {}, it doesn't exist", source.display());
+                continue;
+            };
+
+            if source.is_dir() {
+                dirs.insert(source);
+            } else {
+                files.insert(source);
             }
         }
 
-        for hit in hits.values() {
-            if static_hits.get_hit(&HitId::Id(hit.id)).is_none() && !hit.custom
-                || (hit.custom && hit.marked_for_deletion)
+        let (tx, mut rx) = unbounded_channel::<()>();
+        let watched_files = files.clone();
+        let watched_dirs = dirs.clone();
+
+        let mut watcher = match notify::recommended_watcher(move |event: notify::Result<Event>| {
+            if let Ok(event) = event
+                && !event.kind.is_access()
+                && event.paths.iter().any(|p| {
+                    watched_files.contains(p)
+                        || (p.parent().is_some_and(|d| watched_dirs.contains(d))
+                            && matches!(
+                                p.extension().and_then(|e| e.to_str()),
+                                Some("yml" | "yaml")
+                            ))
+                })
             {
-                rocket::info!(
-                    "Deleting hit {} (custom: {}, marked for deletion: {})",
-                    hit.id,
-                    hit.custom,
-                    hit.marked_for_deletion
-                );
-                let _ = sqlx::query!("DELETE FROM hits WHERE id = $1", hit.id)
-                    .execute(&db.0)
-                    .await;
+                let _ = tx.send(());
+            }
+        }) {
+            Ok(watcher) => watcher,
+            Err(e) => {
+                rocket::error!("Unable to watch the hit sources: {}", e);
+                return;
+            }
+        };
+
+        // editors tend to replace files instead of writing them, so we watch their directories
+        for path in dirs
+            .into_iter()
+            .chain(
+                files
+                    .iter()
+                    .filter_map(|f| f.parent().map(|p| p.to_path_buf())),
+            )
+            .collect::<HashSet<_>>()
+        {
+            if let Err(e) = watcher.watch(&path, RecursiveMode::NonRecursive) {
+                rocket::error!("Unable to watch {}: {}", path.display(), e);
             }
         }
 
-        rocket::info!("Finished merging database.");
+        let db = HitsterConfig::fetch(rocket).unwrap().0.clone();
+        let serv = rocket.state::<ServiceStore>().unwrap().clone();
+        let event_sender = rocket.state::<Sender<GlobalEvent>>().unwrap().clone();
 
-        Ok(rocket)
+        rocket::tokio::spawn(async move {
+            let _watcher = watcher;
+
+            while rx.recv().await.is_some() {
+                // give the changes some time to settle
+                sleep(Duration::from_secs(2)).await;
+                while rx.try_recv().is_ok() {}
+
+                rocket::info!("Hit sources changed, reloading...");
+
+                match reload_hits(&db, &serv, &event_sender).await {
+                    Ok(summary) => rocket::info!("Reloaded hits: {:?}", summary),
+                    Err(e) => {
+                        for error in e.errors.iter() {
+                            rocket::error!("{}", error);
+                        }
+                        rocket::error!(
+                            "Unable to reload the hits ({}), keeping the current ones.",
+                            e.message
+                        );
+                    }
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::Connection;
+
+    async fn connection() -> SqliteConnection {
+        let mut conn = SqliteConnection::connect("sqlite::memory:").await.unwrap();
+        sqlx::migrate!().run(&mut conn).await.unwrap();
+        conn
+    }
+
+    fn static_hit(title: &str) -> Hit {
+        Hit {
+            artist: "Artist".into(),
+            title: title.into(),
+            belongs_to: "".into(),
+            year: 1985,
+            packs: vec![],
+            playback_offset: 0,
+            playback_length: None,
+            snippets: vec![],
+            id: Uuid::new_v4(),
+            yt_id: "dQw4w9WgXcQ".into(),
+            source: AudioSource::YouTube,
+            location: "".into(),
+            last_modified: OffsetDateTime::now_utc(),
+            downloaded: false,
+        }
+    }
+
+    fn row(hit: &Hit) -> HitRow {
+        HitRow {
+            id: hit.id,
+            yt_id: hit.yt_id.clone(),
+            source: <&'static str>::from(hit.source).into(),
+            location: hit.location.clone(),
+            last_modified: hit.last_modified,
+            custom: false,
+            marked_for_deletion: false,
+        }
+    }
+
+    /// merge bases for a hit with the given versions within the database and as merged last
+    fn merge_bases(hit: &Hit, ours: &str, base: Option<&str>) -> MergeBases {
+        let version = |title: &str| {
+            HitVersion::from(&Hit {
+                title: title.into(),
+                ..hit.clone()
+            })
+        };
+
+        MergeBases {
+            versions: HashMap::from([(hit.id, version(ours))]),
+            bases: base
+                .map(|b| HashMap::from([(hit.id, version(b))]))
+                .unwrap_or_default(),
+            conflicts: HashSet::new(),
+            pending: vec![],
+        }
+    }
+
+    async fn conflicts(conn: &mut SqliteConnection) -> i64 {
+        sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM hits_merge_conflicts")
+            .fetch_one(conn)
+            .await
+            .unwrap()
+    }
+
+    #[rocket::async_test]
+    async fn merge_keeps_changes_of_the_database() {
+        let mut conn = connection().await;
+        let mut summary = MergeSummary::default();
+        let hit = static_hit("base");
+        let mut bases = merge_bases(&hit, "ours", Some("base"));
+
+        assert!(
+            !bases
+                .merge_hit(&mut conn, &row(&hit), &hit, &mut summary)
+                .await
+        );
+        assert!(bases.pending.is_empty());
+        assert_eq!(summary.conflicts, 0);
+    }
+
+    #[rocket::async_test]
+    async fn merge_applies_changes_of_the_codebase() {
+        let mut conn = connection().await;
+        let mut summary = MergeSummary::default();
+        let hit = static_hit("theirs");
+        let mut bases = merge_bases(&hit, "base", Some("base"));
+
+        assert!(
+            bases
+                .merge_hit(&mut conn, &row(&hit), &hit, &mut summary)
+                .await
+        );
+        assert_eq!(bases.pending, vec![(hit.id, HitVersion::from(&hit))]);
+    }
+
+    #[rocket::async_test]
+    async fn merge_accepts_identical_changes() {
+        let mut conn = connection().await;
+        let mut summary = MergeSummary::default();
+        let hit = static_hit("both");
+        let mut bases = merge_bases(&hit, "both", Some("base"));
+
+        assert!(
+            !bases
+                .merge_hit(&mut conn, &row(&hit), &hit, &mut summary)
+                .await
+        );
+        assert_eq!(bases.pending, vec![(hit.id, HitVersion::from(&hit))]);
+        assert_eq!(conflicts(&mut conn).await, 0);
+    }
+
+    #[rocket::async_test]
+    async fn merge_records_conflicting_changes() {
+        let mut conn = connection().await;
+        let mut summary = MergeSummary::default();
+        let hit = static_hit("theirs");
+        let mut bases = merge_bases(&hit, "ours", Some("base"));
+
+        sqlx::query(
+            "INSERT INTO hits (id, title, artist, yt_id, year, playback_offset, belongs_to, last_modified, downloaded, custom, marked_for_deletion) VALUES (?, 'ours', '', '', 0, 0, '', ?, ?, ?, ?)",
+        )
+        .bind(hit.id)
+        .bind(hit.last_modified)
+        .bind(false)
+        .bind(false)
+        .bind(false)
+        .execute(&mut conn)
+        .await
+        .unwrap();
+
+        assert!(
+            !bases
+                .merge_hit(&mut conn, &row(&hit), &hit, &mut summary)
+                .await
+        );
+        assert!(bases.pending.is_empty());
+        assert_eq!(summary.conflicts, 1);
+        assert_eq!(conflicts(&mut conn).await, 1);
+
+        // the conflict goes away once the codebase returns to the merge base
+        let hit = Hit {
+            title: "base".into(),
+            ..hit
+        };
+        bases.conflicts.insert(hit.id);
+
+        assert!(
+            !bases
+                .merge_hit(&mut conn, &row(&hit), &hit, &mut summary)
+                .await
+        );
+        assert_eq!(conflicts(&mut conn).await, 0);
+    }
+
+    #[rocket::async_test]
+    async fn merge_without_base_compares_modification_dates() {
+        let mut conn = connection().await;
+        let mut summary = MergeSummary::default();
+        let hit = static_hit("theirs");
+        let mut bases = merge_bases(&hit, "ours", None);
+        let mut older = row(&hit);
+        older.last_modified = hit.last_modified - time::Duration::days(1);
+
+        assert!(bases.merge_hit(&mut conn, &older, &hit, &mut summary).await);
+        assert!(
+            !bases
+                .merge_hit(&mut conn, &row(&hit), &hit, &mut summary)
+                .await
+        );
+        assert_eq!(bases.pending.len(), 2);
     }
 }
@@ -1,33 +1,41 @@
 use crate::{
-    GlobalEvent,
+    GlobalEvent, HitsterConfig,
+    audio::{AudioRequest, AudioResponse, padding},
     games::{
-        ConfirmationPayload, CreateGamePayload, GameEvent, GameMode, GamePayload,
-        GameSettingsPayload, GameState, SlotPayload,
+        AudioTokenPayload, ConfirmationPayload, CreateGamePayload, GameCommand, GameEvent,
+        GameEventQueue, GameMode, GamePayload, GameSettingsPayload, GameSocketMessage, GameState,
+        LastEventId, SlotPayload, finish_game, get_history,
     },
     responses::{
-        ClaimHitError, ConfirmSlotError, GamesResponse, GetGameError, GuessSlotError, HitError,
-        JoinGameError, LeaveGameError, MessageResponse, SkipHitError, StartGameError,
-        StopGameError, UpdateGameError,
+        ClaimHitError, ConfirmSlotError, GameHistoryResponse, GamesResponse, GetGameError,
+        GuessSlotError, HitError, JoinGameError, KickPlayerError, LeaveGameError, MessageResponse,
+        SkipHitError, StartGameError, StopGameError, UpdateGameError,
     },
     services::ServiceStore,
+    transcoding,
     users::UserAuthenticator,
+    websocket::{Channel, Message, WebSocket},
 };
+use hitster_core::User;
 use rocket::{
-    Shutdown, State,
-    fs::NamedFile,
-    futures::stream::Stream,
+    Route, Shutdown, State,
+    futures::{SinkExt, StreamExt, stream::Stream},
+    http::Accept,
     response::{
         status::Created,
         stream::{Event, EventStream},
     },
     serde::json::Json,
-    tokio::{
-        select,
-        sync::broadcast::{Sender, error::RecvError},
-    },
+    tokio::{fs::read, select, sync::broadcast::Sender},
 };
+use rocket_db_pools::Connection;
 use rocket_okapi::openapi;
-use std::{default::Default, path::PathBuf};
+use std::{
+    default::Default,
+    hash::{DefaultHasher, Hash, Hasher},
+    io,
+    path::PathBuf,
+};
 use uuid::Uuid;
 
 /// # Create a new game
@@ -63,7 +71,7 @@ pub fn create_game(
     }
 
     if mode == GameMode::Public {
-        let _ = queue.send(GlobalEvent::CreateGame((&game).into()));
+        let _ = queue.send(GlobalEvent::CreateGame(Box::new((&game).into())));
     }
 
     Created::new(format!("/games/{}", game.id)).body(Json((&game).into()))
@@ -102,15 +110,17 @@ pub fn get_all_games(
 /// Local games will usually be joined automatically and cannot be joined by users other than the creator. The player parameter however can be used to create virtual players who join the local game instead.
 /// Private games can be joined if the id is known, e.g. by sharing the game link.
 /// Public games can be joined by everyone.
+/// If the game is played in teams, every user joins as a team of their own by default. Provide the id of an existing team via the team parameter to become a member of that team instead. This works for virtual players as well.
 
 #[openapi(tag = "Games")]
-#[patch("/games/<game_id>/join/<player..>")]
+#[patch("/games/<game_id>/join/<player..>?<team>")]
 pub async fn join_game(
     game_id: &str,
     player: PathBuf,
+    team: Option<Uuid>,
     user: UserAuthenticator,
     serv: &State<ServiceStore>,
-    queue: &State<Sender<GameEvent>>,
+    queue: &State<GameEventQueue>,
 ) -> Result<Json<MessageResponse>, JoinGameError> {
     let game_svc = serv.game_service();
     let games = game_svc.lock();
@@ -122,11 +132,12 @@ pub async fn join_game(
             player
                 .to_str()
                 .and_then(|p| if !p.is_empty() { Some(p) } else { None }),
+            team,
         )
         .map(|p| {
             let _ = queue.send(GameEvent {
                 game_id: game_id.into(),
-                event: "join".into(),
+                event: if team.is_some() { "team" } else { "join" }.into(),
                 players: Some(vec![(&p).into()]),
                 ..Default::default()
             });
@@ -137,10 +148,44 @@ pub async fn join_game(
         })
 }
 
+/// # Spectate a game
+///
+/// Any authenticated user who isn't playing can watch a game as a spectator, even while it is running.
+/// Spectators receive all game events and can listen to a hit once it got revealed, but they can't guess, intercept or confirm.
+/// Local games can't be spectated. Use the leave endpoint to stop spectating.
+
+#[openapi(tag = "Games")]
+#[patch("/games/<game_id>/spectate")]
+pub async fn spectate_game(
+    game_id: &str,
+    user: UserAuthenticator,
+    serv: &State<ServiceStore>,
+    queue: &State<GameEventQueue>,
+) -> Result<Json<MessageResponse>, JoinGameError> {
+    let game_svc = serv.game_service();
+    let games = game_svc.lock();
+
+    games.spectate(game_id, &user.0).map(|_| {
+        let _ = queue.send(GameEvent {
+            game_id: game_id.into(),
+            event: "spectate".into(),
+            spectators: games.get(game_id, Some(&user.0)).map(|g| g.spectators),
+            ..Default::default()
+        });
+
+        Json(MessageResponse {
+            message: "spectating the game successfully".into(),
+            r#type: "success".into(),
+        })
+    })
+}
+
 /// # (forcefully) leave a game
 ///
 /// If called without any additional parameter, the authenticated user will leave the provided game.
 /// If the authenticated user is the creator of said game, you can provide a player id to kick them from the game instead.
+/// If the game is played in teams, providing the id of a team member only removes this member from their team, while the team id kicks the whole team.
+/// Spectators use this endpoint to stop spectating, the creator can kick them by providing their id as well.
 
 #[openapi(tag = "Games")]
 #[patch("/games/<game_id>/leave/<player_id..>")]
@@ -149,57 +194,212 @@ pub async fn leave_game(
     player_id: PathBuf,
     user: UserAuthenticator,
     serv: &State<ServiceStore>,
-    game_event_queue: &State<Sender<GameEvent>>,
+    game_event_queue: &State<GameEventQueue>,
     global_event_queue: &State<Sender<GlobalEvent>>,
 ) -> Result<Json<MessageResponse>, LeaveGameError> {
     let game_svc = serv.game_service();
     let games = game_svc.lock();
+    let player_id = player_id.to_str().and_then(|p| Uuid::parse_str(p).ok());
     let old_mode = games
         .get(game_id, Some(&user.0))
         .map(|g| g.mode)
         .unwrap_or(GameMode::Public);
+    let old_creator = games
+        .get(game_id, Some(&user.0))
+        .and_then(|g| g.players.iter().find(|p| p.creator).map(|p| p.id));
 
-    games
-        .leave(
-            game_id,
-            &user.0,
-            player_id.to_str().and_then(|p| Uuid::parse_str(p).ok()),
-        )
-        .map(|p| {
+    if games
+        .get(game_id, Some(&user.0))
+        .map(|g| {
+            g.spectators
+                .iter()
+                .any(|s| s.id == player_id.unwrap_or(user.0.id))
+        })
+        .unwrap_or(false)
+    {
+        return games.stop_spectating(game_id, &user.0, player_id).map(|_| {
             let _ = game_event_queue.send(GameEvent {
                 game_id: game_id.into(),
-                event: "leave".into(),
-                players: Some(vec![(&p).into()]),
+                event: "spectate".into(),
+                spectators: games.get(game_id, Some(&user.0)).map(|g| g.spectators),
                 ..Default::default()
             });
 
-            let new_state = games
-                .get(game_id, Some(&user.0))
-                .map(|g| g.state)
-                .unwrap_or_else(|| {
-                    if old_mode == GameMode::Public {
-                        let _ =
-                            global_event_queue.send(GlobalEvent::RemoveGame(game_id.to_string()));
-                    }
+            Json(MessageResponse {
+                message: "stopped spectating the game successfully".into(),
+                r#type: "success".into(),
+            })
+        });
+    }
 
-                    GameState::Open
-                });
+    let res = games.leave(game_id, &user.0, player_id).map(|p| {
+        // the player is still around if only a member left their team
+        let team = games
+            .get(game_id, Some(&user.0))
+            .map(|g| g.players.iter().any(|o| o.id == p.id))
+            .unwrap_or(false);
+
+        let _ = game_event_queue.send(GameEvent {
+            game_id: game_id.into(),
+            event: if team { "team" } else { "leave" }.into(),
+            players: Some(vec![(&p).into()]),
+            ..Default::default()
+        });
+
+        let new_state = games
+            .get(game_id, Some(&user.0))
+            .map(|g| g.state)
+            .unwrap_or_else(|| {
+                if old_mode == GameMode::Public {
+                    let _ = global_event_queue.send(GlobalEvent::RemoveGame(game_id.to_string()));
+                }
+
+                GameState::Open
+            });
+
+        let _ = game_event_queue.send(GameEvent {
+            game_id: game_id.into(),
+            event: "change_state".into(),
+            state: Some(new_state),
+            players: games
+                .get(game_id, Some(&user.0))
+                .map(|g| g.players.iter().map(|p| p.into()).collect::<Vec<_>>()),
+            remaining_time: games
+                .get(game_id, Some(&user.0))
+                .and_then(|g| g.remaining_time()),
+            ..Default::default()
+        });
 
+        if let Some(creator) = games
+            .get(game_id, Some(&user.0))
+            .and_then(|g| g.players.into_iter().find(|p| p.creator))
+            .filter(|p| Some(p.id) != old_creator)
+        {
             let _ = game_event_queue.send(GameEvent {
                 game_id: game_id.into(),
-                event: "change_state".into(),
-                state: Some(new_state),
-                players: games
-                    .get(game_id, Some(&user.0))
-                    .map(|g| g.players.iter().map(|p| p.into()).collect::<Vec<_>>()),
+                event: "creator".into(),
+                players: Some(vec![(&creator).into()]),
+                ..Default::default()
+            });
+        }
+
+        Json(MessageResponse {
+            message: "left the game successfully".into(),
+            r#type: "success".into(),
+        })
+    });
+    let game = games.get(game_id, Some(&user.0));
+
+    drop(games);
+
+    // the deck might've run out with the hit of the player who left
+    if res.is_ok()
+        && let Some(game) = game
+    {
+        finish_game(&game, &game_svc, game_event_queue);
+    }
+
+    res
+}
+
+/// # Kick a player from a game
+///
+/// Only the creator of a game can kick players, team members or spectators, regardless of the game mode.
+/// Set the ban parameter to additionally ban the kicked users from joining or spectating the game again. Kicking a team bans all of its members.
+/// If the game is running and only one player remains, the game will be stopped.
+
+#[openapi(tag = "Games")]
+#[patch("/games/<game_id>/kick/<player_id>?<ban>")]
+pub async fn kick_player(
+    game_id: &str,
+    player_id: Uuid,
+    ban: Option<bool>,
+    user: UserAuthenticator,
+    serv: &State<ServiceStore>,
+    queue: &State<GameEventQueue>,
+) -> Result<Json<MessageResponse>, KickPlayerError> {
+    let game_svc = serv.game_service();
+    let games = game_svc.lock();
+
+    let res = games
+        .kick(game_id, &user.0, player_id, ban.unwrap_or(false))
+        .map(|p| {
+            let game = games.get(game_id, Some(&user.0));
+
+            let _ = queue.send(GameEvent {
+                game_id: game_id.into(),
+                event: "kick".into(),
+                players: p.as_ref().map(|p| vec![p.into()]),
+                spectators: game
+                    .as_ref()
+                    .filter(|_| p.is_none())
+                    .map(|g| g.spectators.clone()),
+                banned: game
+                    .as_ref()
+                    .filter(|_| ban == Some(true))
+                    .map(|g| g.banned.clone()),
                 ..Default::default()
             });
 
+            if let Some(game) = game.filter(|_| p.is_some()) {
+                let _ = queue.send(GameEvent {
+                    game_id: game_id.into(),
+                    event: "change_state".into(),
+                    state: Some(game.state),
+                    players: Some(game.players.iter().map(|p| p.into()).collect::<Vec<_>>()),
+                    remaining_time: game.remaining_time(),
+                    ..Default::default()
+                });
+            }
+
             Json(MessageResponse {
-                message: "left the game successfully".into(),
+                message: "kicked successfully".into(),
                 r#type: "success".into(),
             })
+        });
+    let game = games.get(game_id, Some(&user.0));
+
+    drop(games);
+
+    // the deck might've run out with the hit of the kicked player
+    if res.is_ok()
+        && let Some(game) = game
+    {
+        finish_game(&game, &game_svc, queue);
+    }
+
+    res
+}
+
+/// # Unban a user from a game
+///
+/// Only the creator of a game can lift a ban, the user can join or spectate the game again afterwards.
+
+#[openapi(tag = "Games")]
+#[patch("/games/<game_id>/unban/<user_id>")]
+pub async fn unban_user(
+    game_id: &str,
+    user_id: Uuid,
+    user: UserAuthenticator,
+    serv: &State<ServiceStore>,
+    queue: &State<GameEventQueue>,
+) -> Result<Json<MessageResponse>, KickPlayerError> {
+    let game_svc = serv.game_service();
+    let games = game_svc.lock();
+
+    games.unban(game_id, &user.0, user_id).map(|_| {
+        let _ = queue.send(GameEvent {
+            game_id: game_id.into(),
+            event: "unban".into(),
+            banned: games.get(game_id, Some(&user.0)).map(|g| g.banned),
+            ..Default::default()
+        });
+
+        Json(MessageResponse {
+            message: "unbanned successfully".into(),
+            r#type: "success".into(),
         })
+    })
 }
 
 /// # Start a game
@@ -212,7 +412,7 @@ pub async fn start_game(
     game_id: &str,
     user: UserAuthenticator,
     serv: &State<ServiceStore>,
-    queue: &State<Sender<GameEvent>>,
+    queue: &State<GameEventQueue>,
 ) -> Result<Json<MessageResponse>, StartGameError> {
     let game_svc = serv.game_service();
     let games = game_svc.lock();
@@ -223,6 +423,7 @@ pub async fn start_game(
             event: "change_state".into(),
             state: Some(g.state),
             players: Some(g.players.iter().map(|p| p.into()).collect::<Vec<_>>()),
+            remaining_time: g.remaining_time(),
             ..Default::default()
         });
 
@@ -243,7 +444,7 @@ pub async fn stop_game(
     game_id: &str,
     user: UserAuthenticator,
     serv: &State<ServiceStore>,
-    queue: &State<Sender<GameEvent>>,
+    queue: &State<GameEventQueue>,
 ) -> Result<Json<MessageResponse>, StopGameError> {
     serv.game_service()
         .lock()
@@ -288,9 +489,59 @@ pub fn get_game(
     }
 }
 
+/// # Get the history of a game
+///
+/// Retrieve everything that happened within a game in chronological order, e.g. which slots were guessed, who intercepted, which hits were revealed, skipped or claimed and how the tokens changed.
+/// The history is kept after the game was removed, so finished games can still be reviewed.
+/// Local games which are still running can only be inspected by their creator.
+
+#[openapi(tag = "Games")]
+#[get("/games/<game_id>/history")]
+pub async fn get_game_history(
+    game_id: &str,
+    user: Option<UserAuthenticator>,
+    serv: &State<ServiceStore>,
+    mut db: Connection<HitsterConfig>,
+) -> Result<Json<GameHistoryResponse>, GetGameError> {
+    let (exists, visible) = {
+        let game_svc = serv.game_service();
+        let games = game_svc.lock();
+
+        (
+            games.exists(game_id),
+            games.get(game_id, user.map(|u| u.0).as_ref()).is_some(),
+        )
+    };
+
+    if exists && !visible {
+        return Err(GetGameError {
+            message: "game id not found".into(),
+            http_status_code: 404,
+        });
+    }
+
+    let history = get_history(&mut db, game_id)
+        .await
+        .map_err(|_| GetGameError {
+            message: "failed to read history".into(),
+            http_status_code: 500,
+        })?;
+
+    if !exists && history.is_empty() {
+        return Err(GetGameError {
+            message: "game id not found".into(),
+            http_status_code: 404,
+        });
+    }
+
+    Ok(Json(GameHistoryResponse { history }))
+}
+
 /// # Subscribe to game events
 ///
 /// All events that affect the game will be distributed via this event stream (Server-Side Events) in real-time.
+/// Every event carries an id which increases with every event of the game. When reconnecting, clients can send the id of the last event they received via the Last-Event-ID header (browsers do so automatically) to receive all events they missed in the meantime.
+/// If the missed events aren't available anymore, e.g. because the server restarted, a snapshot event with the current state of the game gets sent instead.
 /// The following table lists all the possible payloads that are provided as JSON:
 ///
 /// <table>
@@ -301,7 +552,7 @@ pub fn get_game(
 ///   </thead>
 ///   <tbody>
 ///     <tr>
-///       <th rowSpan="6">change_state</th>
+///       <th rowSpan="9">change_state</th>
 ///     </tr>
 ///     <tr>
 ///       <td>hit</td>
@@ -312,16 +563,28 @@ pub fn get_game(
 ///       <td>this field is set when someone wins a hit, mostly when entering GameState.Confirming state</td>
 ///     </tr>
 ///     <tr>
+///       <td>name_guess</td>
+///       <td>this field is set when the hit is flipped up and the turn player named title and artist, including the verdict</td>
+///     </tr>
+///     <tr>
 ///       <td>players</td>
 ///       <td>Array of player objects in the game</td>
 ///     </tr>
 ///     <tr>
+///       <td>remaining_time</td>
+///       <td>the time in seconds until the new state times out, if a timeout is configured for it</td>
+///     </tr>
+///     <tr>
 ///       <td>state</td>
 ///       <td>a state as specified within the GameState enum</td>
 ///     </tr>
 ///     <tr>
 ///       <td>winner</td>
-///       <td>this field is set when a player wins the game, e.g. after someone claims a hit or guesses correctly</td>
+///       <td>this field is set when a single player wins the game, e.g. after someone claims a hit or guesses correctly</td>
+///     </tr>
+///     <tr>
+///       <td>winners</td>
+///       <td>this field is set when the game is over and contains all players who won, more than one if there is a tie</td>
 ///     </tr>
 ///     <tr>
 ///       <th rowSpan="3">claim</th>
@@ -335,6 +598,11 @@ pub fn get_game(
 ///       <td>Array of players who claimed a hit</td>
 ///     </tr>
 ///     <tr>
+///       <td>creator</td>
+///       <td>players</td>
+///       <td>Array with the player who became the new creator after the previous creator left</td>
+///     </tr>
+///     <tr>
 ///       <td>guess</td>
 ///       <td>players</td>
 ///       <td>Array of players who last updated their guess</td>
@@ -345,12 +613,42 @@ pub fn get_game(
 ///       <td>Array of player objects who joined the game</td>
 ///     </tr>
 ///     <tr>
+///       <th rowSpan="4">kick</th>
+///     </tr>
+///     <tr>
+///       <td>banned</td>
+///       <td>Array of all users who are banned from the game, only set if the kicked users got banned</td>
+///     </tr>
+///     <tr>
+///       <td>players</td>
+///       <td>Array with the kicked player, or the team the kicked member was part of, a change_state event follows</td>
+///     </tr>
+///     <tr>
+///       <td>spectators</td>
+///       <td>Array of all users who still spectate the game, only set if a spectator got kicked</td>
+///     </tr>
+///     <tr>
 ///       <td>leave</td>
 ///       <td>players</td>
 ///       <td>Array of player objects who left the game</td>
 ///     </tr>
 ///     <tr>
-///       <th rowSpan="3">skip</th>
+///       <td>snapshot</td>
+///       <td>game</td>
+///       <td>the GamePayload with the full state of the game, sent instead of events which can't be replayed</td>
+///     </tr>
+///     <tr>
+///       <td>spectate</td>
+///       <td>spectators</td>
+///       <td>Array of all users who currently spectate the game</td>
+///     </tr>
+///     <tr>
+///       <td>team</td>
+///       <td>players</td>
+///       <td>Array of teams whose members changed</td>
+///     </tr>
+///     <tr>
+///       <th rowSpan="4">skip</th>
 ///     </tr>
 ///     <tr>
 ///       <td>hit</td>
@@ -361,7 +659,27 @@ pub fn get_game(
 ///       <td>Array of players who skipped a hit</td>
 ///     </tr>
 ///     <tr>
-///       <td>update</td>
+///       <td>remaining_time</td>
+///       <td>the time in seconds the player has to guess the new hit, if a timeout is configured</td>
+///     </tr>
+///     <tr>
+///       <td>timeout</td>
+///       <td>players</td>
+///       <td>Array of players who didn't react in time, a change_state event follows</td>
+///     </tr>
+///     <tr>
+///       <td>unban</td>
+///       <td>banned</td>
+///       <td>Array of all users who are still banned from the game</td>
+///     </tr>
+///     <tr>
+///       <th rowSpan="3">update</th>
+///     </tr>
+///     <tr>
+///       <td>players</td>
+///       <td>Array of player objects in the game, they change when switching from or to teams</td>
+///     </tr>
+///     <tr>
 ///       <td>settings</td>
 ///       <td>GameSettingsPayload with the updated game settings</td>
 ///     </tr>
@@ -372,55 +690,320 @@ pub fn get_game(
 #[get("/games/<game_id>/events")]
 pub async fn events(
     game_id: String,
-    queue: &State<Sender<GameEvent>>,
+    last_event_id: LastEventId,
+    user: Option<UserAuthenticator>,
+    serv: &State<ServiceStore>,
+    queue: &State<GameEventQueue>,
     mut end: Shutdown,
 ) -> EventStream<impl Stream<Item = Event>> {
-    let mut rx = queue.subscribe();
+    let mut events = queue.subscribe_game(
+        &game_id,
+        last_event_id.0,
+        user.map(|u| u.0),
+        serv.game_service(),
+    );
+
     EventStream! {
         loop {
             let msg = select! {
-                msg = rx.recv() => match msg {
-                    Ok(msg) => msg,
-                    Err(RecvError::Closed) => break,
-                    Err(RecvError::Lagged(_)) => continue,
+                msg = events.next() => match msg {
+                    Some(msg) => msg,
+                    None => break,
                 },
                 _ = &mut end => break,
             };
 
-            if msg.game_id == game_id {
-                yield Event::json(&msg).event(msg.event);
+            yield Event::json(&msg).event(msg.event).id(msg.id.to_string());
+        }
+    }
+}
+
+/// # Play a game via websocket
+///
+/// Upgrade the connection to a websocket to play a game without mixing REST calls and the event stream.
+/// The socket pushes all events of the game, the same ones the event stream sends, as JSON objects like {"type": "event", "id": 1, "event": "join", "data": {...}}. Reconnecting clients can provide the Last-Event-ID header to catch up on the events they missed.
+/// Clients can send the following commands as JSON objects, e.g. {"command": "guess", "slot": {"id": 1}}. They require an authenticated user and behave exactly like their REST counterparts:
+///
+///   * join with the optional player and team fields
+///
+///   * guess with the slot field containing a SlotPayload and an optional player_id
+///
+///   * confirm with the confirm field
+///
+///   * skip and claim with an optional player_id
+///
+///   * update with the settings field containing a GameSettingsPayload
+///
+/// Every command gets answered with either {"type": "success", "command": ..., "message": ...} or {"type": "error", "command": ..., "message": ..., "http_status_code": ...}, where the status code is the one the REST endpoint would respond with.
+
+#[openapi(tag = "Games")]
+#[get("/games/<game_id>/socket")]
+pub fn socket(
+    game_id: String,
+    socket: WebSocket,
+    last_event_id: LastEventId,
+    user: Option<UserAuthenticator>,
+    serv: &State<ServiceStore>,
+    queue: &State<GameEventQueue>,
+    mut end: Shutdown,
+) -> Channel<'static> {
+    let serv = serv.inner().clone();
+    let queue = queue.inner().clone();
+    let user = user.map(|u| u.0);
+    let mut events =
+        queue.subscribe_game(&game_id, last_event_id.0, user.clone(), serv.game_service());
+
+    socket.channel(move |mut stream| {
+        Box::pin(async move {
+            loop {
+                let msg: GameSocketMessage = select! {
+                    event = events.next() => match event {
+                        Some(event) => event.into(),
+                        None => break,
+                    },
+                    msg = stream.next() => match msg {
+                        Some(Ok(Message::Text(text))) => {
+                            match serde_json::from_str::<GameCommand>(&text) {
+                                Ok(command) => {
+                                    run_command(
+                                        &game_id,
+                                        command,
+                                        user.as_ref(),
+                                        (&serv).into(),
+                                        (&queue).into(),
+                                    )
+                                    .await
+                                }
+                                Err(e) => GameSocketMessage::Error {
+                                    command: None,
+                                    message: e.to_string(),
+                                    http_status_code: 400,
+                                },
+                            }
+                        }
+                        Some(Ok(Message::Close(_))) | None => break,
+                        Some(Ok(_)) => continue,
+                        Some(Err(e)) => return Err(io::Error::other(e)),
+                    },
+                    _ = &mut end => break,
+                };
+
+                stream
+                    .send(Message::Text(serde_json::to_string(&msg).unwrap()))
+                    .await
+                    .map_err(io::Error::other)?;
             }
+
+            let _ = stream.close(None).await;
+
+            Ok(())
+        })
+    })
+}
+
+/// execute a command received via websocket by calling the matching REST endpoint
+async fn run_command(
+    game_id: &str,
+    command: GameCommand,
+    user: Option<&User>,
+    serv: &State<ServiceStore>,
+    queue: &State<GameEventQueue>,
+) -> GameSocketMessage {
+    let name: &'static str = (&command).into();
+
+    let Some(user) = user.cloned().map(UserAuthenticator) else {
+        return GameSocketMessage::Error {
+            command: Some(name.into()),
+            message: "you need to be logged in to send commands".into(),
+            http_status_code: 401,
+        };
+    };
+
+    let player_path = |player_id: Option<Uuid>| {
+        PathBuf::from(player_id.map(|p| p.to_string()).unwrap_or_default())
+    };
+
+    let res = match command {
+        GameCommand::Join { player, team } => join_game(
+            game_id,
+            PathBuf::from(player.unwrap_or_default()),
+            team,
+            user,
+            serv,
+            queue,
+        )
+        .await
+        .map_err(|e| (e.message, e.http_status_code)),
+        GameCommand::Guess { slot, player_id } => guess_slot(
+            game_id,
+            player_path(player_id),
+            Json(slot),
+            user,
+            serv,
+            queue,
+        )
+        .map_err(|e| (e.message, e.http_status_code)),
+        GameCommand::Confirm { confirm } => confirm_slot(
+            game_id,
+            Json(ConfirmationPayload { confirm }),
+            user,
+            serv,
+            queue,
+        )
+        .map_err(|e| (e.message, e.http_status_code)),
+        GameCommand::Skip { player_id } => {
+            skip_hit(game_id, player_path(player_id), user, serv, queue)
+                .map_err(|e| (e.message, e.http_status_code))
         }
+        GameCommand::Claim { player_id } => {
+            claim_hit(game_id, player_path(player_id), user, serv, queue)
+                .map_err(|e| (e.message, e.http_status_code))
+        }
+        GameCommand::Update { settings } => update_game(game_id, Json(settings), user, serv, queue)
+            .map_err(|e| (e.message, e.http_status_code)),
+    };
+
+    match res {
+        Ok(res) => GameSocketMessage::Success {
+            command: name.into(),
+            message: res.into_inner().message,
+        },
+        Err((message, http_status_code)) => GameSocketMessage::Error {
+            command: Some(name.into()),
+            message,
+            http_status_code,
+        },
     }
 }
 
 /// # Get the audio file for a revealed hit
 ///
 /// Retrieve the audio file for a specific revealed hit in a game.
-/// The audio files are trimmed (see FullHitPayload.playback_offset) and come in MP3 format.
+/// The audio files are trimmed (see FullHitPayload.playback_offset and FullHitPayload.playback_length).
+/// Hits with snippets play one of them instead, which one depends on the game.
+/// The format is negotiated via the Accept header (e.g. audio/ogg for Opus, audio/mp4 for AAC or audio/mpeg for MP3) among the transcoding profiles configured on the server.
+/// The first configured profile, MP3 by default, is used if none of them is acceptable.
+/// Parts of the file can be requested via the Range header. Responses carry an ETag which changes whenever the hit or its file changes, send it via If-None-Match to avoid downloading the same file again.
+/// The current hit (no hit_id) always needs to be revalidated, since it changes while the URL stays the same.
 /// If no hit_id is specified, the last revealed hit will be fetched.
 /// You can provide any hit_id of a hit that is currently in a player's possession to fetch that one instead.
+/// The current hit can only be fetched this way by players and spectators of the game once it got revealed, request an audio token (see /games/{game_id}/audio) to play it before.
 
 #[openapi(tag = "Games")]
 #[get("/games/<game_id>/hit/<hit_id..>")]
 pub async fn hit(
     game_id: &str,
     hit_id: PathBuf,
+    user: Option<UserAuthenticator>,
+    accept: Option<&Accept>,
+    audio: AudioRequest,
     serv: &State<ServiceStore>,
-) -> Result<NamedFile, HitError> {
-    let hit = serv.game_service().lock().get_hit(
+) -> Result<AudioResponse, HitError> {
+    let profile = transcoding::negotiate(accept);
+    let explicit = hit_id.to_str().and_then(|h| Uuid::parse_str(h).ok());
+    let (hit, file) = serv.game_service().lock().get_hit(
         game_id,
-        hit_id.to_str().and_then(|h| Uuid::parse_str(h).ok()),
-    );
+        explicit,
+        user.map(|u| u.0).as_ref(),
+        profile,
+    )?;
 
-    if let Ok(hit) = hit {
-        return NamedFile::open(&hit.file()).await.or(Err(HitError {
-            message: "hit file couldn't be found".into(),
-            http_status_code: 404,
-        }));
-    }
+    // the game is part of the tag so that it can't be used to recognize the hit in other games
+    let mut hasher = DefaultHasher::new();
+    game_id.hash(&mut hasher);
+    hit.id.hash(&mut hasher);
+    hit.last_modified.hash(&mut hasher);
+    file.hash(&mut hasher);
+    let etag = format!("\"{:016x}\"", hasher.finish());
+
+    // the url of the current hit stays the same while the hit changes
+    let cache_control = if explicit.is_some() {
+        "private, max-age=3600"
+    } else {
+        "private, no-cache"
+    };
+
+    let mut body = read(&file).await.or(Err(HitError {
+        message: "hit file couldn't be found".into(),
+        http_status_code: 404,
+    }))?;
+
+    profile.codec.strip_metadata(&mut body);
 
-    Err(hit.err().unwrap())
+    Ok(audio.respond(
+        body,
+        profile.codec.content_type(),
+        Some(etag),
+        cache_control,
+    ))
+}
+
+/// # Request an audio token for the current hit
+///
+/// Request a token to play the current hit, even before it got revealed.
+/// The token is only valid for the authenticated user, for a single request within a minute and until the current round ends.
+/// Only players of the game can request a token, spectators can only do so once the hit got revealed.
+
+#[openapi(tag = "Games")]
+#[post("/games/<game_id>/audio")]
+pub fn create_audio_token(
+    game_id: &str,
+    user: UserAuthenticator,
+    route: &Route,
+    serv: &State<ServiceStore>,
+) -> Result<Json<AudioTokenPayload>, HitError> {
+    let (token, expires_at) = serv
+        .game_service()
+        .lock()
+        .issue_audio_token(game_id, &user.0)?;
+
+    Ok(Json(AudioTokenPayload {
+        url: uri!(route.uri.base.clone(), audio(game_id, &token)).to_string(),
+        token,
+        expires_at,
+    }))
+}
+
+/// # Get the audio file of the current hit with a token
+///
+/// Retrieve the audio file of the current hit with a token requested via /games/{game_id}/audio.
+/// Every token can only be used for a single request, request a new one to play the hit again.
+/// The format is negotiated via the Accept header, just like when fetching revealed hits.
+/// The audio doesn't carry any tags and is padded to a multiple of 256 KiB plus a random amount of bytes, so that neither the tags nor the file size give away the hit.
+
+#[openapi(tag = "Games")]
+#[get("/games/<game_id>/audio/<token>")]
+pub async fn audio(
+    game_id: &str,
+    token: &str,
+    user: UserAuthenticator,
+    accept: Option<&Accept>,
+    audio: AudioRequest,
+    serv: &State<ServiceStore>,
+) -> Result<AudioResponse, HitError> {
+    let profile = transcoding::negotiate(accept);
+    let (_, file, jitter) = serv
+        .game_service()
+        .lock()
+        .redeem_audio_token(game_id, token, &user.0, profile)?;
+
+    let mut body = read(&file).await.or(Err(HitError {
+        message: "hit file couldn't be found".into(),
+        http_status_code: 404,
+    }))?;
+
+    profile.codec.strip_metadata(&mut body);
+
+    let amount = padding(body.len(), jitter);
+
+    profile.codec.pad(&mut body, amount);
+
+    Ok(audio.respond(
+        body,
+        profile.codec.content_type(),
+        None,
+        "private, no-store",
+    ))
 }
 
 /// # Guess a slot
@@ -429,6 +1012,7 @@ pub async fn hit(
 /// If its not the player's turn, you can leave the slot's id empty to don't step in.
 /// By default the authenticated user will guess their slot.
 /// When in a local game, the creator can guess for any virtual player by specifying the player id here.
+/// If the game has name_hits enabled, the turn player can additionally name title and artist of the hit when guessing. They will be judged as soon as the hit gets revealed.
 
 #[openapi(tag = "Games")]
 #[post(
@@ -442,7 +1026,7 @@ pub fn guess_slot(
     slot: Json<SlotPayload>,
     user: UserAuthenticator,
     serv: &State<ServiceStore>,
-    queue: &State<Sender<GameEvent>>,
+    queue: &State<GameEventQueue>,
 ) -> Result<Json<MessageResponse>, GuessSlotError> {
     let player_id = player_id.to_str().and_then(|p| Uuid::parse_str(p).ok());
     let state = serv
@@ -454,7 +1038,7 @@ pub fn guess_slot(
     let game = serv
         .game_service()
         .lock()
-        .guess(game_id, &user.0, slot.id, player_id);
+        .guess(game_id, &user.0, &slot, player_id);
 
     game.map(|mut game| {
         let _ = queue.send(GameEvent {
@@ -463,7 +1047,7 @@ pub fn guess_slot(
             players: game
                 .players
                 .iter()
-                .find(|p| p.id == player_id.unwrap_or(user.0.id))
+                .find(|p| p.contains(player_id.unwrap_or(user.0.id)))
                 .map(|p| vec![p.into()]),
             ..Default::default()
         });
@@ -471,9 +1055,10 @@ pub fn guess_slot(
         if state != game.state {
             let last_scored = game.last_scored.clone();
             let hit = game.hits_remaining.front().cloned();
-            let winner = serv.game_service().lock().get_winner(&game);
+            let name_guess = game.name_guess.clone();
+            let winners = serv.game_service().lock().get_winners(&game);
 
-            if winner.is_some() {
+            if !winners.is_empty() {
                 game = serv.game_service().lock().stop(&game.id, None).unwrap();
             }
 
@@ -490,7 +1075,18 @@ pub fn guess_slot(
                     }
                 }),
                 last_scored: last_scored.map(|p| (&p).into()),
-                winner: winner.as_ref().map(|p| p.into()),
+                name_guess: name_guess.filter(|_| game.state != GameState::Intercepting),
+                winner: if winners.len() == 1 {
+                    winners.first().map(|p| p.into())
+                } else {
+                    None
+                },
+                winners: if !winners.is_empty() {
+                    Some(winners.iter().map(|p| p.into()).collect::<Vec<_>>())
+                } else {
+                    None
+                },
+                remaining_time: game.remaining_time(),
                 ..Default::default()
             });
         }
@@ -505,6 +1101,7 @@ pub fn guess_slot(
 /// # Confirm a guess
 ///
 /// After guessing a song, confirm wether the guessing player needs to get a token for their guess.
+/// If the game has name_hits enabled, the verdict on the named title and artist decides wether the guessing player gets a token. The confirm value is only taken into account if the verdict is NameJudgement.Ambiguous.
 
 #[openapi(tag = "Games")]
 #[post("/games/<game_id>/confirm", format = "json", data = "<confirmation>")]
@@ -513,25 +1110,30 @@ pub fn confirm_slot(
     confirmation: Json<ConfirmationPayload>,
     user: UserAuthenticator,
     serv: &State<ServiceStore>,
-    queue: &State<Sender<GameEvent>>,
+    queue: &State<GameEventQueue>,
 ) -> Result<Json<MessageResponse>, ConfirmSlotError> {
-    serv.game_service()
+    let res = serv
+        .game_service()
         .lock()
-        .confirm(game_id, &user.0, confirmation.confirm)
-        .map(|game| {
-            let _ = queue.send(GameEvent {
-                game_id: game_id.into(),
-                event: "change_state".into(),
-                state: Some(game.state),
-                players: Some(game.players.iter().map(|p| p.into()).collect::<Vec<_>>()),
-                ..Default::default()
-            });
+        .confirm(game_id, &user.0, confirmation.confirm);
 
-            Json(MessageResponse {
-                message: "confirmation received".into(),
-                r#type: "success".into(),
-            })
+    res.map(|game| {
+        let _ = queue.send(GameEvent {
+            game_id: game_id.into(),
+            event: "change_state".into(),
+            state: Some(game.state),
+            players: Some(game.players.iter().map(|p| p.into()).collect::<Vec<_>>()),
+            remaining_time: game.remaining_time(),
+            ..Default::default()
+        });
+
+        finish_game(&game, &serv.game_service(), queue);
+
+        Json(MessageResponse {
+            message: "confirmation received".into(),
+            r#type: "success".into(),
         })
+    })
 }
 
 /// # Skip a hit
@@ -546,30 +1148,32 @@ pub fn skip_hit(
     player_id: PathBuf,
     user: UserAuthenticator,
     serv: &State<ServiceStore>,
-    queue: &State<Sender<GameEvent>>,
+    queue: &State<GameEventQueue>,
 ) -> Result<Json<MessageResponse>, SkipHitError> {
     let player_id = player_id.to_str().and_then(|p| Uuid::parse_str(p).ok());
-    serv.game_service()
-        .lock()
-        .skip(game_id, &user.0, player_id)
-        .map(|(game, hit)| {
-            let _ = queue.send(GameEvent {
-                game_id: game_id.into(),
-                event: "skip".into(),
-                players: game
-                    .players
-                    .iter()
-                    .find(|p| p.id == player_id.unwrap_or(user.0.id))
-                    .map(|p| vec![p.into()]),
-                hit: Some((&hit).into()),
-                ..Default::default()
-            });
+    let res = serv.game_service().lock().skip(game_id, &user.0, player_id);
 
-            Json(MessageResponse {
-                message: "skipped successfully".into(),
-                r#type: "success".into(),
-            })
+    res.map(|(game, hit)| {
+        let _ = queue.send(GameEvent {
+            game_id: game_id.into(),
+            event: "skip".into(),
+            players: game
+                .players
+                .iter()
+                .find(|p| p.contains(player_id.unwrap_or(user.0.id)))
+                .map(|p| vec![p.into()]),
+            hit: Some((&hit).into()),
+            remaining_time: game.remaining_time(),
+            ..Default::default()
+        });
+
+        finish_game(&game, &serv.game_service(), queue);
+
+        Json(MessageResponse {
+            message: "skipped successfully".into(),
+            r#type: "success".into(),
         })
+    })
 }
 
 /// # Claim a hit
@@ -584,7 +1188,7 @@ pub fn claim_hit(
     player_id: PathBuf,
     user: UserAuthenticator,
     serv: &State<ServiceStore>,
-    queue: &State<Sender<GameEvent>>,
+    queue: &State<GameEventQueue>,
 ) -> Result<Json<MessageResponse>, ClaimHitError> {
     let player_id = player_id.to_str().and_then(|p| Uuid::parse_str(p).ok());
     let res = serv
@@ -592,33 +1196,20 @@ pub fn claim_hit(
         .lock()
         .claim(game_id, &user.0, player_id);
 
-    res.map(|(mut game, hit)| {
+    res.map(|(game, hit)| {
         let _ = queue.send(GameEvent {
             game_id: game_id.into(),
             event: "claim".into(),
             players: game
                 .players
                 .iter()
-                .find(|p| p.id == player_id.unwrap_or(user.0.id))
+                .find(|p| p.contains(player_id.unwrap_or(user.0.id)))
                 .map(|p| vec![p.into()]),
             hit: Some((&hit).into()),
             ..Default::default()
         });
 
-        let winner = serv.game_service().lock().get_winner(&game);
-
-        if winner.is_some() {
-            game = serv.game_service().lock().stop(game_id, None).unwrap();
-
-            let _ = queue.send(GameEvent {
-                game_id: game_id.into(),
-                event: "change_state".into(),
-                state: Some(game.state),
-                players: Some(game.players.iter().map(|p| p.into()).collect::<Vec<_>>()),
-                winner: winner.as_ref().map(|p| p.into()),
-                ..Default::default()
-            });
-        }
+        finish_game(&game, &serv.game_service(), queue);
 
         Json(MessageResponse {
             message: "claimed hit successfully".into(),
@@ -638,16 +1229,17 @@ pub fn update_game(
     settings: Json<GameSettingsPayload>,
     user: UserAuthenticator,
     serv: &State<ServiceStore>,
-    queue: &State<Sender<GameEvent>>,
+    queue: &State<GameEventQueue>,
 ) -> Result<Json<MessageResponse>, UpdateGameError> {
     serv.game_service()
         .lock()
         .update(game_id, &user.0, &settings)
-        .map(|_| {
+        .map(|game| {
             let _ = queue.send(GameEvent {
                 game_id: game_id.into(),
                 event: "update".into(),
                 settings: Some(settings.into()),
+                players: Some(game.players.iter().map(|p| p.into()).collect::<Vec<_>>()),
                 ..Default::default()
             });
 
@@ -2,27 +2,42 @@ use crate::{
     GlobalEvent, HitsterConfig,
     games::PackPayload,
     hits::{
-        CreatePackPayload, ExportHitsQuery, FullHitPayload, HitPartsQuery, HitPayload,
-        HitQueryPart, HitSearchFilter, HitSearchQuery,
+        CreatePackPayload, DUPLICATE_MAX_YEAR_DISTANCE, ExportHitsQuery, FullHitPayload,
+        HIT_ISSUE_COLUMNS, HistoryAction, HitConflictPayload, HitConflictSide, HitDownload,
+        HitDownloadPayload, HitDownloadStatus, HitDownloadsQuery, HitDuplicatePayload,
+        HitHistoryPayload, HitIssueComment, HitIssueFilter, HitIssuePayload, HitIssuesQuery,
+        HitPartsQuery, HitPayload, HitQueryPart, HitSearchFilter, HitSearchQuery, HitsImport,
+        ImportData, ImportHitsPayload, ImportHitsQuery, MergeHitPayload, PackHistoryPayload,
+        ResolveHitConflictPayload, UpdateHitDownloadPayload, flag_duplicate_hits,
+        send_process_hits, sort_hit_downloads, update_download_count,
     },
+    merge_db::{self, HitVersion, MergeSummary},
     responses::{
-        CreateHitError, CreateHitIssueError, CreatePackError, DeleteHitError, DeleteHitIssueError,
-        DeletePackError, ExportHitsError, GetHitError, MessageResponse, PacksResponse,
-        PaginatedResponse, UpdateHitError, UpdatePackError, Yaml,
+        CancelHitDownloadError, CreateHitError, CreateHitIssueCommentError, CreateHitIssueError,
+        CreatePackError, DeleteHitError, DeleteHitIssueError, DeletePackError, ExportHitsError,
+        GetHistoryError, GetHitConflictsError, GetHitDownloadsError, GetHitDuplicatesError,
+        GetHitError, GetHitIssueCommentsError, GetHitIssuesError, ImportHitsError, MergeHitsError,
+        MessageResponse, PacksResponse, PaginatedResponse, ReloadHitsError,
+        ResolveHitConflictError, RetryHitDownloadError, RevertError, UpdateHitDownloadError,
+        UpdateHitError, UpdateHitIssueError, UpdatePackError, Yaml,
     },
     routes::captcha::verify_captcha,
     services::ServiceStore,
+    transcoding,
     users::UserAuthenticator,
 };
-use hitster_core::{Hit, HitId, HitIssue, HitIssueType, HitsterData, Pack, Permissions};
+use hitster_core::{
+    AudioSource, Hit, HitId, HitIssue, HitIssueState, HitIssueType, HitsterData, Pack, Permissions,
+    User,
+};
 use rocket::{State, serde::json::Json, tokio::sync::broadcast::Sender};
 use rocket_db_pools::{
     Connection,
-    sqlx::{self, FromRow},
+    sqlx::{self, FromRow, SqliteConnection},
 };
 use rocket_okapi::okapi::schemars::JsonSchema;
 use rocket_okapi::openapi;
-use serde::Deserialize;
+use serde::{Deserialize, Deserializer};
 use std::collections::{HashMap, HashSet};
 use time::OffsetDateTime;
 use uuid::Uuid;
@@ -56,12 +71,486 @@ struct HitPackRow {
     marked_for_deletion: bool,
 }
 
+/// write a new hit and its pack associations into the database
+async fn insert_hit_rows(conn: &mut SqliteConnection, hit: &Hit) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+INSERT INTO hits (
+    id,
+    artist,
+    title,
+    year,
+    belongs_to,
+    yt_id,
+    source,
+    location,
+    playback_offset,
+    playback_length,
+    snippets,
+    last_modified,
+    downloaded,
+    custom,
+    marked_for_deletion
+) VALUES (
+    ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"#,
+    )
+    .bind(hit.id)
+    .bind(&hit.artist)
+    .bind(&hit.title)
+    .bind(hit.year)
+    .bind(&hit.belongs_to)
+    .bind(&hit.yt_id)
+    .bind(<&'static str>::from(hit.source))
+    .bind(&hit.location)
+    .bind(hit.playback_offset)
+    .bind(hit.playback_length)
+    .bind(serde_json::to_string(&hit.snippets).unwrap())
+    .bind(hit.last_modified)
+    .bind(hit.downloaded)
+    .bind(true)
+    .bind(false)
+    .execute(&mut *conn)
+    .await?;
+
+    for pack in hit.packs.iter() {
+        sqlx::query!(
+            r#"
+INSERT INTO hits_packs (
+    hit_id,
+    pack_id,
+    custom,
+    marked_for_deletion) VALUES (
+    ?, ?, ?, ?)"#,
+            hit.id,
+            pack,
+            true,
+            false
+        )
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// whether another hit, including those marked as deleted, already uses the audio of this hit
+async fn source_in_use(conn: &mut SqliteConnection, hit: &Hit) -> Result<bool, sqlx::Error> {
+    sqlx::query(
+        "
+SELECT 1 FROM hits WHERE id != ? AND (
+    (source = 'youtube' AND yt_id = ?) OR
+    (source != 'youtube' AND location = ?))",
+    )
+    .bind(hit.id)
+    .bind(hit.source.is_youtube().then_some(&hit.yt_id))
+    .bind((!hit.source.is_youtube()).then_some(&hit.location))
+    .fetch_optional(&mut *conn)
+    .await
+    .map(|row| row.is_some())
+}
+
+/// update an existing hit within the database and sync its pack associations
+async fn update_hit_rows(conn: &mut SqliteConnection, hit: &Hit) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "
+UPDATE hits SET
+    title = $1,
+    artist = $2,
+    yt_id = $3,
+    source = $4,
+    location = $5,
+    year = $6,
+    playback_offset = $7,
+    belongs_to = $8,
+    last_modified = $9,
+    downloaded = $10,
+    playback_length = $11,
+    snippets = $12
+    WHERE id = $13",
+    )
+    .bind(&hit.title)
+    .bind(&hit.artist)
+    .bind(&hit.yt_id)
+    .bind(<&'static str>::from(hit.source))
+    .bind(&hit.location)
+    .bind(hit.year)
+    .bind(hit.playback_offset)
+    .bind(&hit.belongs_to)
+    .bind(hit.last_modified)
+    .bind(hit.downloaded)
+    .bind(hit.playback_length)
+    .bind(serde_json::to_string(&hit.snippets).unwrap())
+    .bind(hit.id)
+    .execute(&mut *conn)
+    .await?;
+
+    let mut hits_packs = sqlx::query_as!(
+        HitPackRow,
+        r#"
+SELECT
+    hit_id AS "hit_id: Uuid",
+    pack_id AS "pack_id: Uuid",
+    custom,
+    marked_for_deletion
+FROM hits_packs WHERE hit_id = ?"#,
+        hit.id
+    )
+    .fetch_all(&mut *conn)
+    .await?
+    .into_iter()
+    .map(|row| (row.pack_id, row))
+    .collect::<HashMap<Uuid, HitPackRow>>();
+
+    for pack in hit.packs.iter() {
+        if let Some(row) = hits_packs.get(pack) {
+            if row.marked_for_deletion {
+                sqlx::query!(
+                    r#"
+UPDATE
+    hits_packs
+SET 
+    marked_for_deletion = ?
+WHERE hit_id = ? AND pack_id = ?"#,
+                    false,
+                    row.hit_id,
+                    row.pack_id
+                )
+                .execute(&mut *conn)
+                .await?;
+            }
+            let id = row.pack_id;
+            hits_packs.remove(&id);
+        } else {
+            sqlx::query!(
+                r#"
+INSERT INTO
+    hits_packs (
+    hit_id, 
+    pack_id, 
+    custom, 
+    marked_for_deletion
+) VALUES (
+    ?, ?, ?, ?)"#,
+                hit.id,
+                pack,
+                true,
+                false
+            )
+            .execute(&mut *conn)
+            .await?;
+        }
+    }
+
+    for row in hits_packs.values() {
+        if row.custom {
+            sqlx::query!(
+                r#"
+DELETE FROM hits_packs
+WHERE hit_id = ? AND pack_id = ?"#,
+                row.hit_id,
+                row.pack_id
+            )
+            .execute(&mut *conn)
+            .await?;
+        } else if !row.marked_for_deletion {
+            sqlx::query!(
+                r#"
+UPDATE
+    hits_packs
+SET 
+    marked_for_deletion = ?
+WHERE hit_id = ? AND pack_id = ?"#,
+                true,
+                row.hit_id,
+                row.pack_id
+            )
+            .execute(&mut *conn)
+            .await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// delete a hit from the database, hits from the codebase only get marked as deleted
+/// or else they would be restored on next launch
+async fn delete_hit_rows(conn: &mut SqliteConnection, hit_id: Uuid) -> Result<(), sqlx::Error> {
+    let hit = sqlx::query_as!(HitRow, "SELECT custom FROM hits WHERE id = ?", hit_id)
+        .fetch_one(&mut *conn)
+        .await?;
+
+    if hit.custom {
+        sqlx::query!("DELETE FROM hits WHERE id = ?", hit_id)
+            .execute(&mut *conn)
+            .await?;
+        sqlx::query!("DELETE FROM hits_packs WHERE hit_id = ?", hit_id)
+            .execute(&mut *conn)
+            .await?;
+    } else {
+        sqlx::query!(
+            "UPDATE hits SET marked_for_deletion = ? WHERE id = ?",
+            true,
+            hit_id
+        )
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    Ok(())
+}
+
+#[derive(FromRow)]
+struct HitHistoryRow {
+    version: u32,
+    action: String,
+    user_id: Option<Uuid>,
+    user_name: Option<String>,
+    #[sqlx(flatten)]
+    hit: HitVersion,
+    packs: String,
+    created_at: OffsetDateTime,
+}
+
+impl HitHistoryRow {
+    /// the hit as it was stored within this version
+    fn to_hit(&self, hit_id: Uuid) -> Hit {
+        let mut hit = Hit {
+            artist: String::new(),
+            title: String::new(),
+            belongs_to: String::new(),
+            year: 0,
+            packs: serde_json::from_str(&self.packs).unwrap_or_default(),
+            playback_offset: 0,
+            playback_length: None,
+            snippets: vec![],
+            id: hit_id,
+            yt_id: String::new(),
+            source: AudioSource::default(),
+            location: String::new(),
+            last_modified: self.created_at,
+            downloaded: false,
+        };
+
+        self.hit.apply(&mut hit);
+
+        hit
+    }
+}
+
+#[derive(FromRow)]
+struct PackHistoryRow {
+    version: u32,
+    action: String,
+    user_id: Option<Uuid>,
+    user_name: Option<String>,
+    name: String,
+    created_at: OffsetDateTime,
+}
+
+/// record a new version of the hit within its history
+async fn record_hit_history(
+    conn: &mut SqliteConnection,
+    hit: &Hit,
+    action: HistoryAction,
+    user: &User,
+) -> Result<(), sqlx::Error> {
+    insert_hit_history(conn, hit, action, Some(user)).await
+}
+
+/// hits from the codebase or from before the history existed need their current state recorded
+/// before the first change, or else there would be nothing to revert to
+async fn record_hit_baseline(conn: &mut SqliteConnection, hit: &Hit) -> Result<(), sqlx::Error> {
+    if sqlx::query("SELECT 1 FROM hits_history WHERE hit_id = ?")
+        .bind(hit.id)
+        .fetch_optional(&mut *conn)
+        .await?
+        .is_some()
+    {
+        return Ok(());
+    }
+
+    insert_hit_history(conn, hit, HistoryAction::Initial, None).await
+}
+
+async fn insert_hit_history(
+    conn: &mut SqliteConnection,
+    hit: &Hit,
+    action: HistoryAction,
+    user: Option<&User>,
+) -> Result<(), sqlx::Error> {
+    let version = HitVersion::from(hit);
+
+    sqlx::query(
+        r#"
+INSERT INTO hits_history (
+    hit_id,
+    version,
+    action,
+    user_id,
+    user_name,
+    title,
+    artist,
+    yt_id,
+    source,
+    location,
+    year,
+    playback_offset,
+    belongs_to,
+    playback_length,
+    snippets,
+    packs,
+    created_at
+) VALUES (
+    ?, (SELECT COALESCE(MAX(version), 0) + 1 FROM hits_history WHERE hit_id = ?), ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"#,
+    )
+    .bind(hit.id)
+    .bind(hit.id)
+    .bind(<&'static str>::from(action))
+    .bind(user.map(|u| u.id))
+    .bind(user.map(|u| &u.name))
+    .bind(&version.title)
+    .bind(&version.artist)
+    .bind(&version.yt_id)
+    .bind(&version.source)
+    .bind(&version.location)
+    .bind(version.year)
+    .bind(version.playback_offset)
+    .bind(&version.belongs_to)
+    .bind(version.playback_length)
+    .bind(&version.snippets)
+    .bind(serde_json::to_string(&hit.packs).unwrap())
+    .bind(OffsetDateTime::now_utc())
+    .execute(&mut *conn)
+    .await
+    .map(|_| ())
+}
+
+/// record a new version of the pack within its history
+async fn record_pack_history(
+    conn: &mut SqliteConnection,
+    pack: &Pack,
+    action: HistoryAction,
+    user: &User,
+) -> Result<(), sqlx::Error> {
+    insert_pack_history(conn, pack, action, Some(user)).await
+}
+
+/// see record_hit_baseline()
+async fn record_pack_baseline(conn: &mut SqliteConnection, pack: &Pack) -> Result<(), sqlx::Error> {
+    if sqlx::query("SELECT 1 FROM packs_history WHERE pack_id = ?")
+        .bind(pack.id)
+        .fetch_optional(&mut *conn)
+        .await?
+        .is_some()
+    {
+        return Ok(());
+    }
+
+    insert_pack_history(conn, pack, HistoryAction::Initial, None).await
+}
+
+async fn insert_pack_history(
+    conn: &mut SqliteConnection,
+    pack: &Pack,
+    action: HistoryAction,
+    user: Option<&User>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+INSERT INTO packs_history (
+    pack_id,
+    version,
+    action,
+    user_id,
+    user_name,
+    name,
+    created_at
+) VALUES (
+    ?, (SELECT COALESCE(MAX(version), 0) + 1 FROM packs_history WHERE pack_id = ?), ?, ?, ?, ?, ?)"#,
+    )
+    .bind(pack.id)
+    .bind(pack.id)
+    .bind(<&'static str>::from(action))
+    .bind(user.map(|u| u.id))
+    .bind(user.map(|u| &u.name))
+    .bind(&pack.name)
+    .bind(OffsetDateTime::now_utc())
+    .execute(&mut *conn)
+    .await
+    .map(|_| ())
+}
+
 #[derive(Deserialize, JsonSchema)]
 pub struct CreateHitIssuePayload {
     pub message: String,
     pub altcha_token: String,
 }
 
+/// distinguishes between a missing field (None) and an explicit null (Some(None))
+fn deserialize_some<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    T::deserialize(deserializer).map(Some)
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct UpdateHitIssuePayload {
+    /// the state to move the issue to
+    pub state: Option<HitIssueState>,
+    /// the user to assign the issue to, null to unassign it
+    #[serde(default, deserialize_with = "deserialize_some")]
+    pub assignee: Option<Option<Uuid>>,
+}
+
+/// the changes to a hit, fields which are left out keep their current value
+#[derive(Deserialize, JsonSchema)]
+pub struct UpdateHitPayload {
+    /// artist of the song
+    pub artist: String,
+    /// title of the song
+    pub title: String,
+    /// any movie, musical or whatever the song is known for
+    pub belongs_to: String,
+    /// the year the song was released in
+    pub year: u32,
+    /// the packs the song lives in
+    pub packs: Vec<Uuid>,
+    /// the time offset within the audio at which the song starts playing
+    pub playback_offset: u16,
+    /// how many seconds of the song get played, null to play until the end of the audio
+    #[serde(default, deserialize_with = "deserialize_some")]
+    pub playback_length: Option<Option<u16>>,
+    /// alternative playback offsets, games rotate between them and the playback_offset
+    pub snippets: Option<Vec<u16>>,
+    /// the YouTube video ID, empty for other sources
+    pub yt_id: Option<String>,
+    /// where the audio of the hit gets fetched from
+    pub source: Option<AudioSource>,
+    /// the file path or URL of the audio, empty for YouTube hits
+    pub location: Option<String>,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct CreateHitIssueCommentPayload {
+    pub message: String,
+}
+
+async fn fetch_hit_issue(
+    conn: &mut SqliteConnection,
+    hit_id: Uuid,
+    issue_id: Uuid,
+) -> Result<Option<HitIssue>, sqlx::Error> {
+    sqlx::query_as::<_, HitIssue>(&format!(
+        "SELECT {HIT_ISSUE_COLUMNS} FROM hit_issues WHERE hit_id = ? AND id = ?"
+    ))
+    .bind(hit_id)
+    .bind(issue_id)
+    .fetch_optional(&mut *conn)
+    .await
+}
+
 /// # Get all packs
 ///
 /// This endpoint returns all packs currently available on this server.
@@ -135,9 +624,9 @@ pub async fn search_hits(
     );
 
     let issues_by_hit = if include_issues && can_read_issues {
-        let mut qb = sqlx::QueryBuilder::<sqlx::Sqlite>::new(
-            "SELECT id, hit_id, type, message, created_at, last_modified FROM hit_issues WHERE hit_id IN (",
-        );
+        let mut qb = sqlx::QueryBuilder::<sqlx::Sqlite>::new(format!(
+            "SELECT {HIT_ISSUE_COLUMNS} FROM hit_issues WHERE hit_id IN ("
+        ));
         let mut separated = qb.separated(", ");
         res.results.iter().for_each(|hit| {
             separated.push_bind(hit.id);
@@ -220,19 +709,10 @@ pub async fn get_hit(
     }
 
     if include_issues && can_read_issues {
-        let issues = sqlx::query_as!(
-            HitIssue,
-            r#"
-SELECT 
-    id AS "id: Uuid", 
-    hit_id AS "hit_id: Uuid", 
-    type, 
-    message, 
-    created_at AS "created_at: OffsetDateTime", 
-    last_modified AS "last_modified: OffsetDateTime" 
-FROM hit_issues WHERE hit_id = ? ORDER BY created_at ASC"#,
-            hit.id
-        )
+        let issues = sqlx::query_as::<_, HitIssue>(&format!(
+            "SELECT {HIT_ISSUE_COLUMNS} FROM hit_issues WHERE hit_id = ? ORDER BY created_at ASC"
+        ))
+        .bind(hit.id)
         .fetch_all(&mut **db)
         .await
         .unwrap_or_default();
@@ -293,6 +773,9 @@ pub async fn create_hit_issue(
         hit_id,
         r#type: HitIssueType::Custom,
         message: message.to_string(),
+        state: HitIssueState::Open,
+        assignee_id: None,
+        assignee_name: None,
         created_at: now,
         last_modified: now,
     };
@@ -366,168 +849,442 @@ pub async fn delete_hit_issue(
     }
 }
 
-/// # Update a hit
+/// # Get all hit issues
 ///
-/// Update a hit's info. This endpoint is only usable if the authenticated user has the permission to write hits.
-/// If the YouTube ID or playback offset changed, the hit will be added to the download queue.
+/// List the issues of all hits, oldest first.
+/// The results will be paginated, use the parameters to specify the page size.
+/// Use the optional `filters` parameter to narrow down results.
+/// Supported values are `open`, `in_progress`, `resolved`, `wont_fix`, `auto`, `custom` and `unassigned`.
+/// The authenticated user needs to have issue read permissions.
 
 #[openapi(tag = "Hits")]
-#[patch("/hits/<hit_id>", format = "json", data = "<hit>")]
-pub async fn update_hit(
-    hit_id: Uuid,
-    hit: Json<FullHitPayload>,
+#[get("/hits/issues?<query..>")]
+pub async fn get_hit_issues(
+    query: HitIssuesQuery,
     user: UserAuthenticator,
     serv: &State<ServiceStore>,
     mut db: Connection<HitsterConfig>,
-) -> Result<Json<MessageResponse>, UpdateHitError> {
-    if !user.0.permissions.contains(Permissions::WRITE_HITS) {
-        return Err(UpdateHitError {
+) -> Result<Json<PaginatedResponse<HitIssuePayload>>, GetHitIssuesError> {
+    if !user.0.permissions.contains(Permissions::READ_ISSUES) {
+        return Err(GetHitIssuesError {
             message: "permission denied".into(),
             http_status_code: 401,
         });
     }
 
-    let hs = serv.hit_service();
+    let filters = query.filters.clone().unwrap_or_default();
+    let states = filters.iter().filter_map(|f| f.state()).collect::<Vec<_>>();
+    let types = filters
+        .iter()
+        .filter_map(|f| f.issue_type())
+        .collect::<Vec<_>>();
+    let start = query.start.unwrap_or(1).max(1);
+    let amount = query.amount.unwrap_or(50);
 
-    if hs.lock().get_hit(&HitId::Id(hit_id)).is_none() {
-        return Err(UpdateHitError {
-            message: "hit not found".into(),
-            http_status_code: 404,
+    let mut qb = sqlx::QueryBuilder::<sqlx::Sqlite>::new(format!(
+        "SELECT {HIT_ISSUE_COLUMNS} FROM hit_issues WHERE TRUE"
+    ));
+
+    if !states.is_empty() {
+        qb.push(" AND state IN (");
+        let mut separated = qb.separated(", ");
+        states.iter().for_each(|state| {
+            separated.push_bind(<&'static str>::from(*state));
         });
+        separated.push_unseparated(")");
     }
 
-    let mut new_hit = Hit {
-        id: hit_id,
-        title: hit.title.clone(),
-        artist: hit.artist.clone(),
-        packs: hit.packs.clone(),
-        belongs_to: hit.belongs_to.clone(),
-        yt_id: hit.yt_id.clone(),
-        playback_offset: hit.playback_offset,
-        last_modified: OffsetDateTime::now_utc(),
-        year: hit.year,
-        downloaded: false,
-    };
-    new_hit.downloaded = new_hit.exists();
+    if !types.is_empty() {
+        qb.push(" AND type IN (");
+        let mut separated = qb.separated(", ");
+        types.iter().for_each(|issue_type| {
+            separated.push_bind(*issue_type);
+        });
+        separated.push_unseparated(")");
+    }
 
-    hs.lock().remove_hit(&HitId::Id(hit_id));
+    if filters.contains(&HitIssueFilter::Unassigned) {
+        qb.push(" AND assignee_id IS NULL");
+    }
 
-    let _ = sqlx::query!(
-        "
-UPDATE hits SET
-    title = $1,
-    artist = $2,
-    yt_id = $3,
-    year = $4,
-    playback_offset = $5,
-    belongs_to = $6,
-    last_modified = $7,
-    downloaded = $8
-    WHERE id = $9",
-        new_hit.title,
-        new_hit.artist,
-        new_hit.yt_id,
-        new_hit.year,
-        new_hit.playback_offset,
-        new_hit.belongs_to,
-        new_hit.last_modified,
-        new_hit.downloaded,
-        new_hit.id,
-    )
-    .execute(&mut **db)
-    .await;
+    if let Some(assignee) = query.assignee {
+        qb.push(" AND assignee_id = ").push_bind(assignee);
+    }
 
-    let mut hits_packs = sqlx::query_as!(
-        HitPackRow,
-        r#"
-SELECT
-    hit_id AS "hit_id: Uuid",
-    pack_id AS "pack_id: Uuid",
-    custom,
-    marked_for_deletion
-FROM hits_packs WHERE hit_id = ?"#,
-        hit_id
-    )
-    .fetch_all(&mut **db)
-    .await
-    .unwrap()
-    .into_iter()
-    .map(|row| (row.pack_id, row))
-    .collect::<HashMap<Uuid, HitPackRow>>();
+    qb.push(" ORDER BY created_at ASC");
 
-    for pack in new_hit.packs.iter() {
-        if let Some(row) = hits_packs.get(pack) {
-            if row.marked_for_deletion {
-                let _ = sqlx::query!(
-                    r#"
-UPDATE
-    hits_packs
-SET 
-    marked_for_deletion = ?
-WHERE hit_id = ? AND pack_id = ?"#,
-                    false,
-                    row.hit_id,
-                    row.pack_id
-                )
-                .execute(&mut **db)
-                .await;
-            }
-            let id = row.pack_id;
-            hits_packs.remove(&id);
-        } else {
-            let _ = sqlx::query!(
-                r#"
-INSERT INTO
-    hits_packs (
-    hit_id, 
-    pack_id, 
-    custom, 
-    marked_for_deletion
-) VALUES (
-    ?, ?, ?, ?)"#,
-                new_hit.id,
-                pack,
-                true,
-                false
-            )
-            .execute(&mut **db)
-            .await;
+    let issues = qb
+        .build_query_as::<HitIssue>()
+        .fetch_all(&mut **db)
+        .await
+        .map_err(|_| GetHitIssuesError {
+            message: "failed to read issues".into(),
+            http_status_code: 500,
+        })?;
+
+    let hs = serv.hit_service();
+    let issues = {
+        let hsl = hs.lock();
+
+        issues
+            .into_iter()
+            .filter_map(|issue| {
+                hsl.get_hit(&HitId::Id(issue.hit_id))
+                    .map(|hit| HitIssuePayload {
+                        hit: hit.into(),
+                        issue,
+                    })
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let total = issues.len();
+    let results = issues
+        .into_iter()
+        .skip(start - 1)
+        .take(amount)
+        .collect::<Vec<_>>();
+
+    Ok(Json(PaginatedResponse {
+        start,
+        end: start + results.len() - 1,
+        results,
+        total,
+    }))
+}
+
+/// # Update a hit issue
+///
+/// Move an issue to another state or assign it to a user.
+/// Fields which aren't part of the request stay as they are, set `assignee` to `null` to unassign the issue.
+/// The authenticated user needs to have issue read and write permissions, the same goes for the assignee.
+
+#[openapi(tag = "Hits")]
+#[patch("/hits/<hit_id>/issues/<issue_id>", format = "json", data = "<issue>")]
+pub async fn update_hit_issue(
+    hit_id: Uuid,
+    issue_id: Uuid,
+    issue: Json<UpdateHitIssuePayload>,
+    user: UserAuthenticator,
+    queue: &State<Sender<GlobalEvent>>,
+    mut db: Connection<HitsterConfig>,
+) -> Result<Json<HitIssue>, UpdateHitIssueError> {
+    if !user
+        .0
+        .permissions
+        .contains(Permissions::READ_ISSUES | Permissions::WRITE_ISSUES)
+    {
+        return Err(UpdateHitIssueError {
+            message: "permission denied".into(),
+            http_status_code: 401,
+        });
+    }
+
+    let mut updated = match fetch_hit_issue(&mut db, hit_id, issue_id).await {
+        Ok(Some(issue)) => issue,
+        Ok(None) => {
+            return Err(UpdateHitIssueError {
+                message: "issue not found".into(),
+                http_status_code: 404,
+            });
+        }
+        Err(_) => {
+            return Err(UpdateHitIssueError {
+                message: "failed to read issue".into(),
+                http_status_code: 500,
+            });
         }
+    };
+
+    if let Some(state) = issue.state {
+        updated.state = state;
     }
 
-    for row in hits_packs.values() {
-        if row.custom {
-            let _ = sqlx::query!(
-                r#"
-DELETE FROM hits_packs
-WHERE hit_id = ? AND pack_id = ?"#,
-                row.hit_id,
-                row.pack_id
-            )
-            .execute(&mut **db)
-            .await;
-        } else if !row.marked_for_deletion {
-            let _ = sqlx::query!(
-                r#"
-UPDATE
-    hits_packs
-SET 
-    marked_for_deletion = ?
-WHERE hit_id = ? AND pack_id = ?"#,
-                true,
-                row.hit_id,
-                row.pack_id
-            )
-            .execute(&mut **db)
-            .await;
+    match issue.assignee {
+        Some(Some(assignee_id)) => {
+            let assignee = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = ?")
+                .bind(assignee_id.to_string())
+                .fetch_optional(&mut **db)
+                .await
+                .ok()
+                .flatten()
+                .filter(|u| {
+                    u.permissions
+                        .contains(Permissions::READ_ISSUES | Permissions::WRITE_ISSUES)
+                })
+                .ok_or_else(|| UpdateHitIssueError {
+                    message: "assignee not found or not allowed to work on issues".into(),
+                    http_status_code: 400,
+                })?;
+
+            updated.assignee_id = Some(assignee.id);
+            updated.assignee_name = Some(assignee.name);
+        }
+        Some(None) => {
+            updated.assignee_id = None;
+            updated.assignee_name = None;
         }
+        None => {}
     }
 
-    if !new_hit.downloaded {
-        hs.lock().download_hit(new_hit.clone());
+    updated.last_modified = OffsetDateTime::now_utc();
+
+    if sqlx::query(
+        "UPDATE hit_issues SET state = ?, assignee_id = ?, assignee_name = ?, last_modified = ? WHERE hit_id = ? AND id = ?",
+    )
+    .bind(<&'static str>::from(updated.state))
+    .bind(updated.assignee_id)
+    .bind(&updated.assignee_name)
+    .bind(updated.last_modified)
+    .bind(hit_id)
+    .bind(issue_id)
+    .execute(&mut **db)
+    .await
+    .is_err()
+    {
+        return Err(UpdateHitIssueError {
+            message: "failed to update issue".into(),
+            http_status_code: 500,
+        });
+    }
+
+    let _ = queue.send(GlobalEvent::UpdateHitIssue(updated.clone()));
+
+    Ok(Json(updated))
+}
+
+/// # Get the comments of a hit issue
+///
+/// Retrieve all comments written on an issue, oldest first.
+/// The authenticated user needs to have issue read permissions.
+
+#[openapi(tag = "Hits")]
+#[get("/hits/<hit_id>/issues/<issue_id>/comments")]
+pub async fn get_hit_issue_comments(
+    hit_id: Uuid,
+    issue_id: Uuid,
+    user: UserAuthenticator,
+    mut db: Connection<HitsterConfig>,
+) -> Result<Json<Vec<HitIssueComment>>, GetHitIssueCommentsError> {
+    if !user.0.permissions.contains(Permissions::READ_ISSUES) {
+        return Err(GetHitIssueCommentsError {
+            message: "permission denied".into(),
+            http_status_code: 401,
+        });
+    }
+
+    if !matches!(
+        fetch_hit_issue(&mut db, hit_id, issue_id).await,
+        Ok(Some(_))
+    ) {
+        return Err(GetHitIssueCommentsError {
+            message: "issue not found".into(),
+            http_status_code: 404,
+        });
+    }
+
+    Ok(Json(
+        sqlx::query_as::<_, HitIssueComment>(
+            "SELECT id, issue_id, user_id, user_name, message, created_at FROM hit_issues_comments WHERE issue_id = ? ORDER BY created_at ASC",
+        )
+        .bind(issue_id)
+        .fetch_all(&mut **db)
+        .await
+        .unwrap_or_default(),
+    ))
+}
+
+/// # Comment on a hit issue
+///
+/// Add a comment to the discussion of an issue.
+/// The authenticated user needs to have issue read and write permissions.
+
+#[openapi(tag = "Hits")]
+#[post(
+    "/hits/<hit_id>/issues/<issue_id>/comments",
+    format = "json",
+    data = "<comment>"
+)]
+pub async fn create_hit_issue_comment(
+    hit_id: Uuid,
+    issue_id: Uuid,
+    comment: Json<CreateHitIssueCommentPayload>,
+    user: UserAuthenticator,
+    queue: &State<Sender<GlobalEvent>>,
+    mut db: Connection<HitsterConfig>,
+) -> Result<Json<HitIssueComment>, CreateHitIssueCommentError> {
+    if !user
+        .0
+        .permissions
+        .contains(Permissions::READ_ISSUES | Permissions::WRITE_ISSUES)
+    {
+        return Err(CreateHitIssueCommentError {
+            message: "permission denied".into(),
+            http_status_code: 401,
+        });
+    }
+
+    let message = comment.message.trim();
+    if message.is_empty() {
+        return Err(CreateHitIssueCommentError {
+            message: "comment message is required".into(),
+            http_status_code: 400,
+        });
+    }
+
+    if !matches!(
+        fetch_hit_issue(&mut db, hit_id, issue_id).await,
+        Ok(Some(_))
+    ) {
+        return Err(CreateHitIssueCommentError {
+            message: "issue not found".into(),
+            http_status_code: 404,
+        });
+    }
+
+    let new_comment = HitIssueComment {
+        id: Uuid::new_v4(),
+        issue_id,
+        user_id: user.0.id,
+        user_name: user.0.name.clone(),
+        message: message.to_string(),
+        created_at: OffsetDateTime::now_utc(),
+    };
+
+    if sqlx::query(
+        "INSERT INTO hit_issues_comments (id, issue_id, user_id, user_name, message, created_at) VALUES (?, ?, ?, ?, ?, ?)",
+    )
+    .bind(new_comment.id)
+    .bind(new_comment.issue_id)
+    .bind(new_comment.user_id)
+    .bind(&new_comment.user_name)
+    .bind(&new_comment.message)
+    .bind(new_comment.created_at)
+    .execute(&mut **db)
+    .await
+    .is_err()
+    {
+        return Err(CreateHitIssueCommentError {
+            message: "failed to create comment".into(),
+            http_status_code: 500,
+        });
+    }
+
+    let _ = queue.send(GlobalEvent::CreateHitIssueComment(new_comment.clone()));
+
+    Ok(Json(new_comment))
+}
+
+/// # Update a hit
+///
+/// Update a hit's info. This endpoint is only usable if the authenticated user has the permission to write hits.
+/// The playback length, snippets, YouTube ID, source and location keep their current values if they are left out.
+/// If the source, YouTube ID, location, playback offset, playback length or snippets changed, the hit will be added to the download queue.
+
+#[openapi(tag = "Hits")]
+#[patch("/hits/<hit_id>", format = "json", data = "<hit>")]
+pub async fn update_hit(
+    hit_id: Uuid,
+    hit: Json<UpdateHitPayload>,
+    user: UserAuthenticator,
+    serv: &State<ServiceStore>,
+    mut db: Connection<HitsterConfig>,
+) -> Result<Json<MessageResponse>, UpdateHitError> {
+    if !user.0.permissions.contains(Permissions::WRITE_HITS) {
+        return Err(UpdateHitError {
+            message: "permission denied".into(),
+            http_status_code: 401,
+        });
+    }
+
+    let hs = serv.hit_service();
+
+    let Some(old_hit) = hs.lock().get_hit(&HitId::Id(hit_id)).cloned() else {
+        return Err(UpdateHitError {
+            message: "hit not found".into(),
+            http_status_code: 404,
+        });
+    };
+
+    let mut new_hit = Hit {
+        id: hit_id,
+        title: hit.title.clone(),
+        artist: hit.artist.clone(),
+        packs: hit.packs.clone(),
+        belongs_to: hit.belongs_to.clone(),
+        yt_id: hit.yt_id.clone().unwrap_or_else(|| old_hit.yt_id.clone()),
+        source: hit.source.unwrap_or(old_hit.source),
+        location: hit
+            .location
+            .clone()
+            .unwrap_or_else(|| old_hit.location.clone()),
+        playback_offset: hit.playback_offset,
+        playback_length: hit.playback_length.unwrap_or(old_hit.playback_length),
+        snippets: hit
+            .snippets
+            .clone()
+            .unwrap_or_else(|| old_hit.snippets.clone()),
+        last_modified: OffsetDateTime::now_utc(),
+        year: hit.year,
+        downloaded: false,
+    };
+
+    if source_in_use(&mut db, &new_hit)
+        .await
+        .map_err(|_| UpdateHitError {
+            message: "failed to read hits".into(),
+            http_status_code: 500,
+        })?
+    {
+        return Err(UpdateHitError {
+            message: "another hit uses the same YouTube ID, file or URL".into(),
+            http_status_code: 409,
+        });
+    }
+
+    if new_hit.source == AudioSource::File && new_hit.source_file().is_none() {
+        return Err(UpdateHitError {
+            message: "the file needs to be located within the library directory".into(),
+            http_status_code: 400,
+        });
+    }
+
+    if new_hit.playback_length == Some(0) {
+        return Err(UpdateHitError {
+            message: "the playback length needs to be at least one second".into(),
+            http_status_code: 400,
+        });
+    }
+
+    new_hit.downloaded = new_hit.exists(&transcoding::extensions());
+
+    let mut tx = sqlx::Connection::begin(&mut **db)
+        .await
+        .map_err(|_| UpdateHitError {
+            message: "failed to start a transaction".into(),
+            http_status_code: 500,
+        })?;
+
+    if record_hit_baseline(&mut tx, &old_hit).await.is_err()
+        || update_hit_rows(&mut tx, &new_hit).await.is_err()
+        || record_hit_history(&mut tx, &new_hit, HistoryAction::Update, &user.0)
+            .await
+            .is_err()
+        || tx.commit().await.is_err()
+    {
+        return Err(UpdateHitError {
+            message: "failed to update the hit".into(),
+            http_status_code: 500,
+        });
     }
 
-    hs.lock().insert_hit(new_hit);
+    hs.lock().remove_hit(&HitId::Id(hit_id));
+
+    hs.lock().insert_hit(new_hit.clone());
+
+    if !new_hit.downloaded {
+        hs.lock().download_hit(new_hit);
+    }
 
     Ok(Json(MessageResponse {
         message: "hit updated successfully".into(),
@@ -556,37 +1313,34 @@ pub async fn delete_hit(
 
     let hs = serv.hit_service();
 
-    if hs.lock().get_hit(&HitId::Id(hit_id)).is_none() {
+    let Some(old_hit) = hs.lock().get_hit(&HitId::Id(hit_id)).cloned() else {
         return Err(DeleteHitError {
             message: "hit not found".into(),
             http_status_code: 404,
         });
-    }
-
-    hs.lock().remove_hit(&HitId::Id(hit_id));
+    };
 
-    let hit = sqlx::query_as!(HitRow, "SELECT custom FROM hits WHERE id = ?", hit_id)
-        .fetch_one(&mut **db)
+    let mut tx = sqlx::Connection::begin(&mut **db)
         .await
-        .unwrap();
+        .map_err(|_| DeleteHitError {
+            message: "failed to start a transaction".into(),
+            http_status_code: 500,
+        })?;
 
-    if hit.custom {
-        let _ = sqlx::query!("DELETE FROM hits WHERE id = ?", hit_id)
-            .execute(&mut **db)
-            .await;
-        let _ = sqlx::query!("DELETE FROM hits_packs WHERE hit_id = ?", hit_id)
-            .execute(&mut **db)
-            .await;
-    } else {
-        let _ = sqlx::query!(
-            "UPDATE hits SET marked_for_deletion = ? WHERE id = ?",
-            true,
-            hit_id
-        )
-        .execute(&mut **db)
-        .await;
+    if record_hit_history(&mut tx, &old_hit, HistoryAction::Delete, &user.0)
+        .await
+        .is_err()
+        || delete_hit_rows(&mut tx, hit_id).await.is_err()
+        || tx.commit().await.is_err()
+    {
+        return Err(DeleteHitError {
+            message: "failed to delete the hit".into(),
+            http_status_code: 500,
+        });
     }
 
+    hs.lock().remove_hit(&HitId::Id(hit_id));
+
     Ok(Json(MessageResponse {
         message: "hit deleted successfully".into(),
         r#type: "success".into(),
@@ -612,316 +1366,1683 @@ pub async fn delete_pack(
         });
     }
 
-    let hs = serv.hit_service();
+    let hs = serv.hit_service();
+
+    let Some(old_pack) = hs.lock().get_pack(pack_id).cloned() else {
+        return Err(DeletePackError {
+            message: "pack not found".into(),
+            http_status_code: 404,
+        });
+    };
+
+    let failed = || DeletePackError {
+        message: "failed to delete the pack".into(),
+        http_status_code: 500,
+    };
+
+    let mut tx = sqlx::Connection::begin(&mut **db)
+        .await
+        .map_err(|_| DeletePackError {
+            message: "failed to start a transaction".into(),
+            http_status_code: 500,
+        })?;
+
+    record_pack_history(&mut tx, &old_pack, HistoryAction::Delete, &user.0)
+        .await
+        .map_err(|_| failed())?;
+
+    let pack = sqlx::query_as!(PackRow, "SELECT custom FROM packs WHERE id = ?", pack_id)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|_| failed())?;
+
+    if pack.custom {
+        sqlx::query!("DELETE FROM packs WHERE id = ?", pack_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|_| failed())?;
+        sqlx::query!("DELETE FROM hits_packs WHERE pack_id = ?", pack_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|_| failed())?;
+    } else {
+        sqlx::query!(
+            "UPDATE packs SET marked_for_deletion = ? WHERE id = ?",
+            true,
+            pack_id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|_| failed())?;
+    }
+
+    tx.commit().await.map_err(|_| failed())?;
+
+    hs.lock().remove_pack(pack_id);
+
+    Ok(Json(MessageResponse {
+        message: "pack deleted successfully".into(),
+        r#type: "success".into(),
+    }))
+}
+
+/// # Create a new pack
+///
+/// Create a new pack. The authenticated user needs to have pack write permissions.
+
+#[openapi(tag = "Hits")]
+#[post("/hits/packs", format = "json", data = "<pack>")]
+pub async fn create_pack(
+    pack: Json<CreatePackPayload>,
+    user: UserAuthenticator,
+    serv: &State<ServiceStore>,
+    mut db: Connection<HitsterConfig>,
+) -> Result<Json<PackPayload>, CreatePackError> {
+    if !user.0.permissions.contains(Permissions::WRITE_PACKS) {
+        return Err(CreatePackError {
+            message: "permission denied".into(),
+            http_status_code: 401,
+        });
+    }
+
+    if sqlx::query!("SELECT * FROM packs WHERE name = ?", pack.name)
+        .fetch_optional(&mut **db)
+        .await
+        .unwrap()
+        .is_some()
+    {
+        return Err(CreatePackError {
+            message: "a pack with that name already exists".into(),
+            http_status_code: 409,
+        });
+    }
+
+    let pack = Pack {
+        name: pack.name.clone(),
+        id: Uuid::new_v4(),
+        last_modified: OffsetDateTime::now_utc(),
+    };
+
+    let mut tx = sqlx::Connection::begin(&mut **db)
+        .await
+        .map_err(|_| CreatePackError {
+            message: "failed to start a transaction".into(),
+            http_status_code: 500,
+        })?;
+
+    if sqlx::query!(
+        r#"
+INSERT INTO packs (
+    id, name, last_modified, custom, marked_for_deletion) VALUES (
+    ?, ?, ?, ?, ?)"#,
+        pack.id,
+        pack.name,
+        pack.last_modified,
+        true,
+        false
+    )
+    .execute(&mut *tx)
+    .await
+    .is_err()
+        || record_pack_history(&mut tx, &pack, HistoryAction::Create, &user.0)
+            .await
+            .is_err()
+        || tx.commit().await.is_err()
+    {
+        return Err(CreatePackError {
+            message: "failed to create the pack".into(),
+            http_status_code: 500,
+        });
+    }
+
+    let hs = serv.hit_service();
+
+    hs.lock().insert_pack(pack.clone());
+
+    Ok(Json(PackPayload {
+        name: pack.name.clone(),
+        id: pack.id,
+        hits: 0,
+    }))
+}
+
+/// # Update a pack
+///
+/// Update a pack. The authenticated user needs to have pack write permissions.
+
+#[openapi(tag = "Hits")]
+#[patch("/hits/packs/<pack_id>", format = "json", data = "<pack>")]
+pub async fn update_pack(
+    pack_id: Uuid,
+    pack: Json<CreatePackPayload>,
+    user: UserAuthenticator,
+    serv: &State<ServiceStore>,
+    mut db: Connection<HitsterConfig>,
+) -> Result<Json<MessageResponse>, UpdatePackError> {
+    if !user.0.permissions.contains(Permissions::WRITE_PACKS) {
+        return Err(UpdatePackError {
+            message: "permission denied".into(),
+            http_status_code: 401,
+        });
+    }
+
+    let hs = serv.hit_service();
+
+    let old_pack = hs.lock().get_pack(pack_id).cloned();
+
+    if let Some(old_pack) = old_pack {
+        let pack = Pack {
+            name: pack.name.clone(),
+            id: pack_id,
+            last_modified: OffsetDateTime::now_utc(),
+        };
+
+        let mut tx = sqlx::Connection::begin(&mut **db)
+            .await
+            .map_err(|_| UpdatePackError {
+                message: "failed to start a transaction".into(),
+                http_status_code: 500,
+            })?;
+
+        if record_pack_baseline(&mut tx, &old_pack).await.is_err()
+            || sqlx::query!(
+                r#"
+UPDATE packs SET
+    name = $1,
+    last_modified = $2
+WHERE id = $3"#,
+                pack.name,
+                pack.last_modified,
+                pack.id,
+            )
+            .execute(&mut *tx)
+            .await
+            .is_err()
+            || record_pack_history(&mut tx, &pack, HistoryAction::Update, &user.0)
+                .await
+                .is_err()
+            || tx.commit().await.is_err()
+        {
+            return Err(UpdatePackError {
+                message: "failed to update the pack".into(),
+                http_status_code: 500,
+            });
+        }
+
+        hs.lock().insert_pack(pack.clone());
+
+        Ok(Json(MessageResponse {
+            message: "pack updated successfully".into(),
+            r#type: "success".into(),
+        }))
+    } else {
+        Err(UpdatePackError {
+            message: "a pack with that id doesn't exist".into(),
+            http_status_code: 404,
+        })
+    }
+}
+
+/// # Create a new hit
+///
+/// Create a new hit. The hit will be added to the download queue and will be available in all new games once the download finishes.
+/// The audio is fetched from YouTube by default. Set the source to file to use an audio file within the library directory of the server instead, or to url to download it via HTTP. The location then contains the relative file path or the URL.
+/// The authenticated user needs to have hit write permissions.
+
+#[openapi(tag = "Hits")]
+#[post("/hits", format = "json", data = "<hit>")]
+pub async fn create_hit(
+    hit: Json<FullHitPayload>,
+    user: UserAuthenticator,
+    serv: &State<ServiceStore>,
+    mut db: Connection<HitsterConfig>,
+) -> Result<Json<FullHitPayload>, CreateHitError> {
+    if !user.0.permissions.contains(Permissions::WRITE_HITS) {
+        return Err(CreateHitError {
+            message: "permission denied".into(),
+            http_status_code: 401,
+        });
+    }
+
+    let mut hit = Hit {
+        title: hit.title.clone(),
+        artist: hit.artist.clone(),
+        last_modified: OffsetDateTime::now_utc(),
+        id: Uuid::new_v4(),
+        yt_id: hit.yt_id.clone(),
+        source: hit.source,
+        location: hit.location.clone(),
+        belongs_to: hit.belongs_to.clone(),
+        playback_offset: hit.playback_offset,
+        playback_length: hit.playback_length,
+        snippets: hit.snippets.clone(),
+        year: hit.year,
+        packs: hit.packs.clone(),
+        downloaded: false,
+    };
+
+    if source_in_use(&mut db, &hit)
+        .await
+        .map_err(|_| CreateHitError {
+            message: "failed to read hits".into(),
+            http_status_code: 500,
+        })?
+    {
+        return Err(CreateHitError {
+            message: "a hit for that YouTube ID, file or URL already exists".into(),
+            http_status_code: 409,
+        });
+    }
+
+    if hit.source == AudioSource::File && hit.source_file().is_none() {
+        return Err(CreateHitError {
+            message: "the file needs to be located within the library directory".into(),
+            http_status_code: 400,
+        });
+    }
+
+    if hit.playback_length == Some(0) {
+        return Err(CreateHitError {
+            message: "the playback length needs to be at least one second".into(),
+            http_status_code: 400,
+        });
+    }
+
+    hit.downloaded = hit.exists(&transcoding::extensions());
+
+    let mut tx = sqlx::Connection::begin(&mut **db)
+        .await
+        .map_err(|_| CreateHitError {
+            message: "failed to start a transaction".into(),
+            http_status_code: 500,
+        })?;
+
+    if insert_hit_rows(&mut tx, &hit).await.is_err()
+        || record_hit_history(&mut tx, &hit, HistoryAction::Create, &user.0)
+            .await
+            .is_err()
+        || tx.commit().await.is_err()
+    {
+        return Err(CreateHitError {
+            message: "failed to create the hit".into(),
+            http_status_code: 500,
+        });
+    }
+
+    let hs = serv.hit_service();
+
+    hs.lock().insert_hit(hit.clone());
+
+    if !hit.downloaded {
+        hs.lock().download_hit(hit.clone());
+    }
+
+    Ok(Json((&hit).into()))
+}
+
+/// # Export hits database
+///
+/// This endpoint allows authenticated users with hits write permissions to
+/// export the hits database of this server in the YAML format used when
+/// deploying hits within the codebase. Use this to transfer hits between server instances.
+///
+/// The query and pack parameters behave similarly to those within the /hits/search endpoint.
+
+#[openapi(tag = "Hits")]
+#[get("/hits/export?<query..>")]
+pub async fn export_hits(
+    query: ExportHitsQuery,
+    user: UserAuthenticator,
+    serv: &State<ServiceStore>,
+) -> Result<Yaml, ExportHitsError> {
+    if !user.0.permissions.contains(Permissions::WRITE_HITS) {
+        return Err(ExportHitsError {
+            message: "permission denied".into(),
+            http_status_code: 401,
+        });
+    }
+
+    let hs = serv.hit_service();
+    let hsl = hs.lock();
+
+    let hsq = HitSearchQuery {
+        query: query.query,
+        packs: query.pack,
+        start: Some(1),
+        amount: Some(hsl.get_hits().len()),
+        ..Default::default()
+    };
+
+    let results = hsl.search_hits(&hsq, None);
+
+    let mut data = HitsterData::new(vec![], vec![]);
+
+    results.results.iter().for_each(|h| {
+        h.packs.iter().for_each(|p| {
+            if data.get_pack(*p).is_none() {
+                data.insert_pack(hsl.get_pack(*p).cloned().unwrap());
+            }
+        });
+        data.insert_hit(h.clone());
+    });
+
+    Ok(Yaml(serde_yml::to_string(&data).unwrap()))
+}
+
+/// write all changes of an import into the database, hits and packs which were deleted before get restored
+async fn apply_import(
+    conn: &mut SqliteConnection,
+    import: &HitsImport,
+    previous: &HitsterData,
+    user: &User,
+) -> Result<(), sqlx::Error> {
+    for pack in import.new_packs.iter() {
+        if sqlx::query("SELECT 1 FROM packs WHERE id = ?")
+            .bind(pack.id)
+            .fetch_optional(&mut *conn)
+            .await?
+            .is_some()
+        {
+            sqlx::query(
+                "UPDATE packs SET name = ?, last_modified = ?, marked_for_deletion = ? WHERE id = ?",
+            )
+            .bind(&pack.name)
+            .bind(pack.last_modified)
+            .bind(false)
+            .bind(pack.id)
+            .execute(&mut *conn)
+            .await?;
+        } else {
+            sqlx::query(
+                r#"
+INSERT INTO packs (
+    id, name, last_modified, custom, marked_for_deletion) VALUES (
+    ?, ?, ?, ?, ?)"#,
+            )
+            .bind(pack.id)
+            .bind(&pack.name)
+            .bind(pack.last_modified)
+            .bind(true)
+            .bind(false)
+            .execute(&mut *conn)
+            .await?;
+        }
+    }
+
+    for pack in import.changed_packs.iter() {
+        sqlx::query("UPDATE packs SET name = ?, last_modified = ? WHERE id = ?")
+            .bind(&pack.name)
+            .bind(pack.last_modified)
+            .bind(pack.id)
+            .execute(&mut *conn)
+            .await?;
+    }
+
+    for hit in import.new_hits.iter() {
+        if sqlx::query("SELECT 1 FROM hits WHERE id = ?")
+            .bind(hit.id)
+            .fetch_optional(&mut *conn)
+            .await?
+            .is_some()
+        {
+            sqlx::query("UPDATE hits SET marked_for_deletion = ? WHERE id = ?")
+                .bind(false)
+                .bind(hit.id)
+                .execute(&mut *conn)
+                .await?;
+            update_hit_rows(conn, hit).await?;
+        } else {
+            insert_hit_rows(conn, hit).await?;
+        }
+    }
+
+    for hit in import.changed_hits.iter() {
+        update_hit_rows(conn, hit).await?;
+    }
+
+    for pack in import.new_packs.iter().chain(import.changed_packs.iter()) {
+        if let Some(previous) = previous.get_pack(pack.id) {
+            record_pack_baseline(conn, previous).await?;
+        }
+
+        record_pack_history(conn, pack, HistoryAction::Import, user).await?;
+    }
+
+    for hit in import.new_hits.iter().chain(import.changed_hits.iter()) {
+        if let Some(previous) = previous.get_hit(&HitId::Id(hit.id)) {
+            record_hit_baseline(conn, previous).await?;
+        }
+
+        record_hit_history(conn, hit, HistoryAction::Import, user).await?;
+    }
+
+    Ok(())
+}
+
+/// # Import hits
+///
+/// This endpoint allows authenticated users with hits and packs write permissions to
+/// import hits and packs, e.g. from another server. The data can be uploaded in the YAML
+/// format returned by the export endpoint, the same structure as JSON, or as CSV.
+///
+/// Hits are matched with existing ones by their id, or by their YouTube ID, file or URL if the import doesn't contain any ids.
+/// Packs are matched by their id first and by their name second.
+/// The response lists all new, changed and conflicting hits and packs. Use dry_run to only inspect these differences.
+/// Otherwise all changes get applied at once, but only if the import doesn't contain any conflicts.
+
+#[openapi(tag = "Hits")]
+#[post("/hits/import?<query..>", data = "<data>")]
+pub async fn import_hits(
+    query: ImportHitsQuery,
+    data: ImportData,
+    user: UserAuthenticator,
+    serv: &State<ServiceStore>,
+    mut db: Connection<HitsterConfig>,
+) -> Result<Json<ImportHitsPayload>, ImportHitsError> {
+    if !user
+        .0
+        .permissions
+        .contains(Permissions::WRITE_HITS | Permissions::WRITE_PACKS)
+    {
+        return Err(ImportHitsError {
+            message: "permission denied".into(),
+            http_status_code: 401,
+        });
+    }
+
+    let hs = serv.hit_service();
+
+    let mut import = HitsImport::new(&data.0, query.format.unwrap_or_default(), &hs.lock())
+        .map_err(|e| ImportHitsError {
+            message: format!("unable to parse the import: {e}"),
+            http_status_code: 400,
+        })?;
+
+    // the database also knows about hits which aren't loaded, e.g. the ones marked for deletion
+    for hits in [&mut import.new_hits, &mut import.changed_hits] {
+        let mut kept = vec![];
+
+        for hit in hits.drain(..) {
+            match source_in_use(&mut db, &hit).await {
+                Ok(false) => kept.push(hit),
+                Ok(true) => import.conflicts.push((
+                    hit,
+                    "a different hit with that YouTube ID, file or URL already exists".into(),
+                )),
+                Err(_) => {
+                    return Err(ImportHitsError {
+                        message: "failed to read hits".into(),
+                        http_status_code: 500,
+                    });
+                }
+            }
+        }
+
+        *hits = kept;
+    }
+
+    if query.dry_run.unwrap_or(false) {
+        return Ok(Json(import.to_payload(false)));
+    }
+
+    if !import.conflicts.is_empty() {
+        return Err(ImportHitsError {
+            message: format!(
+                "the import contains {} conflicting hits",
+                import.conflicts.len()
+            ),
+            http_status_code: 409,
+        });
+    }
+
+    for hit in import
+        .new_hits
+        .iter_mut()
+        .chain(import.changed_hits.iter_mut())
+    {
+        hit.downloaded = hit.exists(&transcoding::extensions());
+    }
+
+    // the current state of everything the import changes, for the history
+    let previous = {
+        let hsl = hs.lock();
+
+        HitsterData::new(
+            import
+                .changed_hits
+                .iter()
+                .filter_map(|h| hsl.get_hit(&HitId::Id(h.id)).cloned())
+                .collect::<Vec<_>>(),
+            import
+                .changed_packs
+                .iter()
+                .filter_map(|p| hsl.get_pack(p.id).cloned())
+                .collect::<Vec<_>>(),
+        )
+    };
+
+    let mut tx = sqlx::Connection::begin(&mut **db)
+        .await
+        .map_err(|_| ImportHitsError {
+            message: "failed to start a transaction".into(),
+            http_status_code: 500,
+        })?;
+
+    if apply_import(&mut tx, &import, &previous, &user.0)
+        .await
+        .is_err()
+        || tx.commit().await.is_err()
+    {
+        return Err(ImportHitsError {
+            message: "failed to write the import into the database".into(),
+            http_status_code: 500,
+        });
+    }
+
+    for pack in import.new_packs.iter().chain(import.changed_packs.iter()) {
+        hs.lock().insert_pack(pack.clone());
+    }
+
+    for hit in import.new_hits.iter().chain(import.changed_hits.iter()) {
+        hs.lock().remove_hit(&HitId::Id(hit.id));
+
+        hs.lock().insert_hit(hit.clone());
+
+        if !hit.downloaded {
+            hs.lock().download_hit(hit.clone());
+        }
+    }
+
+    Ok(Json(import.to_payload(true)))
+}
+
+/// # Reload hits
+///
+/// This endpoint allows authenticated users with hits and packs write permissions to
+/// load the hits from the configured hit sources again without restarting the server.
+/// New and changed hits and packs get merged into the database, removed ones get marked as deleted.
+/// Running games keep their hits, new games will use the reloaded ones.
+/// If the sources contain any errors, nothing gets changed and all errors are returned.
+
+#[openapi(tag = "Hits")]
+#[post("/hits/reload")]
+pub async fn reload_hits(
+    user: UserAuthenticator,
+    serv: &State<ServiceStore>,
+    db: &State<HitsterConfig>,
+    queue: &State<Sender<GlobalEvent>>,
+) -> Result<Json<MergeSummary>, ReloadHitsError> {
+    if !user
+        .0
+        .permissions
+        .contains(Permissions::WRITE_HITS | Permissions::WRITE_PACKS)
+    {
+        return Err(ReloadHitsError {
+            message: "permission denied".into(),
+            errors: vec![],
+            http_status_code: 401,
+        });
+    }
+
+    merge_db::reload_hits(&db.0, serv, queue).await.map(Json)
+}
+
+#[derive(FromRow)]
+struct HitConflictRow {
+    hit_id: Uuid,
+    #[sqlx(flatten)]
+    version: HitVersion,
+    created_at: OffsetDateTime,
+}
+
+/// # Get hit conflicts
+///
+/// Hits which got changed by an admin and within the codebase since the last merge conflict with each other.
+/// The server keeps the version from the database until an admin decides which version to keep.
+/// This endpoint returns all conflicts along with both versions of the hit.
+/// The authenticated user needs to have write permissions for hits.
+
+#[openapi(tag = "Hits")]
+#[get("/hits/conflicts")]
+pub async fn get_hit_conflicts(
+    user: UserAuthenticator,
+    serv: &State<ServiceStore>,
+    mut db: Connection<HitsterConfig>,
+) -> Result<Json<Vec<HitConflictPayload>>, GetHitConflictsError> {
+    if !user.0.permissions.contains(Permissions::WRITE_HITS) {
+        return Err(GetHitConflictsError {
+            message: "permission denied".into(),
+            http_status_code: 401,
+        });
+    }
+
+    let conflicts = sqlx::query_as::<_, HitConflictRow>(
+        "
+SELECT
+    hit_id,
+    title,
+    artist,
+    yt_id,
+    source,
+    location,
+    year,
+    playback_offset,
+    belongs_to,
+    playback_length,
+    snippets,
+    created_at
+FROM hits_merge_conflicts ORDER BY created_at",
+    )
+    .fetch_all(&mut **db)
+    .await
+    .map_err(|_| GetHitConflictsError {
+        message: "failed to read conflicts".into(),
+        http_status_code: 500,
+    })?;
+
+    let hs = serv.hit_service();
+    let hsl = hs.lock();
+
+    Ok(Json(
+        conflicts
+            .into_iter()
+            .filter_map(|conflict| {
+                let hit = hsl.get_hit(&HitId::Id(conflict.hit_id))?;
+                let mut codebase = hit.clone();
+
+                conflict.version.apply(&mut codebase);
+
+                Some(HitConflictPayload {
+                    database: hit.into(),
+                    codebase: (&codebase).into(),
+                    created_at: conflict.created_at,
+                })
+            })
+            .collect::<Vec<_>>(),
+    ))
+}
+
+/// # Resolve a hit conflict
+///
+/// Decide which version of a conflicting hit to keep.
+/// Keeping the database version ignores the changes from the codebase until the hit gets changed there again.
+/// Keeping the codebase version overwrites the changes made within the database.
+/// The authenticated user needs to have write permissions for hits.
+
+#[openapi(tag = "Hits")]
+#[post("/hits/<hit_id>/conflict", format = "json", data = "<resolution>")]
+pub async fn resolve_hit_conflict(
+    hit_id: Uuid,
+    resolution: Json<ResolveHitConflictPayload>,
+    user: UserAuthenticator,
+    serv: &State<ServiceStore>,
+    mut db: Connection<HitsterConfig>,
+) -> Result<Json<MessageResponse>, ResolveHitConflictError> {
+    if !user.0.permissions.contains(Permissions::WRITE_HITS) {
+        return Err(ResolveHitConflictError {
+            message: "permission denied".into(),
+            http_status_code: 401,
+        });
+    }
+
+    let hs = serv.hit_service();
+
+    let conflict = sqlx::query_as::<_, HitConflictRow>(
+        "
+SELECT
+    hit_id,
+    title,
+    artist,
+    yt_id,
+    source,
+    location,
+    year,
+    playback_offset,
+    belongs_to,
+    playback_length,
+    snippets,
+    created_at
+FROM hits_merge_conflicts WHERE hit_id = ?",
+    )
+    .bind(hit_id)
+    .fetch_optional(&mut **db)
+    .await
+    .ok()
+    .flatten();
+
+    let (Some(conflict), Some(mut hit)) =
+        (conflict, hs.lock().get_hit(&HitId::Id(hit_id)).cloned())
+    else {
+        return Err(ResolveHitConflictError {
+            message: "conflict not found".into(),
+            http_status_code: 404,
+        });
+    };
+
+    let keep_codebase = resolution.keep == HitConflictSide::Codebase;
+    let previous = hit.clone();
+
+    if keep_codebase {
+        conflict.version.apply(&mut hit);
+        hit.last_modified = OffsetDateTime::now_utc();
+        hit.downloaded = hit.exists(&transcoding::extensions());
+    }
+
+    let mut tx = sqlx::Connection::begin(&mut **db)
+        .await
+        .map_err(|_| ResolveHitConflictError {
+            message: "failed to start a transaction".into(),
+            http_status_code: 500,
+        })?;
+
+    // the codebase version becomes the new merge base either way,
+    // so the conflict won't come up again until the codebase changes the hit once more
+    if (keep_codebase
+        && (record_hit_baseline(&mut tx, &previous).await.is_err()
+            || update_hit_rows(&mut tx, &hit).await.is_err()
+            || record_hit_history(&mut tx, &hit, HistoryAction::Update, &user.0)
+                .await
+                .is_err()))
+        || merge_db::store_merge_base(&mut *tx, hit_id, &conflict.version)
+            .await
+            .is_err()
+        || merge_db::remove_merge_conflict(&mut *tx, hit_id)
+            .await
+            .is_err()
+        || tx.commit().await.is_err()
+    {
+        return Err(ResolveHitConflictError {
+            message: "failed to resolve the conflict".into(),
+            http_status_code: 500,
+        });
+    }
+
+    if keep_codebase {
+        hs.lock().remove_hit(&HitId::Id(hit_id));
+
+        hs.lock().insert_hit(hit.clone());
+
+        if !hit.downloaded {
+            hs.lock().download_hit(hit);
+        }
+    }
+
+    Ok(Json(MessageResponse {
+        message: "conflict resolved successfully".into(),
+        r#type: "success".into(),
+    }))
+}
+
+/// # Get the history of a hit
+///
+/// Every change users made to a hit is stored as a new version, including the packs it belongs to.
+/// This endpoint returns all versions of a hit, the newest one first, along with the user who made the change.
+/// The history of deleted hits is kept as well.
+/// The authenticated user needs to have write permissions for hits.
+
+#[openapi(tag = "Hits")]
+#[get("/hits/<hit_id>/history")]
+pub async fn get_hit_history(
+    hit_id: Uuid,
+    user: UserAuthenticator,
+    mut db: Connection<HitsterConfig>,
+) -> Result<Json<Vec<HitHistoryPayload>>, GetHistoryError> {
+    if !user.0.permissions.contains(Permissions::WRITE_HITS) {
+        return Err(GetHistoryError {
+            message: "permission denied".into(),
+            http_status_code: 401,
+        });
+    }
+
+    let history = sqlx::query_as::<_, HitHistoryRow>(
+        r#"
+SELECT
+    version,
+    action,
+    user_id,
+    user_name,
+    title,
+    artist,
+    yt_id,
+    source,
+    location,
+    year,
+    playback_offset,
+    belongs_to,
+    playback_length,
+    snippets,
+    packs,
+    created_at
+FROM hits_history WHERE hit_id = ? ORDER BY version DESC"#,
+    )
+    .bind(hit_id)
+    .fetch_all(&mut **db)
+    .await
+    .map_err(|_| GetHistoryError {
+        message: "failed to read history".into(),
+        http_status_code: 500,
+    })?;
+
+    if history.is_empty() {
+        return Err(GetHistoryError {
+            message: "no history found for this hit".into(),
+            http_status_code: 404,
+        });
+    }
+
+    Ok(Json(
+        history
+            .into_iter()
+            .map(|row| HitHistoryPayload {
+                hit: (&row.to_hit(hit_id)).into(),
+                version: row.version,
+                action: row.action.into(),
+                user_id: row.user_id,
+                user_name: row.user_name,
+                created_at: row.created_at,
+            })
+            .collect::<Vec<_>>(),
+    ))
+}
+
+/// # Revert a hit
+///
+/// Restore a hit to a previous version from its history. Deleted hits get restored as well.
+/// Packs which don't exist anymore are left out. The revert itself gets recorded as a new version.
+/// The authenticated user needs to have write permissions for hits.
+
+#[openapi(tag = "Hits")]
+#[post("/hits/<hit_id>/history/<version>/revert")]
+pub async fn revert_hit(
+    hit_id: Uuid,
+    version: u32,
+    user: UserAuthenticator,
+    serv: &State<ServiceStore>,
+    mut db: Connection<HitsterConfig>,
+) -> Result<Json<FullHitPayload>, RevertError> {
+    if !user.0.permissions.contains(Permissions::WRITE_HITS) {
+        return Err(RevertError {
+            message: "permission denied".into(),
+            http_status_code: 401,
+        });
+    }
+
+    let Some(row) = sqlx::query_as::<_, HitHistoryRow>(
+        r#"
+SELECT
+    version,
+    action,
+    user_id,
+    user_name,
+    title,
+    artist,
+    yt_id,
+    source,
+    location,
+    year,
+    playback_offset,
+    belongs_to,
+    playback_length,
+    snippets,
+    packs,
+    created_at
+FROM hits_history WHERE hit_id = ? AND version = ?"#,
+    )
+    .bind(hit_id)
+    .bind(version)
+    .fetch_optional(&mut **db)
+    .await
+    .map_err(|_| RevertError {
+        message: "failed to read history".into(),
+        http_status_code: 500,
+    })?
+    else {
+        return Err(RevertError {
+            message: "version not found".into(),
+            http_status_code: 404,
+        });
+    };
+
+    let hs = serv.hit_service();
+    let mut hit = row.to_hit(hit_id);
+
+    hit.packs.retain(|p| hs.lock().get_pack(*p).is_some());
+    hit.last_modified = OffsetDateTime::now_utc();
+    hit.downloaded = hit.exists(&transcoding::extensions());
+
+    if source_in_use(&mut db, &hit)
+        .await
+        .map_err(|_| RevertError {
+            message: "failed to read hits".into(),
+            http_status_code: 500,
+        })?
+    {
+        return Err(RevertError {
+            message: "another hit uses the same YouTube ID, file or URL".into(),
+            http_status_code: 409,
+        });
+    }
+
+    let mut tx = sqlx::Connection::begin(&mut **db)
+        .await
+        .map_err(|_| RevertError {
+            message: "failed to start a transaction".into(),
+            http_status_code: 500,
+        })?;
+
+    let exists = sqlx::query("SELECT 1 FROM hits WHERE id = ?")
+        .bind(hit_id)
+        .fetch_optional(&mut *tx)
+        .await
+        .map(|r| r.is_some());
+
+    let result = match exists {
+        Ok(true) => {
+            // deleted hits from the codebase are only marked as such
+            sqlx::query("UPDATE hits SET marked_for_deletion = ? WHERE id = ?")
+                .bind(false)
+                .bind(hit_id)
+                .execute(&mut *tx)
+                .await
+                .map(|_| ())
+                .and(update_hit_rows(&mut tx, &hit).await)
+        }
+        Ok(false) => insert_hit_rows(&mut tx, &hit).await,
+        Err(e) => Err(e),
+    };
+
+    if result.is_err()
+        || record_hit_history(&mut tx, &hit, HistoryAction::Revert, &user.0)
+            .await
+            .is_err()
+        || tx.commit().await.is_err()
+    {
+        return Err(RevertError {
+            message: "failed to restore the hit".into(),
+            http_status_code: 500,
+        });
+    }
+
+    hs.lock().remove_hit(&HitId::Id(hit_id));
+
+    hs.lock().insert_hit(hit.clone());
+
+    if !hit.downloaded {
+        hs.lock().download_hit(hit.clone());
+    }
+
+    Ok(Json((&hit).into()))
+}
+
+/// # Get the history of a pack
+///
+/// Every change users made to a pack is stored as a new version.
+/// This endpoint returns all versions of a pack, the newest one first, along with the user who made the change.
+/// The history of deleted packs is kept as well.
+/// The authenticated user needs to have write permissions for packs.
+
+#[openapi(tag = "Hits")]
+#[get("/hits/packs/<pack_id>/history")]
+pub async fn get_pack_history(
+    pack_id: Uuid,
+    user: UserAuthenticator,
+    mut db: Connection<HitsterConfig>,
+) -> Result<Json<Vec<PackHistoryPayload>>, GetHistoryError> {
+    if !user.0.permissions.contains(Permissions::WRITE_PACKS) {
+        return Err(GetHistoryError {
+            message: "permission denied".into(),
+            http_status_code: 401,
+        });
+    }
+
+    let history = sqlx::query_as::<_, PackHistoryRow>(
+        r#"
+SELECT
+    version,
+    action,
+    user_id,
+    user_name,
+    name,
+    created_at
+FROM packs_history WHERE pack_id = ? ORDER BY version DESC"#,
+    )
+    .bind(pack_id)
+    .fetch_all(&mut **db)
+    .await
+    .map_err(|_| GetHistoryError {
+        message: "failed to read history".into(),
+        http_status_code: 500,
+    })?;
+
+    if history.is_empty() {
+        return Err(GetHistoryError {
+            message: "no history found for this pack".into(),
+            http_status_code: 404,
+        });
+    }
+
+    Ok(Json(
+        history
+            .into_iter()
+            .map(|row| PackHistoryPayload {
+                version: row.version,
+                action: row.action.into(),
+                user_id: row.user_id,
+                user_name: row.user_name,
+                name: row.name,
+                created_at: row.created_at,
+            })
+            .collect::<Vec<_>>(),
+    ))
+}
+
+/// # Revert a pack
+///
+/// Restore a pack to a previous version from its history. Deleted packs get restored as well,
+/// hits which still belong to the pack within the database return into it.
+/// The revert itself gets recorded as a new version.
+/// The authenticated user needs to have write permissions for packs.
+
+#[openapi(tag = "Hits")]
+#[post("/hits/packs/<pack_id>/history/<version>/revert")]
+pub async fn revert_pack(
+    pack_id: Uuid,
+    version: u32,
+    user: UserAuthenticator,
+    serv: &State<ServiceStore>,
+    mut db: Connection<HitsterConfig>,
+) -> Result<Json<PackPayload>, RevertError> {
+    if !user.0.permissions.contains(Permissions::WRITE_PACKS) {
+        return Err(RevertError {
+            message: "permission denied".into(),
+            http_status_code: 401,
+        });
+    }
+
+    let Some(row) = sqlx::query_as::<_, PackHistoryRow>(
+        r#"
+SELECT
+    version,
+    action,
+    user_id,
+    user_name,
+    name,
+    created_at
+FROM packs_history WHERE pack_id = ? AND version = ?"#,
+    )
+    .bind(pack_id)
+    .bind(version)
+    .fetch_optional(&mut **db)
+    .await
+    .map_err(|_| RevertError {
+        message: "failed to read history".into(),
+        http_status_code: 500,
+    })?
+    else {
+        return Err(RevertError {
+            message: "version not found".into(),
+            http_status_code: 404,
+        });
+    };
+
+    if sqlx::query("SELECT 1 FROM packs WHERE name = ? AND id != ?")
+        .bind(&row.name)
+        .bind(pack_id)
+        .fetch_optional(&mut **db)
+        .await
+        .map_err(|_| RevertError {
+            message: "failed to read packs".into(),
+            http_status_code: 500,
+        })?
+        .is_some()
+    {
+        return Err(RevertError {
+            message: "a pack with that name already exists".into(),
+            http_status_code: 409,
+        });
+    }
+
+    let pack = Pack {
+        id: pack_id,
+        name: row.name,
+        last_modified: OffsetDateTime::now_utc(),
+    };
+
+    let mut tx = sqlx::Connection::begin(&mut **db)
+        .await
+        .map_err(|_| RevertError {
+            message: "failed to start a transaction".into(),
+            http_status_code: 500,
+        })?;
+
+    let result = match sqlx::query("SELECT 1 FROM packs WHERE id = ?")
+        .bind(pack_id)
+        .fetch_optional(&mut *tx)
+        .await
+    {
+        Ok(Some(_)) => sqlx::query(
+            "UPDATE packs SET name = ?, last_modified = ?, marked_for_deletion = ? WHERE id = ?",
+        )
+        .bind(&pack.name)
+        .bind(pack.last_modified)
+        .bind(false)
+        .bind(pack_id)
+        .execute(&mut *tx)
+        .await
+        .map(|_| ()),
+        Ok(None) => sqlx::query(
+            r#"
+INSERT INTO packs (
+    id, name, last_modified, custom, marked_for_deletion) VALUES (
+    ?, ?, ?, ?, ?)"#,
+        )
+        .bind(pack_id)
+        .bind(&pack.name)
+        .bind(pack.last_modified)
+        .bind(true)
+        .bind(false)
+        .execute(&mut *tx)
+        .await
+        .map(|_| ()),
+        Err(e) => Err(e),
+    };
+
+    if result.is_err()
+        || record_pack_history(&mut tx, &pack, HistoryAction::Revert, &user.0)
+            .await
+            .is_err()
+    {
+        return Err(RevertError {
+            message: "failed to restore the pack".into(),
+            http_status_code: 500,
+        });
+    }
+
+    let hit_ids = sqlx::query_scalar::<_, Uuid>(
+        "SELECT hit_id FROM hits_packs WHERE pack_id = ? AND marked_for_deletion = ?",
+    )
+    .bind(pack_id)
+    .bind(false)
+    .fetch_all(&mut *tx)
+    .await
+    .unwrap_or_default();
+
+    if tx.commit().await.is_err() {
+        return Err(RevertError {
+            message: "failed to restore the pack".into(),
+            http_status_code: 500,
+        });
+    }
+
+    let hs = serv.hit_service();
+    let mut hsl = hs.lock();
+
+    hsl.insert_pack(pack.clone());
+
+    for hit_id in hit_ids.into_iter() {
+        if let Some(mut hit) = hsl.get_hit(&HitId::Id(hit_id)).cloned()
+            && !hit.packs.contains(&pack_id)
+        {
+            hit.packs.push(pack_id);
+            hsl.remove_hit(&HitId::Id(hit_id));
+            hsl.insert_hit(hit);
+        }
+    }
+
+    Ok(Json(PackPayload {
+        id: pack.id,
+        name: pack.name.clone(),
+        hits: hsl.get_hits_for_packs(&[pack_id]).len(),
+    }))
+}
+
+/// # Get duplicate hits
+///
+/// Find hits which likely are the same song, e.g. because it got uploaded as different videos.
+/// Hits are considered duplicates if artist and title match, ignoring case and accents,
+/// and the years differ by one year at most. Hits which belong to different movies, musicals etc. aren't duplicates.
+/// Each duplicate also has an automatic issue filed.
+/// The authenticated user needs to have write permissions for hits.
+
+#[openapi(tag = "Hits")]
+#[get("/hits/duplicates")]
+pub async fn get_hit_duplicates(
+    user: UserAuthenticator,
+    serv: &State<ServiceStore>,
+) -> Result<Json<Vec<HitDuplicatePayload>>, GetHitDuplicatesError> {
+    if !user.0.permissions.contains(Permissions::WRITE_HITS) {
+        return Err(GetHitDuplicatesError {
+            message: "permission denied".into(),
+            http_status_code: 401,
+        });
+    }
+
+    let hs = serv.hit_service();
+    let hsl = hs.lock();
+    let mut duplicates = hsl.find_duplicates(DUPLICATE_MAX_YEAR_DISTANCE);
+
+    duplicates.sort_by(|(a, _), (b, _)| {
+        a.artist
+            .cmp(&b.artist)
+            .then(a.title.cmp(&b.title))
+            .then(a.year.cmp(&b.year))
+    });
+
+    Ok(Json(
+        duplicates
+            .into_iter()
+            .map(|(hit, duplicate)| HitDuplicatePayload {
+                hit: hit.into(),
+                duplicate: duplicate.into(),
+            })
+            .collect::<Vec<_>>(),
+    ))
+}
+
+/// # Merge a duplicate into a hit
+///
+/// Combine two hits which are the same song. The hit keeps its info and additionally joins all packs of the duplicate,
+/// the duplicate gets deleted afterwards. Both changes are recorded within the history of the hits.
+/// The authenticated user needs to have write permissions for hits.
+
+#[openapi(tag = "Hits")]
+#[post("/hits/<hit_id>/merge", format = "json", data = "<merge>")]
+pub async fn merge_hits(
+    hit_id: Uuid,
+    merge: Json<MergeHitPayload>,
+    user: UserAuthenticator,
+    serv: &State<ServiceStore>,
+    db: &State<HitsterConfig>,
+    queue: &State<Sender<GlobalEvent>>,
+) -> Result<Json<FullHitPayload>, MergeHitsError> {
+    if !user.0.permissions.contains(Permissions::WRITE_HITS) {
+        return Err(MergeHitsError {
+            message: "permission denied".into(),
+            http_status_code: 401,
+        });
+    }
+
+    if hit_id == merge.duplicate {
+        return Err(MergeHitsError {
+            message: "a hit can't be merged with itself".into(),
+            http_status_code: 400,
+        });
+    }
+
+    let hs = serv.hit_service();
+
+    let hit = hs.lock().get_hit(&HitId::Id(hit_id)).cloned();
+    let duplicate = hs.lock().get_hit(&HitId::Id(merge.duplicate)).cloned();
+
+    let (Some(hit), Some(duplicate)) = (hit, duplicate) else {
+        return Err(MergeHitsError {
+            message: "hit not found".into(),
+            http_status_code: 404,
+        });
+    };
+
+    let mut merged = hit.clone();
+
+    for pack in duplicate.packs.iter() {
+        if !merged.packs.contains(pack) {
+            merged.packs.push(*pack);
+        }
+    }
+
+    merged.last_modified = OffsetDateTime::now_utc();
+
+    let mut tx = db.0.begin().await.map_err(|_| MergeHitsError {
+        message: "failed to start a transaction".into(),
+        http_status_code: 500,
+    })?;
 
-    if hs.lock().get_pack(pack_id).is_none() {
-        return Err(DeletePackError {
-            message: "pack not found".into(),
-            http_status_code: 404,
+    if record_hit_baseline(&mut tx, &hit).await.is_err()
+        || update_hit_rows(&mut tx, &merged).await.is_err()
+        || record_hit_history(&mut tx, &merged, HistoryAction::Update, &user.0)
+            .await
+            .is_err()
+        || record_hit_history(&mut tx, &duplicate, HistoryAction::Delete, &user.0)
+            .await
+            .is_err()
+        || delete_hit_rows(&mut tx, duplicate.id).await.is_err()
+        || tx.commit().await.is_err()
+    {
+        return Err(MergeHitsError {
+            message: "failed to merge the hits".into(),
+            http_status_code: 500,
         });
     }
 
-    hs.lock().remove_pack(pack_id);
-
-    let pack = sqlx::query_as!(PackRow, "SELECT custom FROM packs WHERE id = ?", pack_id)
-        .fetch_one(&mut **db)
-        .await
-        .unwrap();
+    {
+        let mut hsl = hs.lock();
 
-    if pack.custom {
-        let _ = sqlx::query!("DELETE FROM packs WHERE id = ?", pack_id)
-            .execute(&mut **db)
-            .await;
-        let _ = sqlx::query!("DELETE FROM hits_packs WHERE pack_id = ?", pack_id)
-            .execute(&mut **db)
-            .await;
-    } else {
-        let _ = sqlx::query!(
-            "UPDATE packs SET marked_for_deletion = ? WHERE id = ?",
-            true,
-            pack_id
-        )
-        .execute(&mut **db)
-        .await;
+        hsl.remove_hit(&HitId::Id(duplicate.id));
+        hsl.remove_hit(&HitId::Id(hit_id));
+        hsl.insert_hit(merged.clone());
     }
 
-    Ok(Json(MessageResponse {
-        message: "pack deleted successfully".into(),
-        r#type: "success".into(),
-    }))
+    flag_duplicate_hits(&db.0, &hs, queue).await;
+
+    Ok(Json((&merged).into()))
 }
 
-/// # Create a new pack
+async fn fetch_hit_download(
+    conn: &mut SqliteConnection,
+    hit_id: Uuid,
+) -> Result<Option<HitDownload>, sqlx::Error> {
+    sqlx::query_as::<_, HitDownload>("SELECT * FROM hit_downloads WHERE hit_id = ?")
+        .bind(hit_id)
+        .fetch_optional(&mut *conn)
+        .await
+}
+
+/// # Get the download queue
 ///
-/// Create a new pack. The authenticated user needs to have pack write permissions.
+/// List all hits which still need to be downloaded, in the order they will be downloaded in.
+/// Hits from packs selected by games which didn't start yet come first, followed by the priority and age of the download.
+/// The results will be paginated, use the parameters to specify the page size.
+/// Use the optional `status` parameter to only list downloads in one of the given states.
+/// Supported values are `queued`, `downloading`, `processing`, `failed` and `cancelled`.
+/// The authenticated user needs to have write permissions for hits.
 
 #[openapi(tag = "Hits")]
-#[post("/hits/packs", format = "json", data = "<pack>")]
-pub async fn create_pack(
-    pack: Json<CreatePackPayload>,
+#[get("/hits/downloads?<query..>")]
+pub async fn get_hit_downloads(
+    query: HitDownloadsQuery,
     user: UserAuthenticator,
     serv: &State<ServiceStore>,
     mut db: Connection<HitsterConfig>,
-) -> Result<Json<PackPayload>, CreatePackError> {
-    if !user.0.permissions.contains(Permissions::WRITE_PACKS) {
-        return Err(CreatePackError {
+) -> Result<Json<PaginatedResponse<HitDownloadPayload>>, GetHitDownloadsError> {
+    if !user.0.permissions.contains(Permissions::WRITE_HITS) {
+        return Err(GetHitDownloadsError {
             message: "permission denied".into(),
             http_status_code: 401,
         });
     }
 
-    if sqlx::query!("SELECT * FROM packs WHERE name = ?", pack.name)
-        .fetch_optional(&mut **db)
-        .await
-        .unwrap()
-        .is_some()
-    {
-        return Err(CreatePackError {
-            message: "a pack with that name already exists".into(),
-            http_status_code: 409,
+    let statuses = query.status.clone().unwrap_or_default();
+    let start = query.start.unwrap_or(1).max(1);
+    let amount = query.amount.unwrap_or(50);
+
+    let mut qb = sqlx::QueryBuilder::<sqlx::Sqlite>::new("SELECT * FROM hit_downloads WHERE TRUE");
+
+    if !statuses.is_empty() {
+        qb.push(" AND status IN (");
+        let mut separated = qb.separated(", ");
+        statuses.iter().for_each(|status| {
+            separated.push_bind(<&'static str>::from(*status));
         });
+        separated.push_unseparated(")");
     }
 
-    let pack = Pack {
-        name: pack.name.clone(),
-        id: Uuid::new_v4(),
-        last_modified: OffsetDateTime::now_utc(),
+    let downloads = qb
+        .build_query_as::<HitDownload>()
+        .fetch_all(&mut **db)
+        .await
+        .map_err(|_| GetHitDownloadsError {
+            message: "failed to read downloads".into(),
+            http_status_code: 500,
+        })?;
+
+    let hs = serv.hit_service();
+    let mut downloads = {
+        let hsl = hs.lock();
+
+        downloads
+            .into_iter()
+            .filter_map(|download| {
+                hsl.get_hit(&HitId::Id(download.hit_id))
+                    .map(|hit| (download, hit.clone()))
+            })
+            .collect::<Vec<_>>()
     };
 
-    let _ = sqlx::query!(
-        r#"
-INSERT INTO packs (
-    id, name, last_modified, custom, marked_for_deletion) VALUES (
-    ?, ?, ?, ?, ?)"#,
-        pack.id,
-        pack.name,
-        pack.last_modified,
-        true,
-        false
-    )
-    .execute(&mut **db)
-    .await;
+    let gs = serv.game_service();
+    let packs = gs.lock().open_packs();
 
-    let hs = serv.hit_service();
+    sort_hit_downloads(&mut downloads, &packs);
 
-    hs.lock().insert_pack(pack.clone());
+    let total = downloads.len();
+    let results = downloads
+        .into_iter()
+        .skip(start - 1)
+        .take(amount)
+        .map(|(download, hit)| HitDownloadPayload {
+            hit: (&hit).into(),
+            download,
+        })
+        .collect::<Vec<_>>();
 
-    Ok(Json(PackPayload {
-        name: pack.name.clone(),
-        id: pack.id,
-        hits: 0,
+    Ok(Json(PaginatedResponse {
+        start,
+        end: start + results.len() - 1,
+        results,
+        total,
     }))
 }
 
-/// # Update a pack
+/// # Retry a hit download
 ///
-/// Update a pack. The authenticated user needs to have pack write permissions.
+/// Queue a download again, e.g. after it failed or got cancelled.
+/// The attempts get reset and the download won't wait for a back-off.
+/// The authenticated user needs to have write permissions for hits.
 
 #[openapi(tag = "Hits")]
-#[patch("/hits/packs/<pack_id>", format = "json", data = "<pack>")]
-pub async fn update_pack(
-    pack_id: Uuid,
-    pack: Json<CreatePackPayload>,
+#[post("/hits/downloads/<hit_id>/retry")]
+pub async fn retry_hit_download(
+    hit_id: Uuid,
     user: UserAuthenticator,
     serv: &State<ServiceStore>,
     mut db: Connection<HitsterConfig>,
-) -> Result<Json<MessageResponse>, UpdatePackError> {
-    if !user.0.permissions.contains(Permissions::WRITE_PACKS) {
-        return Err(UpdatePackError {
+) -> Result<Json<HitDownloadPayload>, RetryHitDownloadError> {
+    if !user.0.permissions.contains(Permissions::WRITE_HITS) {
+        return Err(RetryHitDownloadError {
             message: "permission denied".into(),
             http_status_code: 401,
         });
     }
 
     let hs = serv.hit_service();
+    let hit = hs.lock().get_hit(&HitId::Id(hit_id)).cloned();
+
+    let (mut download, hit) = match (fetch_hit_download(&mut db, hit_id).await, hit) {
+        (Ok(Some(download)), Some(hit)) => (download, hit),
+        (Ok(_), _) => {
+            return Err(RetryHitDownloadError {
+                message: "download not found".into(),
+                http_status_code: 404,
+            });
+        }
+        (Err(_), _) => {
+            return Err(RetryHitDownloadError {
+                message: "failed to read download".into(),
+                http_status_code: 500,
+            });
+        }
+    };
 
-    if hs.lock().get_pack(pack_id).is_some() {
-        let pack = Pack {
-            name: pack.name.clone(),
-            id: pack_id,
-            last_modified: OffsetDateTime::now_utc(),
-        };
+    if download.status == HitDownloadStatus::Downloading
+        || download.status == HitDownloadStatus::Processing
+    {
+        return Err(RetryHitDownloadError {
+            message: "the hit is downloading already".into(),
+            http_status_code: 409,
+        });
+    }
 
-        let _ = sqlx::query!(
-            r#"
-UPDATE packs SET
-    name = $1,
-    last_modified = $2
-WHERE id = $3"#,
-            pack.name,
-            pack.last_modified,
-            pack.id,
-        )
-        .execute(&mut **db)
-        .await;
+    let now = OffsetDateTime::now_utc();
 
-        hs.lock().insert_pack(pack.clone());
+    download.status = HitDownloadStatus::Queued;
+    download.attempts = 0;
+    download.next_attempt_at = now;
+    download.last_error = None;
+    download.last_modified = now;
 
-        Ok(Json(MessageResponse {
-            message: "pack updated successfully".into(),
-            r#type: "success".into(),
-        }))
-    } else {
-        Err(UpdatePackError {
-            message: "a pack with that id doesn't exist".into(),
-            http_status_code: 404,
-        })
-    }
+    // resets the download and wakes up the download queue
+    hs.lock().download_hit(hit.clone());
+
+    Ok(Json(HitDownloadPayload {
+        hit: (&hit).into(),
+        download,
+    }))
 }
 
-/// # Create a new hit
+/// # Cancel a hit download
 ///
-/// Create a new hit. The hit will be added to the download queue and will be available in all new games once the download finishes.
-/// The authenticated user needs to have hit write permissions.
+/// Remove a hit from the download queue, it stays unavailable until the download gets retried.
+/// Running downloads get discarded once they finish, hits which are getting processed already can't be cancelled anymore.
+/// The authenticated user needs to have write permissions for hits.
 
 #[openapi(tag = "Hits")]
-#[post("/hits", format = "json", data = "<hit>")]
-pub async fn create_hit(
-    hit: Json<FullHitPayload>,
+#[post("/hits/downloads/<hit_id>/cancel")]
+pub async fn cancel_hit_download(
+    hit_id: Uuid,
     user: UserAuthenticator,
     serv: &State<ServiceStore>,
+    queue: &State<Sender<GlobalEvent>>,
     mut db: Connection<HitsterConfig>,
-) -> Result<Json<FullHitPayload>, CreateHitError> {
+) -> Result<Json<HitDownloadPayload>, CancelHitDownloadError> {
     if !user.0.permissions.contains(Permissions::WRITE_HITS) {
-        return Err(CreateHitError {
+        return Err(CancelHitDownloadError {
             message: "permission denied".into(),
             http_status_code: 401,
         });
     }
 
-    if sqlx::query("SELECT 1 FROM hits WHERE yt_id = ?")
-        .bind(&hit.yt_id)
-        .fetch_optional(&mut **db)
-        .await
-        .unwrap()
-        .is_some()
+    let hit = serv
+        .hit_service()
+        .lock()
+        .get_hit(&HitId::Id(hit_id))
+        .cloned();
+
+    let (mut download, hit) = match (fetch_hit_download(&mut db, hit_id).await, hit) {
+        (Ok(Some(download)), Some(hit)) => (download, hit),
+        (Ok(_), _) => {
+            return Err(CancelHitDownloadError {
+                message: "download not found".into(),
+                http_status_code: 404,
+            });
+        }
+        (Err(_), _) => {
+            return Err(CancelHitDownloadError {
+                message: "failed to read download".into(),
+                http_status_code: 500,
+            });
+        }
+    };
+
+    if download.status == HitDownloadStatus::Cancelled
+        || download.status == HitDownloadStatus::Processing
     {
-        return Err(CreateHitError {
-            message: "a hit for that YouTube ID already exists".into(),
+        return Err(CancelHitDownloadError {
+            message: "the download can't be cancelled".into(),
             http_status_code: 409,
         });
     }
 
-    let mut hit = Hit {
-        title: hit.title.clone(),
-        artist: hit.artist.clone(),
-        last_modified: OffsetDateTime::now_utc(),
-        id: Uuid::new_v4(),
-        yt_id: hit.yt_id.clone(),
-        belongs_to: hit.belongs_to.clone(),
-        playback_offset: hit.playback_offset,
-        year: hit.year,
-        packs: hit.packs.clone(),
-        downloaded: false,
-    };
-
-    hit.downloaded = hit.exists();
+    download.status = HitDownloadStatus::Cancelled;
+    download.last_modified = OffsetDateTime::now_utc();
 
-    let _ = sqlx::query!(
-        r#"
-INSERT INTO hits (
-    id,
-    artist,
-    title,
-    year,
-    belongs_to,
-    yt_id,
-    playback_offset,
-    last_modified,
-    downloaded,
-    custom,
-    marked_for_deletion
-) VALUES (
-    ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"#,
-        hit.id,
-        hit.artist,
-        hit.title,
-        hit.year,
-        hit.belongs_to,
-        hit.yt_id,
-        hit.playback_offset,
-        hit.last_modified,
-        hit.downloaded,
-        true,
-        false
+    // the download could've finished in the meantime
+    match sqlx::query(
+        "UPDATE hit_downloads SET status = ?, last_modified = ? WHERE hit_id = ? AND status NOT IN (?, ?)",
     )
+    .bind(<&'static str>::from(download.status))
+    .bind(download.last_modified)
+    .bind(hit_id)
+    .bind(<&'static str>::from(HitDownloadStatus::Cancelled))
+    .bind(<&'static str>::from(HitDownloadStatus::Processing))
     .execute(&mut **db)
-    .await;
-
-    for pack in hit.packs.iter() {
-        let _ = sqlx::query!(
-            r#"
-INSERT INTO hits_packs (
-    hit_id,
-    pack_id,
-    custom,
-    marked_for_deletion) VALUES (
-    ?, ?, ?, ?)"#,
-            hit.id,
-            pack,
-            true,
-            false
-        )
-        .execute(&mut **db)
-        .await;
+    .await
+    {
+        Ok(result) if result.rows_affected() > 0 => {}
+        Ok(_) => {
+            return Err(CancelHitDownloadError {
+                message: "the download can't be cancelled".into(),
+                http_status_code: 409,
+            });
+        }
+        Err(_) => {
+            return Err(CancelHitDownloadError {
+                message: "failed to update download".into(),
+                http_status_code: 500,
+            });
+        }
     }
 
     let hs = serv.hit_service();
+    update_download_count(&mut **db, &hs).await;
+    send_process_hits(&hs, queue);
 
-    if !hit.downloaded {
-        hs.lock().download_hit(hit.clone());
-    }
-
-    hs.lock().insert_hit(hit.clone());
-
-    Ok(Json((&hit).into()))
+    Ok(Json(HitDownloadPayload {
+        hit: (&hit).into(),
+        download,
+    }))
 }
 
-/// # Export hits database
-///
-/// This endpoint allows authenticated users with hits write permissions to
-/// export the hits database of this server in the YAML format used when
-/// deploying hits within the codebase. Use this to transfer hits between server instances.
+/// # Update a hit download
 ///
-/// The query and pack parameters behave similarly to those within the /hits/search endpoint.
+/// Change the priority of a download, downloads with a higher priority get processed first.
+/// Hits from packs selected by games which didn't start yet are still downloaded before all others.
+/// The authenticated user needs to have write permissions for hits.
 
 #[openapi(tag = "Hits")]
-#[get("/hits/export?<query..>")]
-pub async fn export_hits(
-    query: ExportHitsQuery,
+#[patch("/hits/downloads/<hit_id>", format = "json", data = "<download>")]
+pub async fn update_hit_download(
+    hit_id: Uuid,
+    download: Json<UpdateHitDownloadPayload>,
     user: UserAuthenticator,
     serv: &State<ServiceStore>,
-) -> Result<Yaml, ExportHitsError> {
+    mut db: Connection<HitsterConfig>,
+) -> Result<Json<HitDownloadPayload>, UpdateHitDownloadError> {
     if !user.0.permissions.contains(Permissions::WRITE_HITS) {
-        return Err(ExportHitsError {
+        return Err(UpdateHitDownloadError {
             message: "permission denied".into(),
             http_status_code: 401,
         });
     }
 
-    let hs = serv.hit_service();
-    let hsl = hs.lock();
-
-    let hsq = HitSearchQuery {
-        query: query.query,
-        packs: query.pack,
-        start: Some(1),
-        amount: Some(hsl.get_hits().len()),
-        ..Default::default()
+    let hit = serv
+        .hit_service()
+        .lock()
+        .get_hit(&HitId::Id(hit_id))
+        .cloned();
+
+    let (mut updated, hit) = match (fetch_hit_download(&mut db, hit_id).await, hit) {
+        (Ok(Some(updated)), Some(hit)) => (updated, hit),
+        (Ok(_), _) => {
+            return Err(UpdateHitDownloadError {
+                message: "download not found".into(),
+                http_status_code: 404,
+            });
+        }
+        (Err(_), _) => {
+            return Err(UpdateHitDownloadError {
+                message: "failed to read download".into(),
+                http_status_code: 500,
+            });
+        }
     };
 
-    let results = hsl.search_hits(&hsq, None);
-
-    let mut data = HitsterData::new(vec![], vec![]);
+    updated.priority = download.priority;
+    updated.last_modified = OffsetDateTime::now_utc();
 
-    results.results.iter().for_each(|h| {
-        h.packs.iter().for_each(|p| {
-            if data.get_pack(*p).is_none() {
-                data.insert_pack(hsl.get_pack(*p).cloned().unwrap());
-            }
+    if sqlx::query("UPDATE hit_downloads SET priority = ?, last_modified = ? WHERE hit_id = ?")
+        .bind(updated.priority)
+        .bind(updated.last_modified)
+        .bind(hit_id)
+        .execute(&mut **db)
+        .await
+        .is_err()
+    {
+        return Err(UpdateHitDownloadError {
+            message: "failed to update download".into(),
+            http_status_code: 500,
         });
-        data.insert_hit(h.clone());
-    });
+    }
 
-    Ok(Yaml(serde_yml::to_string(&data).unwrap()))
+    Ok(Json(HitDownloadPayload {
+        hit: (&hit).into(),
+        download: updated,
+    }))
 }
@@ -118,7 +118,10 @@ pub async fn events(
             if !can_read_issues
                 && matches!(
                     msg,
-                    GlobalEvent::CreateHitIssue(_) | GlobalEvent::DeleteHitIssue { .. }
+                    GlobalEvent::CreateHitIssue(_)
+                        | GlobalEvent::CreateHitIssueComment(_)
+                        | GlobalEvent::DeleteHitIssue { .. }
+                        | GlobalEvent::UpdateHitIssue(_)
                 )
             {
                 continue;
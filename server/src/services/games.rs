@@ -1,31 +1,88 @@
 use crate::{
-    games::{Game, GameMode, GameSettingsPayload, GameState, Player, PlayerState, Slot},
+    audio,
+    games::{
+        BannedUser, Game, GameDeck, GameEventQueue, GameHistoryAction, GameHistoryEntry, GameMode,
+        GameSettingsPayload, GameState, GameUpdate, NameGuess, NameJudgement, Player, PlayerState,
+        Slot, SlotPayload, Spectator, TeamMember, TieBreaker, VictoryCondition,
+    },
     responses::{
-        ClaimHitError, ConfirmSlotError, GuessSlotError, HitError, JoinGameError, LeaveGameError,
-        SkipHitError, StartGameError, StopGameError, UpdateGameError,
+        ClaimHitError, ConfirmSlotError, GuessSlotError, HitError, JoinGameError, KickPlayerError,
+        LeaveGameError, SkipHitError, StartGameError, StopGameError, UpdateGameError,
     },
     services::{HitService, ServiceHandle},
+    transcoding::TranscodingProfile,
 };
 use hitster_core::{Hit, User};
 use itertools::sorted;
 use rand::{
     distr::{Alphanumeric, SampleString},
     prelude::SliceRandom,
-    rng,
+    random, rng,
 };
+use rocket::tokio::sync::mpsc::UnboundedSender;
 use std::{
     collections::{HashMap, HashSet, VecDeque},
-    sync::Mutex,
+    hash::{DefaultHasher, Hash, Hasher},
+    path::PathBuf,
+    sync::{Mutex, MutexGuard},
 };
+use time::{Duration, OffsetDateTime};
 use uuid::Uuid;
 
+const AUDIO_TOKEN_LIFETIME: Duration = Duration::minutes(1);
+/// how far below the name tolerance guesses still need to be confirmed manually instead of being wrong
+const NAME_AMBIGUITY_RANGE: f64 = 0.2;
+
 pub struct GameServiceData {
     games: HashMap<String, Game>,
+    /// nonces of audio tokens which got used already, when they expire and the jitter their audio gets padded with
+    redeemed_audio_tokens: HashMap<String, OffsetDateTime>,
+}
+
+/// the data an audio token is signed for
+fn audio_token_data(game_id: &str, hit: &Hit, user: &User, claims: &str) -> String {
+    format!("{}:{}:{}:{}", game_id, hit.id, user.id, claims)
+}
+
+/// wether the user may listen to the current hit of the game.
+/// Players always can, spectators only once it got revealed
+fn can_listen(game: &Game, user: &User) -> bool {
+    game.players.iter().any(|p| p.contains(user.id))
+        || (game.state == GameState::Confirming && game.spectators.iter().any(|s| s.id == user.id))
+}
+
+/// the audio file of a hit within a game.
+/// Hits with multiple snippets play a different one depending on the game
+fn snippet_file(game_id: &str, hit: &Hit, profile: &TranscodingProfile) -> PathBuf {
+    // snippets which didn't get cut yet can't be played
+    let offsets = hit
+        .snippet_offsets()
+        .into_iter()
+        .filter(|o| profile.file(hit, *o).is_file())
+        .collect::<Vec<_>>();
+
+    if offsets.is_empty() {
+        return profile.file(hit, hit.playback_offset);
+    }
+
+    let mut hasher = DefaultHasher::new();
+    game_id.hash(&mut hasher);
+    hit.id.hash(&mut hasher);
+
+    profile.file(
+        hit,
+        offsets[(hasher.finish() % offsets.len() as u64) as usize],
+    )
 }
 
 pub struct GameService {
     data: Mutex<GameServiceData>,
     hit_service: ServiceHandle<HitService>,
+    persistence_sender: Option<UnboundedSender<GameUpdate>>,
+    /// the decks of the games as they were persisted last
+    persisted_decks: Mutex<HashMap<String, GameDeck>>,
+    history_sender: Option<UnboundedSender<GameHistoryEntry>>,
+    event_queue: Option<GameEventQueue>,
 }
 
 impl GameService {
@@ -35,15 +92,336 @@ impl GameService {
         }
     }
 
+    fn persist(&self, game: &mut Game) {
+        if let Some(sender) = &self.persistence_sender {
+            // the deck usually holds lots of hits, it only gets sent along when it changed
+            let deck = {
+                let mut decks = self.persisted_decks.lock().unwrap();
+
+                if decks.get(&game.id).is_some_and(|deck| deck.matches(game)) {
+                    None
+                } else {
+                    let deck = GameDeck::from(&*game);
+                    decks.insert(game.id.clone(), deck.clone());
+                    Some(deck)
+                }
+            };
+
+            let hits_remaining = std::mem::take(&mut game.hits_remaining);
+            let remembered_hits = std::mem::take(&mut game.remembered_hits);
+            let copy = game.clone();
+
+            game.hits_remaining = hits_remaining;
+            game.remembered_hits = remembered_hits;
+
+            let _ = sender.send(GameUpdate::Save(Box::new(copy), deck));
+        }
+    }
+
+    fn persist_removal(&self, game_id: &str) {
+        if let Some(sender) = &self.persistence_sender {
+            let _ = sender.send(GameUpdate::Remove(game_id.to_string()));
+        }
+
+        self.persisted_decks.lock().unwrap().remove(game_id);
+
+        if let Some(queue) = &self.event_queue {
+            queue.remove(game_id);
+        }
+    }
+
+    fn record(&self, entry: GameHistoryEntry) {
+        if let Some(sender) = &self.history_sender {
+            let _ = sender.send(entry);
+        }
+    }
+
+    /// sets the point in time the current phase of the game times out
+    fn reset_deadline(&self, game: &mut Game) {
+        let timeout = match game.state {
+            GameState::Guessing => game.guess_timeout,
+            GameState::Intercepting => game.intercept_timeout,
+            GameState::Confirming => game.confirm_timeout,
+            GameState::Open => 0,
+        };
+
+        game.deadline = if timeout > 0 {
+            Some(OffsetDateTime::now_utc() + Duration::seconds(timeout as i64))
+        } else {
+            None
+        };
+    }
+
+    /// passes the creator role on to the next player who isn't virtual,
+    /// the first member of a team who isn't virtual takes it over
+    fn hand_over_creator(&self, game: &mut Game, pos: usize) {
+        let len = game.players.len();
+
+        if let Some(idx) = (1..len)
+            .map(|i| (pos + i) % len)
+            .find(|i| !game.players.get(*i).unwrap().r#virtual)
+        {
+            let plr = game.players.get_mut(pos).unwrap();
+
+            plr.creator = false;
+            plr.members.iter_mut().for_each(|m| m.creator = false);
+
+            let plr = game.players.get_mut(idx).unwrap();
+
+            plr.creator = true;
+
+            if let Some(member) = plr.members.iter_mut().find(|m| !m.r#virtual) {
+                member.creator = true;
+            }
+        }
+    }
+
+    /// flips up the current hit once nobody can intercept anymore
+    fn reveal(&self, game: &mut Game) {
+        let turn_player_pos = game.players.iter().position(|p| p.turn_player).unwrap();
+        let len = game.players.len();
+
+        let winners = game
+            .players
+            .iter()
+            .enumerate()
+            .filter(|(_, p)| {
+                p.guess
+                    .as_ref()
+                    .map(|s| {
+                        (s.from_year == 0 && game.hits_remaining.front().unwrap().year <= s.to_year)
+                            || (s.to_year == 0
+                                && game.hits_remaining.front().unwrap().year >= s.from_year)
+                            || (s.from_year <= game.hits_remaining.front().unwrap().year
+                                && game.hits_remaining.front().unwrap().year <= s.to_year)
+                    })
+                    .unwrap_or(false)
+            })
+            .map(|(i, _)| i)
+            .collect::<Vec<_>>();
+
+        for (i, p) in game.players.iter_mut().enumerate() {
+            if p.guess.is_some() && !winners.contains(&i) {
+                p.wrong_guesses += 1;
+            }
+        }
+
+        if let Some(i) = winners
+            .iter()
+            .copied()
+            .find(|i| game.players.get(*i).unwrap().turn_player)
+        {
+            let player = game.players.get_mut(i).unwrap();
+
+            player
+                .hits
+                .push(game.hits_remaining.front().cloned().unwrap());
+            player.slots = self.get_slots(&player.hits);
+            game.last_scored = Some(player.clone());
+        } else if winners.len() == 1 {
+            let i = *winners.first().unwrap();
+            let player = game.players.get_mut(i).unwrap();
+
+            player
+                .hits
+                .push(game.hits_remaining.front().cloned().unwrap());
+            player.slots = self.get_slots(&player.hits);
+            game.last_scored = Some(player.clone());
+        }
+
+        self.record(GameHistoryEntry {
+            hit_id: game.hits_remaining.front().map(|h| h.id),
+            ..GameHistoryEntry::new(
+                &game.id,
+                GameHistoryAction::Reveal,
+                game.last_scored.as_ref(),
+            )
+        });
+
+        if game.name_hits {
+            let hit = game.hits_remaining.front().unwrap();
+            let tolerance = game.name_tolerance as f64 / 100.0;
+
+            // names which are close, but not close enough, need to be confirmed manually
+            let judgement = match game.name_guess.as_ref() {
+                Some(n) => {
+                    let similarity = hit
+                        .compare_title(&n.title)
+                        .min(hit.compare_artist(&n.artist));
+
+                    if similarity >= tolerance {
+                        NameJudgement::Correct
+                    } else if similarity >= tolerance - NAME_AMBIGUITY_RANGE {
+                        NameJudgement::Ambiguous
+                    } else {
+                        NameJudgement::Wrong
+                    }
+                }
+                None => NameJudgement::Wrong,
+            };
+
+            game.name_guess = Some(NameGuess {
+                judgement: Some(judgement),
+                ..game.name_guess.clone().unwrap_or(NameGuess {
+                    title: "".into(),
+                    artist: "".into(),
+                    judgement: None,
+                })
+            });
+        }
+
+        game.remembered_hits
+            .push(game.hits_remaining.front().cloned().unwrap());
+
+        game.state = GameState::Confirming;
+        game.hit = game.hits_remaining.front().cloned();
+        if game.mode == GameMode::Local {
+            let creator_pos = game.players.iter().position(|p| p.creator).unwrap();
+            game.players.get_mut(creator_pos).unwrap().state = PlayerState::Confirming;
+        } else {
+            game.players
+                .get_mut((turn_player_pos + 1) % len)
+                .unwrap()
+                .state = PlayerState::Confirming;
+        }
+    }
+
+    /// the guess of the turn player got confirmed or rejected, the next player takes their turn
+    fn complete_turn(&self, game: &mut Game, confirm: bool) {
+        let turn_player_pos = game.players.iter().position(|p| p.turn_player).unwrap();
+
+        // named hits were already judged, unless the names were ambiguous
+        let confirm = match game.name_guess.as_ref().and_then(|n| n.judgement) {
+            Some(NameJudgement::Correct) => true,
+            Some(NameJudgement::Wrong) => false,
+            _ => confirm,
+        };
+
+        if confirm {
+            game.players.get_mut(turn_player_pos).unwrap().tokens += 1;
+        }
+
+        let hit = game.hits_remaining.pop_front().unwrap();
+
+        self.record(GameHistoryEntry {
+            hit_id: Some(hit.id),
+            confirmed: Some(confirm),
+            ..GameHistoryEntry::new(
+                &game.id,
+                GameHistoryAction::Confirm,
+                game.players.get(turn_player_pos),
+            )
+        });
+
+        self.next_turn(game);
+    }
+
+    /// passes the turn on to the next player
+    fn next_turn(&self, game: &mut Game) {
+        let turn_player_pos = game.players.iter().position(|p| p.turn_player).unwrap();
+
+        for p in game.players.iter_mut() {
+            p.guess = None;
+            p.state = PlayerState::Waiting;
+        }
+
+        game.state = GameState::Guessing;
+        game.last_scored = None;
+        game.name_guess = None;
+        game.turns += 1;
+        game.players.get_mut(turn_player_pos).unwrap().turn_player = false;
+
+        let len = game.players.len();
+        let next_pos = (turn_player_pos + 1) % len;
+
+        if next_pos == 0 {
+            game.rounds_played += 1;
+        }
+
+        if let Some(p) = game.players.get_mut(next_pos) {
+            p.turn_player = true;
+            p.state = PlayerState::Guessing;
+        }
+
+        self.refill_hits(game);
+        self.enqueue_availability_check(game.hits_remaining.front().cloned());
+        self.reset_deadline(game);
+    }
+
+    /// shuffles the hits which weren't won by anyone back into an empty deck
+    fn refill_hits(&self, game: &mut Game) {
+        // when playing until the deck is empty, played hits won't come back
+        if game.hits_remaining.is_empty() && game.victory_condition != VictoryCondition::Deck {
+            let mut rng = rng();
+            game.hits_remaining = game
+                .remembered_hits
+                .iter()
+                .filter(|h| !game.players.iter().any(|p| p.hits.contains(h)))
+                .cloned()
+                .collect::<VecDeque<_>>();
+            game.hits_remaining.make_contiguous().shuffle(&mut rng);
+            game.remembered_hits = game
+                .players
+                .iter()
+                .flat_map(|p| &p.hits)
+                .cloned()
+                .collect::<Vec<_>>();
+        }
+    }
+
     pub fn new(hit_service: ServiceHandle<HitService>) -> Self {
         Self {
             hit_service,
             data: Mutex::new(GameServiceData {
                 games: HashMap::new(),
+                redeemed_audio_tokens: HashMap::new(),
             }),
+            persistence_sender: None,
+            persisted_decks: Mutex::new(HashMap::new()),
+            history_sender: None,
+            event_queue: None,
         }
     }
 
+    pub fn set_persistence_sender(&mut self, persistence_sender: UnboundedSender<GameUpdate>) {
+        self.persistence_sender = Some(persistence_sender);
+    }
+
+    /// the deck of the game gets persisted again along with its next change, e.g. after persisting it failed
+    pub fn forget_deck(&self, game_id: &str) {
+        self.persisted_decks.lock().unwrap().remove(game_id);
+    }
+
+    pub fn set_history_sender(&mut self, history_sender: UnboundedSender<GameHistoryEntry>) {
+        self.history_sender = Some(history_sender);
+    }
+
+    pub fn set_event_queue(&mut self, event_queue: GameEventQueue) {
+        self.event_queue = Some(event_queue);
+    }
+
+    /// re-insert a game that was loaded from the database
+    pub fn restore(&self, mut game: Game) {
+        for p in game.players.iter_mut() {
+            p.slots = self.get_slots(&p.hits);
+        }
+
+        if let Some(last_scored) = game.last_scored.as_mut() {
+            last_scored.slots = self.get_slots(&last_scored.hits);
+        }
+
+        self.persisted_decks
+            .lock()
+            .unwrap()
+            .insert(game.id.clone(), GameDeck::from(&game));
+
+        self.data
+            .lock()
+            .unwrap()
+            .games
+            .insert(game.id.clone(), game);
+    }
+
     pub fn add(&self, creator: &User, mode: GameMode) -> Game {
         let mut data = self.data.lock().unwrap();
         let mut player: Player = creator.into();
@@ -62,7 +440,7 @@ impl GameService {
 
         let hs = self.hit_service.lock();
 
-        let game = Game {
+        let mut game = Game {
             id: id.clone(),
             players: vec![player],
             state: GameState::Open,
@@ -75,12 +453,36 @@ impl GameService {
             mode,
             remembered_hits: vec![],
             last_scored: None,
+            victory_condition: VictoryCondition::Goal,
+            rounds: 10,
+            time_limit: 30,
+            tie_breakers: vec![],
+            turns: 0,
+            rounds_played: 0,
+            started_at: None,
+            name_hits: false,
+            name_tolerance: 80,
+            name_guess: None,
+            teams: false,
+            guess_timeout: 0,
+            intercept_timeout: 0,
+            confirm_timeout: 0,
+            deadline: None,
+            spectators: vec![],
+            banned: vec![],
         };
 
         drop(hs);
 
         data.games.insert(id.clone(), game.clone());
 
+        self.record(GameHistoryEntry::new(
+            &game.id,
+            GameHistoryAction::Join,
+            game.players.first(),
+        ));
+        self.persist(&mut game);
+
         game
     }
 
@@ -95,11 +497,17 @@ impl GameService {
                 g.mode == GameMode::Public
                     || (user.is_some()
                         && (g.mode == GameMode::Private
-                            && g.players.iter().any(|p| p.id == user.as_ref().unwrap().id)
+                            && (g
+                                .players
+                                .iter()
+                                .any(|p| p.contains(user.as_ref().unwrap().id))
+                                || g.spectators
+                                    .iter()
+                                    .any(|s| s.id == user.as_ref().unwrap().id))
                             || (g.mode == GameMode::Local
                                 && g.players
                                     .iter()
-                                    .any(|p| p.id == user.as_ref().unwrap().id && p.creator))))
+                                    .any(|p| p.is_creator(user.as_ref().unwrap().id)))))
             })
             .collect::<_>()
     }
@@ -116,15 +524,34 @@ impl GameService {
                     || (user.is_some()
                         && g.players
                             .iter()
-                            .any(|p| p.id == user.as_ref().unwrap().id && p.creator))
+                            .any(|p| p.is_creator(user.as_ref().unwrap().id)))
             })
     }
 
+    /// the packs selected by games which didn't start yet,
+    /// running games only draw from hits which were downloaded when they started
+    pub fn open_packs(&self) -> HashSet<Uuid> {
+        self.data
+            .lock()
+            .unwrap()
+            .games
+            .values()
+            .filter(|g| g.state == GameState::Open)
+            .flat_map(|g| g.packs.iter().copied())
+            .collect::<HashSet<_>>()
+    }
+
+    /// wether a game with that id is currently known, regardless of its visibility
+    pub fn exists(&self, id: &str) -> bool {
+        self.data.lock().unwrap().games.contains_key(id)
+    }
+
     pub fn join(
         &self,
         game_id: &str,
         user: &User,
         player: Option<&str>,
+        team: Option<Uuid>,
     ) -> Result<Player, JoinGameError> {
         let mut data = self.data.lock().unwrap();
 
@@ -134,11 +561,22 @@ impl GameService {
                     message: "the game is already running".into(),
                     http_status_code: 403,
                 })
-            } else if player.is_none() && game.players.iter().any(|p| p.id == user.id) {
+            } else if player.is_none() && game.players.iter().any(|p| p.contains(user.id)) {
                 Err(JoinGameError {
                     message: "user is already part of this game".into(),
                     http_status_code: 409,
                 })
+            } else if player.is_none() && game.banned.iter().any(|b| b.id == user.id) {
+                Err(JoinGameError {
+                    message: "you are banned from this game".into(),
+                    http_status_code: 403,
+                })
+            } else if player.is_none() && game.spectators.iter().any(|s| s.id == user.id) {
+                Err(JoinGameError {
+                    message: "user is spectating this game and needs to stop spectating first"
+                        .into(),
+                    http_status_code: 409,
+                })
             } else if player.is_some() && game.mode != GameMode::Local {
                 Err(JoinGameError {
                     message: "virtual players can only be added to local games".into(),
@@ -146,31 +584,77 @@ impl GameService {
                 })
             } else if player.is_some()
                 && game.mode == GameMode::Local
-                && !game
-                    .players
-                    .iter()
-                    .find(|p| p.id == user.id)
-                    .map(|p| p.creator)
-                    .unwrap_or(false)
+                && !game.players.iter().any(|p| p.is_creator(user.id))
             {
                 Err(JoinGameError {
                     message: "only the creator can add virtual players to a game".into(),
                     http_status_code: 409,
                 })
+            } else if team.is_some() && !game.teams {
+                Err(JoinGameError {
+                    message: "this game isn't played in teams".into(),
+                    http_status_code: 409,
+                })
+            } else if team.is_some() && !game.players.iter().any(|p| Some(p.id) == team) {
+                Err(JoinGameError {
+                    message: "a team with this id isn't part of this game".into(),
+                    http_status_code: 409,
+                })
+            } else if player.is_none()
+                && game.players.iter().any(|p| Some(p.id) == team && p.creator)
+            {
+                // only the creator can decide who plays alongside them
+                Err(JoinGameError {
+                    message: "the team of the creator can't be joined".into(),
+                    http_status_code: 403,
+                })
             } else {
-                let plr: Player;
-
-                if let Some(player) = player {
-                    plr = Player {
+                let member = if let Some(player) = player {
+                    TeamMember {
                         id: Uuid::new_v4(),
                         name: player.into(),
+                        r#virtual: true,
+                        creator: false,
+                    }
+                } else {
+                    user.into()
+                };
+
+                let plr = if let Some(team) = team {
+                    let plr = game.players.iter_mut().find(|p| p.id == team).unwrap();
+
+                    plr.r#virtual = plr.r#virtual && member.r#virtual;
+                    plr.members.push(member);
+                    plr.clone()
+                } else if game.teams {
+                    let plr = Player {
+                        id: Uuid::new_v4(),
+                        name: member.name.clone(),
+                        r#virtual: member.r#virtual,
+                        members: vec![member],
                         ..Default::default()
                     };
+
+                    game.players.push(plr.clone());
+                    plr
                 } else {
-                    plr = user.into();
-                }
+                    let plr = Player {
+                        id: member.id,
+                        name: member.name,
+                        r#virtual: member.r#virtual,
+                        ..Default::default()
+                    };
+
+                    game.players.push(plr.clone());
+                    plr
+                };
 
-                game.players.push(plr.clone());
+                self.record(GameHistoryEntry::new(
+                    &game.id,
+                    GameHistoryAction::Join,
+                    Some(&plr),
+                ));
+                self.persist(game);
 
                 Ok(plr)
             }
@@ -182,37 +666,251 @@ impl GameService {
         }
     }
 
+    pub fn spectate(&self, game_id: &str, user: &User) -> Result<Spectator, JoinGameError> {
+        let mut data = self.data.lock().unwrap();
+
+        if let Some(game) = data.games.get_mut(game_id) {
+            if game.mode == GameMode::Local {
+                Err(JoinGameError {
+                    message: "local games can't be spectated".into(),
+                    http_status_code: 409,
+                })
+            } else if game.players.iter().any(|p| p.contains(user.id)) {
+                Err(JoinGameError {
+                    message: "user is already part of this game".into(),
+                    http_status_code: 409,
+                })
+            } else if game.spectators.iter().any(|s| s.id == user.id) {
+                Err(JoinGameError {
+                    message: "user is already spectating this game".into(),
+                    http_status_code: 409,
+                })
+            } else if game.banned.iter().any(|b| b.id == user.id) {
+                Err(JoinGameError {
+                    message: "you are banned from this game".into(),
+                    http_status_code: 403,
+                })
+            } else {
+                let spectator: Spectator = user.into();
+
+                game.spectators.push(spectator.clone());
+
+                self.persist(game);
+
+                Ok(spectator)
+            }
+        } else {
+            Err(JoinGameError {
+                message: "game not found".into(),
+                http_status_code: 404,
+            })
+        }
+    }
+
+    pub fn stop_spectating(
+        &self,
+        game_id: &str,
+        user: &User,
+        spectator_id: Option<Uuid>,
+    ) -> Result<Spectator, LeaveGameError> {
+        self.stop_spectating_locked(self.data.lock().unwrap(), game_id, user, spectator_id)
+    }
+
+    fn stop_spectating_locked(
+        &self,
+        mut data: MutexGuard<'_, GameServiceData>,
+        game_id: &str,
+        user: &User,
+        spectator_id: Option<Uuid>,
+    ) -> Result<Spectator, LeaveGameError> {
+        if let Some(game) = data.games.get_mut(game_id) {
+            let id = spectator_id.unwrap_or(user.id);
+
+            if spectator_id.is_some() && !game.players.iter().any(|p| p.is_creator(user.id)) {
+                Err(LeaveGameError {
+                    message: "only the creator can kick spectators from a game".into(),
+                    http_status_code: 409,
+                })
+            } else if let Some(pos) = game.spectators.iter().position(|s| s.id == id) {
+                let spectator = game.spectators.remove(pos);
+
+                self.persist(game);
+
+                Ok(spectator)
+            } else {
+                Err(LeaveGameError {
+                    message: "user is not spectating this game".into(),
+                    http_status_code: 409,
+                })
+            }
+        } else {
+            Err(LeaveGameError {
+                message: "game not found".into(),
+                http_status_code: 404,
+            })
+        }
+    }
+
+    /// removes a player, team member or spectator on behalf of the creator.
+    /// Returns the kicked player, or the team the kicked member was part of,
+    /// or nothing if a spectator got kicked
+    pub fn kick(
+        &self,
+        game_id: &str,
+        user: &User,
+        id: Uuid,
+        ban: bool,
+    ) -> Result<Option<Player>, KickPlayerError> {
+        let mut data = self.data.lock().unwrap();
+
+        if let Some(game) = data.games.get_mut(game_id) {
+            let creator = game.players.iter().find(|p| p.is_creator(user.id)).cloned();
+            let spectator = game.spectators.iter().find(|s| s.id == id).cloned();
+            let player = game
+                .players
+                .iter()
+                .find(|p| p.id == id || p.contains(id))
+                .cloned();
+
+            if creator.is_none() {
+                return Err(KickPlayerError {
+                    message: "only the creator can kick players from a game".into(),
+                    http_status_code: 403,
+                });
+            } else if id == user.id || creator.as_ref().is_some_and(|c| c.id == id) {
+                return Err(KickPlayerError {
+                    message: "the creator can't kick themselves".into(),
+                    http_status_code: 409,
+                });
+            } else if spectator.is_none() && player.is_none() {
+                return Err(KickPlayerError {
+                    message: "a player with this id isn't part of this game".into(),
+                    http_status_code: 409,
+                });
+            }
+
+            if ban {
+                let mut users = vec![];
+
+                if let Some(s) = spectator.as_ref() {
+                    users.push(BannedUser {
+                        id: s.id,
+                        name: s.name.clone(),
+                    });
+                }
+
+                if let Some(p) = player.as_ref() {
+                    if p.members.is_empty() && !p.r#virtual {
+                        users.push(BannedUser {
+                            id: p.id,
+                            name: p.name.clone(),
+                        });
+                    }
+
+                    // kicking a team bans all of its members, virtual players can't come back anyway
+                    for m in p
+                        .members
+                        .iter()
+                        .filter(|m| !m.r#virtual && (p.id == id || m.id == id))
+                    {
+                        users.push(BannedUser {
+                            id: m.id,
+                            name: m.name.clone(),
+                        });
+                    }
+                }
+
+                for u in users.into_iter() {
+                    if !game.banned.iter().any(|b| b.id == u.id) {
+                        game.banned.push(u);
+                    }
+                }
+            }
+
+            // keep holding the lock so that the game can't change between banning and kicking
+            if spectator.is_some() {
+                self.stop_spectating_locked(data, game_id, user, Some(id))
+                    .map(|_| None)
+            } else {
+                self.leave_locked(data, game_id, user, Some(id)).map(Some)
+            }
+            .map_err(|e| KickPlayerError {
+                message: e.message,
+                http_status_code: e.http_status_code,
+            })
+        } else {
+            Err(KickPlayerError {
+                message: "game not found".into(),
+                http_status_code: 404,
+            })
+        }
+    }
+
+    pub fn unban(
+        &self,
+        game_id: &str,
+        user: &User,
+        id: Uuid,
+    ) -> Result<BannedUser, KickPlayerError> {
+        let mut data = self.data.lock().unwrap();
+
+        if let Some(game) = data.games.get_mut(game_id) {
+            if !game.players.iter().any(|p| p.is_creator(user.id)) {
+                Err(KickPlayerError {
+                    message: "only the creator can unban users".into(),
+                    http_status_code: 403,
+                })
+            } else if let Some(pos) = game.banned.iter().position(|b| b.id == id) {
+                let user = game.banned.remove(pos);
+
+                self.persist(game);
+
+                Ok(user)
+            } else {
+                Err(KickPlayerError {
+                    message: "a user with this id isn't banned from this game".into(),
+                    http_status_code: 409,
+                })
+            }
+        } else {
+            Err(KickPlayerError {
+                message: "game not found".into(),
+                http_status_code: 404,
+            })
+        }
+    }
+
     pub fn leave(
         &self,
         game_id: &str,
         user: &User,
         player_id: Option<Uuid>,
     ) -> Result<Player, LeaveGameError> {
-        let mut data = self.data.lock().unwrap();
+        self.leave_locked(self.data.lock().unwrap(), game_id, user, player_id)
+    }
 
+    fn leave_locked(
+        &self,
+        mut data: MutexGuard<'_, GameServiceData>,
+        game_id: &str,
+        user: &User,
+        player_id: Option<Uuid>,
+    ) -> Result<Player, LeaveGameError> {
         if let Some(game) = data.games.get_mut(game_id) {
-            if !game.players.iter().any(|p| p.id == user.id) {
+            if !game.players.iter().any(|p| p.contains(user.id)) {
                 Err(LeaveGameError {
                     message: "user is not part of this game".into(),
                     http_status_code: 409,
                 })
-            } else if player_id.is_some()
-                && !game
-                    .players
-                    .iter()
-                    .find(|p| p.id == user.id)
-                    .map(|p| p.creator)
-                    .unwrap_or(false)
-            {
+            } else if player_id.is_some() && !game.players.iter().any(|p| p.is_creator(user.id)) {
                 Err(LeaveGameError {
                     message: "only the creator can kick other players from a game".into(),
                     http_status_code: 409,
                 })
             } else if player_id.is_some()
-                && !game
-                    .players
-                    .iter()
-                    .any(|p| p.id == *player_id.as_ref().unwrap())
+                && !game.players.iter().any(|p| {
+                    p.id == *player_id.as_ref().unwrap() || p.contains(*player_id.as_ref().unwrap())
+                })
             {
                 Err(LeaveGameError {
                     message: "a player with this id isn't part of this game".into(),
@@ -220,18 +918,68 @@ impl GameService {
                 })
             } else {
                 let id = player_id.unwrap_or(user.id);
-                let pos = game.players.iter().position(|p| p.id == id).unwrap();
+                let pos = game
+                    .players
+                    .iter()
+                    .position(|p| p.id == id || p.contains(id))
+                    .unwrap();
+
+                // members can leave their team without affecting the game
+                if game.players.get(pos).unwrap().members.len() > 1
+                    && game.players.get(pos).unwrap().id != id
+                {
+                    let plr = game.players.get_mut(pos).unwrap();
+
+                    plr.members.retain(|m| m.id != id);
+                    plr.r#virtual = plr.members.iter().all(|m| m.r#virtual);
+
+                    // another member of the creator's team takes over if the creator left
+                    if plr.creator
+                        && !plr.members.iter().any(|m| m.creator)
+                        && let Some(member) = plr.members.iter_mut().find(|m| !m.r#virtual)
+                    {
+                        member.creator = true;
+                    }
+
+                    // virtual members can't act as the creator
+                    if plr.creator && plr.r#virtual {
+                        self.hand_over_creator(game, pos);
+                    }
+
+                    let plr = game.players.get(pos).unwrap().clone();
+
+                    self.record(GameHistoryEntry::new(
+                        &game.id,
+                        GameHistoryAction::Leave,
+                        Some(&plr),
+                    ));
+
+                    if game.players.iter().filter(|p| !p.r#virtual).count() == 0 {
+                        data.games.remove(game_id);
+                        self.persist_removal(game_id);
+                    } else {
+                        self.persist(game);
+                    }
+
+                    return Ok(plr);
+                }
+
                 let creator = game.players.get(pos).unwrap().creator;
                 let turn_player = game.players.get(pos).unwrap().turn_player;
 
                 let idx = (pos + 1) % game.players.len();
 
                 if creator {
-                    game.players.get_mut(idx).unwrap().creator = true;
+                    self.hand_over_creator(game, pos);
                 }
 
                 if turn_player {
                     game.players.get_mut(idx).unwrap().turn_player = true;
+
+                    // the round is over when the last player leaves during their turn
+                    if idx == 0 {
+                        game.rounds_played += 1;
+                    }
                 }
 
                 if game.state != GameState::Open {
@@ -245,16 +993,28 @@ impl GameService {
                     }
 
                     game.state = GameState::Guessing;
+                    game.name_guess = None;
                     game.hits_remaining.pop_front();
+                    // an empty deck ends the game when playing until the deck is empty
+                    self.refill_hits(game);
+                    self.reset_deadline(game);
                 }
 
                 let plr = game.players.remove(pos);
 
+                self.record(GameHistoryEntry::new(
+                    &game.id,
+                    GameHistoryAction::Leave,
+                    Some(&plr),
+                ));
+
                 if game.players.iter().filter(|p| !p.r#virtual).count() == 0 {
                     data.games.remove(game_id);
+                    self.persist_removal(game_id);
                 } else if game.players.len() == 1 && game.state != GameState::Open {
-                    drop(data);
-                    let _ = self.stop(game_id, None);
+                    self.stop_locked(game);
+                } else {
+                    self.persist(game);
                 }
 
                 Ok(plr)
@@ -281,13 +1041,7 @@ impl GameService {
                     http_status_code: 409,
                     message: "the game is already running".into(),
                 })
-            } else if !game
-                .players
-                .iter()
-                .find(|p| p.id == user.id)
-                .map(|p| p.creator)
-                .unwrap_or(false)
-            {
+            } else if !game.players.iter().any(|p| p.is_creator(user.id)) {
                 Err(StartGameError {
                     http_status_code: 403,
                     message: "only the creator can start a game".into(),
@@ -331,6 +1085,9 @@ impl GameService {
                 game.remembered_hits = remembered_hits;
 
                 game.state = GameState::Guessing;
+                game.turns = 0;
+                game.rounds_played = 0;
+                game.started_at = Some(OffsetDateTime::now_utc());
                 game.players.shuffle(&mut rng);
                 game.players.get_mut(0).unwrap().state = PlayerState::Guessing;
                 game.players.get_mut(0).unwrap().turn_player = true;
@@ -342,10 +1099,19 @@ impl GameService {
                     player.hits.push(hit.clone());
                     game.remembered_hits.push(hit);
                     player.tokens = game.start_tokens;
+                    player.wrong_guesses = 0;
                     player.slots = self.get_slots(&player.hits);
                 }
 
                 self.enqueue_availability_check(game.hits_remaining.front().cloned());
+                self.reset_deadline(game);
+
+                self.record(GameHistoryEntry::new(
+                    &game.id,
+                    GameHistoryAction::Start,
+                    None,
+                ));
+                self.persist(game);
 
                 Ok(game.clone())
             }
@@ -362,12 +1128,7 @@ impl GameService {
 
         if let Some(game) = data.games.get_mut(game_id) {
             if let Some(u) = user
-                && !game
-                    .players
-                    .iter()
-                    .find(|p| p.id == u.id)
-                    .map(|p| p.creator)
-                    .unwrap_or(false)
+                && !game.players.iter().any(|p| p.is_creator(u.id))
             {
                 return Err(StopGameError {
                     http_status_code: 403,
@@ -382,17 +1143,7 @@ impl GameService {
                 });
             }
 
-            game.state = GameState::Open;
-            game.last_scored = None;
-            game.hits_remaining.clear();
-
-            for p in game.players.iter_mut() {
-                p.state = PlayerState::Waiting;
-                p.tokens = 0;
-                p.hits.clear();
-                p.turn_player = false;
-                p.guess = None;
-            }
+            self.stop_locked(game);
 
             Ok(game.clone())
         } else {
@@ -403,15 +1154,66 @@ impl GameService {
         }
     }
 
-    pub fn get_hit(&self, game_id: &str, hit_id: Option<Uuid>) -> Result<Hit, HitError> {
+    /// stops a running game, the caller needs to hold the lock on the games
+    fn stop_locked(&self, game: &mut Game) {
+        game.state = GameState::Open;
+        game.last_scored = None;
+        game.hits_remaining.clear();
+        game.turns = 0;
+        game.rounds_played = 0;
+        game.started_at = None;
+        game.name_guess = None;
+        game.deadline = None;
+
+        for p in game.players.iter_mut() {
+            p.state = PlayerState::Waiting;
+            p.tokens = 0;
+            p.wrong_guesses = 0;
+            p.hits.clear();
+            p.turn_player = false;
+            p.guess = None;
+        }
+
+        self.record(GameHistoryEntry::new(
+            &game.id,
+            GameHistoryAction::Stop,
+            None,
+        ));
+        self.persist(game);
+    }
+
+    /// a hit within a game and its audio file in the format of the given profile.
+    /// The current hit can only be fetched by members of the game and without a token once it got revealed
+    pub fn get_hit(
+        &self,
+        game_id: &str,
+        hit_id: Option<Uuid>,
+        user: Option<&User>,
+        profile: &TranscodingProfile,
+    ) -> Result<(Hit, PathBuf), HitError> {
         let mut data = self.data.lock().unwrap();
 
         if let Some(game) = data.games.get_mut(game_id) {
-            if game.state == GameState::Open {
+            let hit = if game.state == GameState::Open {
                 Err(HitError {
                     message: "game currently isn't running".into(),
                     http_status_code: 409,
                 })
+            } else if hit_id.is_none() && user.is_none() {
+                Err(HitError {
+                    message: "the current hit can only be fetched when authenticated".into(),
+                    http_status_code: 401,
+                })
+            } else if hit_id.is_none() && !user.is_some_and(|u| can_listen(game, u)) {
+                Err(HitError {
+                    message: "only players can listen to the hit before it got revealed".into(),
+                    http_status_code: 403,
+                })
+            } else if hit_id.is_none() && game.state != GameState::Confirming {
+                Err(HitError {
+                    message: "the current hit can only be fetched with an audio token until it got revealed".into(),
+                    http_status_code: 403,
+                })
             } else if let Some(hit_id) = hit_id {
                 game.players
                     .iter()
@@ -427,7 +1229,10 @@ impl GameService {
                     message: "no hit found".into(),
                     http_status_code: 500,
                 })
-            }
+            }?;
+            let file = snippet_file(game_id, &hit, profile);
+
+            Ok((hit, file))
         } else {
             Err(HitError {
                 message: "game not found".into(),
@@ -436,6 +1241,112 @@ impl GameService {
         }
     }
 
+    /// issue a token to play the current hit of a game.
+    /// The token is only valid for the user it got issued to and until the round ends
+    pub fn issue_audio_token(
+        &self,
+        game_id: &str,
+        user: &User,
+    ) -> Result<(String, OffsetDateTime), HitError> {
+        let data = self.data.lock().unwrap();
+
+        let Some(game) = data.games.get(game_id) else {
+            return Err(HitError {
+                message: "game not found".into(),
+                http_status_code: 404,
+            });
+        };
+
+        if game.state == GameState::Open {
+            return Err(HitError {
+                message: "game currently isn't running".into(),
+                http_status_code: 409,
+            });
+        }
+
+        if !can_listen(game, user) {
+            return Err(HitError {
+                message: "only players can listen to the hit before it got revealed".into(),
+                http_status_code: 403,
+            });
+        }
+
+        let hit = game.hits_remaining.front().ok_or(HitError {
+            message: "no hit found".into(),
+            http_status_code: 500,
+        })?;
+        let expires_at = OffsetDateTime::now_utc() + AUDIO_TOKEN_LIFETIME;
+        let claims = format!(
+            "{}.{}",
+            Uuid::new_v4().simple(),
+            expires_at.unix_timestamp()
+        );
+        let signature = audio::sign(&audio_token_data(game_id, hit, user, &claims));
+
+        Ok((format!("{}.{}", claims, signature), expires_at))
+    }
+
+    /// redeem a token issued by issue_audio_token() for the current hit, its audio file and the jitter to pad it with.
+    /// Every token can only be redeemed once
+    pub fn redeem_audio_token(
+        &self,
+        game_id: &str,
+        token: &str,
+        user: &User,
+        profile: &TranscodingProfile,
+    ) -> Result<(Hit, PathBuf, usize), HitError> {
+        let invalid = || HitError {
+            message: "the audio token is invalid or expired".into(),
+            http_status_code: 403,
+        };
+        let mut data = self.data.lock().unwrap();
+        let now = OffsetDateTime::now_utc();
+
+        // expired tokens get rejected anyway, so there's no need to remember them any longer
+        data.redeemed_audio_tokens.retain(|_, e| *e > now);
+
+        let (claims, signature) = token.rsplit_once('.').ok_or_else(invalid)?;
+        let (nonce, expires_at) = claims.split_once('.').ok_or_else(invalid)?;
+        let expires_at = expires_at
+            .parse::<i64>()
+            .ok()
+            .and_then(|e| OffsetDateTime::from_unix_timestamp(e).ok())
+            .filter(|e| *e > now)
+            .ok_or_else(invalid)?;
+
+        let Some(game) = data.games.get(game_id) else {
+            return Err(HitError {
+                message: "game not found".into(),
+                http_status_code: 404,
+            });
+        };
+
+        // tokens of previous rounds were signed for a different hit
+        let hit = game
+            .hits_remaining
+            .front()
+            .filter(|_| game.state != GameState::Open && can_listen(game, user))
+            .filter(|h| audio::verify(&audio_token_data(game_id, h, user, claims), signature))
+            .cloned()
+            .ok_or_else(invalid)?;
+
+        if data
+            .redeemed_audio_tokens
+            .insert(nonce.into(), expires_at)
+            .is_some()
+        {
+            return Err(HitError {
+                message: "the audio token was already used".into(),
+                http_status_code: 403,
+            });
+        }
+
+        let jitter = random::<u32>() as usize;
+        let file = snippet_file(game_id, &hit, profile);
+
+        Ok((hit, file, jitter))
+    }
+
     pub fn get_slots(&self, hits: &[Hit]) -> Vec<Slot> {
         let mut slots = vec![];
         let years = sorted(hits.iter().map(|h| h.year).collect::<HashSet<_>>()).collect::<Vec<_>>();
@@ -471,13 +1382,14 @@ impl GameService {
         &self,
         game_id: &str,
         user: &User,
-        slot_id: Option<u8>,
+        slot: &SlotPayload,
         player_id: Option<Uuid>,
     ) -> Result<Game, GuessSlotError> {
         let mut data = self.data.lock().unwrap();
+        let slot_id = slot.id;
 
         if let Some(game) = data.games.get_mut(game_id) {
-            if !game.players.iter().any(|p| p.id == user.id) {
+            if !game.players.iter().any(|p| p.contains(user.id)) {
                 return Err(GuessSlotError {
                     message: "user is not part of this game".into(),
                     http_status_code: 409,
@@ -490,12 +1402,7 @@ impl GameService {
                 });
             } else if player_id.is_some()
                 && game.mode == GameMode::Local
-                && !game
-                    .players
-                    .iter()
-                    .find(|p| p.id == user.id)
-                    .map(|p| p.creator)
-                    .unwrap_or(false)
+                && !game.players.iter().any(|p| p.is_creator(user.id))
             {
                 return Err(GuessSlotError {
                     message: "only the creator can submit guesses for virtual players".into(),
@@ -505,7 +1412,11 @@ impl GameService {
 
             let player_id = player_id.unwrap_or(user.id);
             let turn_player_pos = game.players.iter().position(|p| p.turn_player).unwrap();
-            let pos = game.players.iter().position(|p| p.id == player_id).unwrap();
+            let pos = game
+                .players
+                .iter()
+                .position(|p| p.contains(player_id))
+                .unwrap();
 
             if game.players.get(pos).unwrap().state != PlayerState::Guessing
                 && game.players.get(pos).unwrap().state != PlayerState::Intercepting
@@ -557,6 +1468,33 @@ impl GameService {
                     message: "this player needs to send a guess".into(),
                     http_status_code: 409,
                 });
+            } else if (slot.title.is_some() || slot.artist.is_some()) && !game.name_hits {
+                return Err(GuessSlotError {
+                    message: "hits can't be named in this game".into(),
+                    http_status_code: 409,
+                });
+            } else if (slot.title.is_some() || slot.artist.is_some())
+                && (game.state != GameState::Guessing || pos != turn_player_pos)
+            {
+                return Err(GuessSlotError {
+                    message: "only the turn player can name the hit while guessing".into(),
+                    http_status_code: 409,
+                });
+            } else if slot.title.is_some() != slot.artist.is_some() {
+                return Err(GuessSlotError {
+                    message: "title and artist need to be named together".into(),
+                    http_status_code: 409,
+                });
+            }
+
+            let state = game.state;
+
+            if let Some((title, artist)) = slot.title.as_ref().zip(slot.artist.as_ref()) {
+                game.name_guess = Some(NameGuess {
+                    title: title.clone(),
+                    artist: artist.clone(),
+                    judgement: None,
+                });
             }
 
             if let Some(slot) = slot_id {
@@ -586,71 +1524,34 @@ impl GameService {
                     game.players.get_mut(pos).unwrap().tokens -= 1;
                 }
 
+                self.record(GameHistoryEntry {
+                    slot: game.players.get(pos).unwrap().guess.clone(),
+                    ..GameHistoryEntry::new(
+                        &game.id,
+                        if pos == turn_player_pos {
+                            GameHistoryAction::Guess
+                        } else {
+                            GameHistoryAction::Intercept
+                        },
+                        game.players.get(pos),
+                    )
+                });
+
                 if !game
                     .players
                     .iter()
                     .any(|p| p.state == PlayerState::Intercepting)
                 {
-                    let len = game.players.len();
-
-                    let winners = game
-                        .players
-                        .iter()
-                        .enumerate()
-                        .filter(|(_, p)| {
-                            p.guess
-                                .as_ref()
-                                .map(|s| {
-                                    (s.from_year == 0
-                                        && game.hits_remaining.front().unwrap().year <= s.to_year)
-                                        || (s.to_year == 0
-                                            && game.hits_remaining.front().unwrap().year
-                                                >= s.from_year)
-                                        || (s.from_year
-                                            <= game.hits_remaining.front().unwrap().year
-                                            && game.hits_remaining.front().unwrap().year
-                                                <= s.to_year)
-                                })
-                                .unwrap_or(false)
-                        })
-                        .collect::<Vec<_>>();
-
-                    if let Some(i) = winners.iter().find(|(_, p)| p.turn_player).map(|(i, _)| *i) {
-                        let player = game.players.get_mut(i).unwrap();
-
-                        player
-                            .hits
-                            .push(game.hits_remaining.front().cloned().unwrap());
-                        player.slots = self.get_slots(&player.hits);
-                        game.last_scored = Some(player.clone());
-                    } else if winners.len() == 1 {
-                        let i = winners.first().map(|(i, _)| *i).unwrap();
-                        let player = game.players.get_mut(i).unwrap();
-
-                        player
-                            .hits
-                            .push(game.hits_remaining.front().cloned().unwrap());
-                        player.slots = self.get_slots(&player.hits);
-                        game.last_scored = Some(player.clone());
-                    }
-
-                    game.remembered_hits
-                        .push(game.hits_remaining.front().cloned().unwrap());
-
-                    game.state = GameState::Confirming;
-                    game.hit = game.hits_remaining.front().cloned();
-                    if game.mode == GameMode::Local {
-                        let creator_pos = game.players.iter().position(|p| p.creator).unwrap();
-                        game.players.get_mut(creator_pos).unwrap().state = PlayerState::Confirming;
-                    } else {
-                        game.players
-                            .get_mut((turn_player_pos + 1) % len)
-                            .unwrap()
-                            .state = PlayerState::Confirming;
-                    }
+                    self.reveal(game);
                 }
             }
 
+            if game.state != state {
+                self.reset_deadline(game);
+            }
+
+            self.persist(game);
+
             Ok(game.clone())
         } else {
             Err(GuessSlotError {
@@ -669,20 +1570,23 @@ impl GameService {
         let mut data = self.data.lock().unwrap();
 
         if let Some(game) = data.games.get_mut(game_id) {
-            if !game.players.iter().any(|p| p.id == user.id) {
+            if !game.players.iter().any(|p| p.contains(user.id)) {
                 return Err(ConfirmSlotError {
                     message: "user is not part of this game".into(),
                     http_status_code: 409,
                 });
-            } else if self.get_winner(game).is_some() {
+            } else if !self.get_winners(game).is_empty() {
                 return Err(ConfirmSlotError {
                     message: "the game already has a winner".into(),
                     http_status_code: 409,
                 });
             }
 
-            let turn_player_pos = game.players.iter().position(|p| p.turn_player).unwrap();
-            let pos = game.players.iter().position(|p| p.id == user.id).unwrap();
+            let pos = game
+                .players
+                .iter()
+                .position(|p| p.contains(user.id))
+                .unwrap();
 
             if game.players.get(pos).unwrap().state != PlayerState::Confirming {
                 return Err(ConfirmSlotError {
@@ -691,46 +1595,9 @@ impl GameService {
                 });
             }
 
-            if confirm {
-                game.players.get_mut(turn_player_pos).unwrap().tokens += 1;
-            }
-
-            game.hits_remaining.pop_front().unwrap();
-
-            for p in game.players.iter_mut() {
-                p.guess = None;
-                p.state = PlayerState::Waiting;
-            }
-
-            game.state = GameState::Guessing;
-            game.last_scored = None;
-            game.players.get_mut(turn_player_pos).unwrap().turn_player = false;
-
-            let len = game.players.len();
-
-            if let Some(p) = game.players.get_mut((turn_player_pos + 1) % len) {
-                p.turn_player = true;
-                p.state = PlayerState::Guessing;
-            }
-
-            if game.hits_remaining.is_empty() {
-                let mut rng = rng();
-                game.hits_remaining = game
-                    .remembered_hits
-                    .iter()
-                    .filter(|h| !game.players.iter().any(|p| p.hits.contains(h)))
-                    .cloned()
-                    .collect::<VecDeque<_>>();
-                game.hits_remaining.make_contiguous().shuffle(&mut rng);
-                game.remembered_hits = game
-                    .players
-                    .iter()
-                    .flat_map(|p| &p.hits)
-                    .cloned()
-                    .collect::<Vec<_>>();
-            }
+            self.complete_turn(game, confirm);
 
-            self.enqueue_availability_check(game.hits_remaining.front().cloned());
+            self.persist(game);
 
             Ok(game.clone())
         } else {
@@ -750,7 +1617,7 @@ impl GameService {
         let mut data = self.data.lock().unwrap();
 
         if let Some(game) = data.games.get_mut(game_id) {
-            if !game.players.iter().any(|p| p.id == user.id) {
+            if !game.players.iter().any(|p| p.contains(user.id)) {
                 return Err(SkipHitError {
                     message: "user is not part of this game".into(),
                     http_status_code: 409,
@@ -762,12 +1629,7 @@ impl GameService {
                 });
             } else if player_id.is_some()
                 && game.mode == GameMode::Local
-                && !game
-                    .players
-                    .iter()
-                    .find(|p| p.id == user.id)
-                    .map(|p| p.creator)
-                    .unwrap_or(false)
+                && !game.players.iter().any(|p| p.is_creator(user.id))
             {
                 return Err(SkipHitError {
                     message: "only the creator can skip hits of virtual players".into(),
@@ -776,7 +1638,11 @@ impl GameService {
             }
 
             let player_id = player_id.unwrap_or(user.id);
-            let pos = game.players.iter().position(|p| p.id == player_id).unwrap();
+            let pos = game
+                .players
+                .iter()
+                .position(|p| p.contains(player_id))
+                .unwrap();
 
             if game.players.get(pos).unwrap().state != PlayerState::Guessing {
                 return Err(SkipHitError {
@@ -794,26 +1660,20 @@ impl GameService {
 
             let hit = game.hits_remaining.pop_front().unwrap();
 
+            self.record(GameHistoryEntry {
+                hit_id: Some(hit.id),
+                ..GameHistoryEntry::new(&game.id, GameHistoryAction::Skip, game.players.get(pos))
+            });
+
             game.remembered_hits.push(hit.clone());
 
-            if game.hits_remaining.is_empty() {
-                let mut rng = rng();
-                game.hits_remaining = game
-                    .remembered_hits
-                    .iter()
-                    .filter(|h| !game.players.iter().any(|p| p.hits.contains(h)))
-                    .cloned()
-                    .collect::<VecDeque<_>>();
-                game.hits_remaining.make_contiguous().shuffle(&mut rng);
-                game.remembered_hits = game
-                    .players
-                    .iter()
-                    .flat_map(|p| &p.hits)
-                    .cloned()
-                    .collect::<Vec<_>>();
-            }
+            self.refill_hits(game);
 
             self.enqueue_availability_check(game.hits_remaining.front().cloned());
+            // the guessing player gets the full time for the new hit
+            self.reset_deadline(game);
+
+            self.persist(game);
 
             Ok((game.clone(), hit))
         } else {
@@ -833,7 +1693,7 @@ impl GameService {
         let mut data = self.data.lock().unwrap();
 
         if let Some(game) = data.games.get_mut(game_id) {
-            if !game.players.iter().any(|p| p.id == user.id) {
+            if !game.players.iter().any(|p| p.contains(user.id)) {
                 return Err(ClaimHitError {
                     message: "user is not part of this game".into(),
                     http_status_code: 409,
@@ -845,12 +1705,7 @@ impl GameService {
                 });
             } else if player_id.is_some()
                 && game.mode == GameMode::Local
-                && !game
-                    .players
-                    .iter()
-                    .find(|p| p.id == user.id)
-                    .map(|p| p.creator)
-                    .unwrap_or(false)
+                && !game.players.iter().any(|p| p.is_creator(user.id))
             {
                 return Err(ClaimHitError {
                     message: "only the creator can claim hits of virtual players".into(),
@@ -859,7 +1714,11 @@ impl GameService {
             }
 
             let player_id = player_id.unwrap_or(user.id);
-            let pos = game.players.iter().position(|p| p.id == player_id).unwrap();
+            let pos = game
+                .players
+                .iter()
+                .position(|p| p.contains(player_id))
+                .unwrap();
 
             if game.players.get(pos).unwrap().tokens < 3 {
                 return Err(ClaimHitError {
@@ -868,24 +1727,19 @@ impl GameService {
                 });
             }
 
+            if game.hits_remaining.len() == 1 && game.victory_condition == VictoryCondition::Deck {
+                return Err(ClaimHitError {
+                    message: "there are no hits left to claim".into(),
+                    http_status_code: 409,
+                });
+            }
+
             game.players.get_mut(pos).unwrap().tokens -= 3;
 
             if game.hits_remaining.len() == 1 {
+                // the hit which is currently being guessed stays on top of the refilled deck
                 let current_hit = game.hits_remaining.pop_front().unwrap();
-                let mut rng = rng();
-                game.hits_remaining = game
-                    .remembered_hits
-                    .iter()
-                    .filter(|h| !game.players.iter().any(|p| p.hits.contains(h)))
-                    .cloned()
-                    .collect::<VecDeque<_>>();
-                game.hits_remaining.make_contiguous().shuffle(&mut rng);
-                game.remembered_hits = game
-                    .players
-                    .iter()
-                    .flat_map(|p| &p.hits)
-                    .cloned()
-                    .collect::<Vec<_>>();
+                self.refill_hits(game);
                 game.hits_remaining.push_front(current_hit);
             }
 
@@ -896,8 +1750,15 @@ impl GameService {
             player.slots = self.get_slots(&player.hits);
             game.remembered_hits.push(hit.clone());
 
+            self.record(GameHistoryEntry {
+                hit_id: Some(hit.id),
+                ..GameHistoryEntry::new(&game.id, GameHistoryAction::Claim, game.players.get(pos))
+            });
+
             self.enqueue_availability_check(Some(hit.clone()));
 
+            self.persist(game);
+
             Ok((game.clone(), hit))
         } else {
             Err(ClaimHitError {
@@ -916,7 +1777,7 @@ impl GameService {
         let mut data = self.data.lock().unwrap();
 
         if let Some(game) = data.games.get_mut(game_id) {
-            if !game.players.iter().any(|p| p.id == user.id) {
+            if !game.players.iter().any(|p| p.contains(user.id)) {
                 return Err(UpdateGameError {
                     message: "user is not part of this game".into(),
                     http_status_code: 409,
@@ -926,15 +1787,57 @@ impl GameService {
                     message: "game is currently running".into(),
                     http_status_code: 403,
                 });
-            } else if !game
-                .players
+            } else if !game.players.iter().any(|p| p.is_creator(user.id)) {
+                return Err(UpdateGameError {
+                    message: "user must be creator of the game".into(),
+                    http_status_code: 409,
+                });
+            }
+
+            let victory_condition = settings.victory_condition.unwrap_or(game.victory_condition);
+            let tie_breakers = settings
+                .tie_breakers
+                .clone()
+                .unwrap_or(game.tie_breakers.clone());
+
+            if victory_condition == VictoryCondition::Goal && settings.goal == Some(0) {
+                return Err(UpdateGameError {
+                    message: "the goal needs to be at least one hit".into(),
+                    http_status_code: 409,
+                });
+            } else if victory_condition == VictoryCondition::Rounds
+                && settings.rounds.unwrap_or(game.rounds) == 0
+            {
+                return Err(UpdateGameError {
+                    message: "at least one round needs to be played".into(),
+                    http_status_code: 409,
+                });
+            } else if victory_condition == VictoryCondition::Time
+                && settings.time_limit.unwrap_or(game.time_limit) == 0
+            {
+                return Err(UpdateGameError {
+                    message: "the time limit needs to be at least one minute".into(),
+                    http_status_code: 409,
+                });
+            } else if settings.name_tolerance.is_some_and(|t| t == 0 || t > 100) {
+                return Err(UpdateGameError {
+                    message: "the name tolerance needs to be between 1 and 100 percent".into(),
+                    http_status_code: 409,
+                });
+            } else if settings.teams == Some(false)
+                && game.players.iter().any(|p| p.members.len() > 1)
+            {
+                return Err(UpdateGameError {
+                    message: "teams with more than one member need to be dissolved first".into(),
+                    http_status_code: 409,
+                });
+            } else if tie_breakers
                 .iter()
-                .find(|p| p.id == user.id)
-                .unwrap()
-                .creator
+                .enumerate()
+                .any(|(i, t)| tie_breakers.iter().skip(i + 1).any(|o| o == t))
             {
                 return Err(UpdateGameError {
-                    message: "user must be creator of the game".into(),
+                    message: "every tie breaker can only be used once".into(),
                     http_status_code: 409,
                 });
             }
@@ -957,6 +1860,39 @@ impl GameService {
             game.start_tokens = settings.start_tokens.unwrap_or(game.start_tokens);
             game.goal = settings.goal.unwrap_or(game.goal);
             game.hit_duration = settings.hit_duration.unwrap_or(game.hit_duration);
+            game.victory_condition = victory_condition;
+            game.rounds = settings.rounds.unwrap_or(game.rounds);
+            game.time_limit = settings.time_limit.unwrap_or(game.time_limit);
+            game.tie_breakers = tie_breakers;
+            // every player becomes a team of their own and the other way round
+            if settings.teams == Some(true) && !game.teams {
+                for p in game.players.iter_mut() {
+                    p.members = vec![TeamMember {
+                        id: p.id,
+                        name: p.name.clone(),
+                        r#virtual: p.r#virtual,
+                        creator: p.creator,
+                    }];
+                    p.id = Uuid::new_v4();
+                }
+            } else if settings.teams == Some(false) && game.teams {
+                for p in game.players.iter_mut() {
+                    if let Some(m) = p.members.pop() {
+                        p.id = m.id;
+                        p.name = m.name;
+                        p.r#virtual = m.r#virtual;
+                    }
+                }
+            }
+
+            game.teams = settings.teams.unwrap_or(game.teams);
+            game.name_hits = settings.name_hits.unwrap_or(game.name_hits);
+            game.name_tolerance = settings.name_tolerance.unwrap_or(game.name_tolerance);
+            game.guess_timeout = settings.guess_timeout.unwrap_or(game.guess_timeout);
+            game.intercept_timeout = settings.intercept_timeout.unwrap_or(game.intercept_timeout);
+            game.confirm_timeout = settings.confirm_timeout.unwrap_or(game.confirm_timeout);
+
+            self.persist(game);
 
             Ok(game.clone())
         } else {
@@ -967,10 +1903,233 @@ impl GameService {
         }
     }
 
-    pub fn get_winner(&self, game: &Game) -> Option<Player> {
-        game.players
+    /// moves all games on whose current phase timed out.
+    /// Returns the affected games together with the players who didn't react in time
+    pub fn expire(&self) -> Vec<(Game, Vec<Player>)> {
+        let mut data = self.data.lock().unwrap();
+        let now = OffsetDateTime::now_utc();
+        let mut expired = vec![];
+
+        for game in data.games.values_mut() {
+            if game.state == GameState::Open || game.deadline.is_none_or(|d| d > now) {
+                continue;
+            }
+
+            let players = game
+                .players
+                .iter()
+                .filter(|p| p.state != PlayerState::Waiting)
+                .cloned()
+                .collect::<Vec<_>>();
+
+            for p in players.iter() {
+                self.record(GameHistoryEntry {
+                    hit_id: game.hits_remaining.front().map(|h| h.id),
+                    ..GameHistoryEntry::new(&game.id, GameHistoryAction::Timeout, Some(p))
+                });
+            }
+
+            match game.state {
+                // the turn player loses the hit without paying a token
+                GameState::Guessing => {
+                    if let Some(hit) = game.hits_remaining.pop_front() {
+                        game.remembered_hits.push(hit);
+                        self.next_turn(game);
+                    } else {
+                        // the deck ran out, the game gets finished instead
+                        game.deadline = None;
+                    }
+                }
+                GameState::Intercepting => {
+                    for p in game.players.iter_mut() {
+                        if p.state == PlayerState::Intercepting {
+                            p.state = PlayerState::Waiting;
+                        }
+                    }
+
+                    self.reveal(game);
+                    self.reset_deadline(game);
+                }
+                GameState::Confirming => self.complete_turn(game, false),
+                GameState::Open => {}
+            }
+
+            self.persist(game);
+
+            expired.push((game.clone(), players));
+        }
+
+        expired
+    }
+
+    /// the players who won the game, more than one if there is a tie
+    /// or none at all if the game isn't over yet
+    pub fn get_winners(&self, game: &Game) -> Vec<Player> {
+        // all conditions except the goal only end a game after a turn is complete
+        let over = match game.victory_condition {
+            VictoryCondition::Goal => game
+                .players
+                .iter()
+                .any(|p| p.hits.len() >= game.goal as usize),
+            VictoryCondition::Rounds => {
+                game.state == GameState::Guessing && game.rounds_played >= game.rounds as u16
+            }
+            VictoryCondition::Time => {
+                game.state == GameState::Guessing
+                    && game
+                        .started_at
+                        .map(|t| {
+                            t + Duration::minutes(game.time_limit as i64)
+                                <= OffsetDateTime::now_utc()
+                        })
+                        .unwrap_or(false)
+            }
+            VictoryCondition::Deck => {
+                game.state == GameState::Guessing && game.hits_remaining.is_empty()
+            }
+        };
+
+        if game.state == GameState::Open || !over {
+            return vec![];
+        }
+
+        let mut winners = game
+            .players
             .iter()
-            .find(|p| p.hits.len() >= game.goal as usize)
-            .cloned()
+            .filter(|p| {
+                game.victory_condition != VictoryCondition::Goal
+                    || p.hits.len() >= game.goal as usize
+            })
+            .collect::<Vec<_>>();
+
+        let most_hits = winners.iter().map(|p| p.hits.len()).max().unwrap_or(0);
+        winners.retain(|p| p.hits.len() == most_hits);
+
+        for tie_breaker in game.tie_breakers.iter() {
+            match tie_breaker {
+                TieBreaker::Tokens => {
+                    let most_tokens = winners.iter().map(|p| p.tokens).max().unwrap_or(0);
+                    winners.retain(|p| p.tokens == most_tokens);
+                }
+                TieBreaker::WrongGuesses => {
+                    let fewest_wrong_guesses =
+                        winners.iter().map(|p| p.wrong_guesses).min().unwrap_or(0);
+                    winners.retain(|p| p.wrong_guesses == fewest_wrong_guesses);
+                }
+            }
+        }
+
+        winners.into_iter().cloned().collect::<Vec<_>>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hitster_core::{AudioSource, Permissions};
+
+    fn user(name: &str) -> User {
+        User {
+            id: Uuid::new_v4(),
+            name: name.into(),
+            password: "".into(),
+            tokens: vec![],
+            r#virtual: true,
+            permissions: Permissions::empty(),
+        }
+    }
+
+    fn hit() -> Hit {
+        Hit {
+            artist: "Artist".into(),
+            title: "Title".into(),
+            belongs_to: "".into(),
+            year: 1985,
+            packs: vec![],
+            playback_offset: 0,
+            playback_length: None,
+            snippets: vec![],
+            id: Uuid::new_v4(),
+            yt_id: "dQw4w9WgXcQ".into(),
+            source: AudioSource::YouTube,
+            location: "".into(),
+            last_modified: OffsetDateTime::now_utc(),
+            downloaded: true,
+        }
+    }
+
+    /// a service with a running game of the given user which is about to play the given hit
+    fn running_game(creator: &User, hit: &Hit) -> (GameService, String) {
+        let service = GameService::new(ServiceHandle::new(HitService::default()));
+        let mut game = service.add(creator, GameMode::Local);
+
+        game.state = GameState::Guessing;
+        game.hits_remaining = VecDeque::from([hit.clone()]);
+
+        let id = game.id.clone();
+
+        service.restore(game);
+
+        (service, id)
+    }
+
+    fn redeem(service: &GameService, game_id: &str, token: &str, user: &User) -> Result<Hit, u16> {
+        service
+            .redeem_audio_token(game_id, token, user, &TranscodingProfile::default())
+            .map(|(hit, _, _)| hit)
+            .map_err(|e| e.http_status_code)
+    }
+
+    #[test]
+    fn audio_tokens_can_be_redeemed_once() {
+        let player = user("player");
+        let hit = hit();
+        let (service, game_id) = running_game(&player, &hit);
+        let (token, _) = service.issue_audio_token(&game_id, &player).ok().unwrap();
+
+        assert_eq!(
+            redeem(&service, &game_id, &token, &player).map(|h| h.id),
+            Ok(hit.id)
+        );
+        assert_eq!(
+            redeem(&service, &game_id, &token, &player).map(|h| h.id),
+            Err(403)
+        );
+    }
+
+    #[test]
+    fn tampered_audio_tokens_get_rejected() {
+        let player = user("player");
+        let (service, game_id) = running_game(&player, &hit());
+        let (token, _) = service.issue_audio_token(&game_id, &player).ok().unwrap();
+        let (claims, signature) = token.rsplit_once('.').unwrap();
+        let (nonce, expires_at) = claims.split_once('.').unwrap();
+        let extended = format!(
+            "{}.{}.{}",
+            nonce,
+            expires_at.parse::<i64>().unwrap() + 3600,
+            signature
+        );
+
+        assert_eq!(
+            redeem(&service, &game_id, &extended, &player).map(|h| h.id),
+            Err(403)
+        );
+        assert_eq!(
+            redeem(
+                &service,
+                &game_id,
+                &format!("{}.{}", claims, "A".repeat(43)),
+                &player
+            )
+            .map(|h| h.id),
+            Err(403)
+        );
+        assert_eq!(
+            redeem(&service, &game_id, &token, &user("spectator")).map(|h| h.id),
+            Err(403)
+        );
+        // rejected tokens weren't used up
+        assert!(redeem(&service, &game_id, &token, &player).is_ok());
     }
 }
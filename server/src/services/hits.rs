@@ -1,14 +1,16 @@
 use crate::{
     hits::{
-        DownloadHitData, HitSearchFilter, HitSearchPack, HitSearchQuery, SortBy, SortDirection,
+        HitSearchFilter, HitSearchPack, HitSearchQuery, SortBy, SortDirection, queue_hit_download,
     },
     responses::PaginatedResponse,
 };
 use hitster_core::{Hit, HitId, HitsterData, Pack};
-use rocket::tokio::sync::broadcast::Sender;
+use rocket::tokio::sync::{Notify, broadcast::Sender};
+use rocket_db_pools::sqlx::SqlitePool;
 use std::{
     cmp::Ordering,
     collections::{HashMap, HashSet},
+    sync::Arc,
 };
 use uuid::Uuid;
 
@@ -21,10 +23,12 @@ fn includes_filter(filters: &Option<Vec<HitSearchFilter>>, filter: HitSearchFilt
 
 pub struct HitService {
     hitster_data: HitsterData,
-    downloading: bool,
-    processing: bool,
-    dl_sender: Option<Sender<Hit>>,
-    process_sender: Option<Sender<DownloadHitData>>,
+    /// hits within the download queue, processed ones excluded
+    downloading: usize,
+    /// hits handed over for processing which aren't done yet
+    processing: usize,
+    /// the database the download queue lives in and how to wake it up
+    download_queue: Option<(SqlitePool, Arc<Notify>)>,
     availability_sender: Option<Sender<Hit>>,
 }
 
@@ -32,10 +36,9 @@ impl HitService {
     pub fn new(hitster_data: HitsterData) -> Self {
         Self {
             hitster_data,
-            downloading: false,
-            processing: false,
-            dl_sender: None,
-            process_sender: None,
+            downloading: 0,
+            processing: 0,
+            download_queue: None,
             availability_sender: None,
         }
     }
@@ -61,29 +64,18 @@ impl HitService {
     }
 
     pub fn downloading(&self) -> usize {
-        if self.downloading {
-            self.dl_sender.as_ref().map(|d| d.len() + 1).unwrap_or(0)
-        } else {
-            0
-        }
+        self.downloading
     }
 
-    pub fn set_downloading(&mut self, downloading: bool) {
+    pub fn set_downloading(&mut self, downloading: usize) {
         self.downloading = downloading
     }
 
     pub fn processing(&self) -> usize {
-        if self.processing {
-            self.process_sender
-                .as_ref()
-                .map(|p| p.len() + 1)
-                .unwrap_or(0)
-        } else {
-            0
-        }
+        self.processing
     }
 
-    pub fn set_processing(&mut self, processing: bool) {
+    pub fn set_processing(&mut self, processing: usize) {
         self.processing = processing
     }
 
@@ -103,13 +95,8 @@ impl HitService {
         self.hitster_data.get_hits_for_packs(packs)
     }
 
-    pub fn set_download_info(
-        &mut self,
-        dl_sender: Sender<Hit>,
-        process_sender: Sender<DownloadHitData>,
-    ) {
-        self.dl_sender = Some(dl_sender);
-        self.process_sender = Some(process_sender);
+    pub fn set_download_info(&mut self, db: SqlitePool, queue_notify: Arc<Notify>) {
+        self.download_queue = Some((db, queue_notify));
     }
 
     pub fn set_availability_sender(&mut self, availability_sender: Sender<Hit>) {
@@ -120,6 +107,10 @@ impl HitService {
         self.hitster_data.get_hit(hit_id)
     }
 
+    pub fn find_duplicates(&self, max_year_distance: u32) -> Vec<(&Hit, &Hit)> {
+        self.hitster_data.find_duplicates(max_year_distance)
+    }
+
     pub fn remove_hit(&mut self, hit: &HitId) -> bool {
         self.hitster_data.remove_hit(hit)
     }
@@ -224,8 +215,14 @@ impl HitService {
         }
     }
 
+    /// add the hit to the download queue
     pub fn download_hit(&self, hit: Hit) {
-        let _ = self.dl_sender.as_ref().unwrap().send(hit);
+        let (db, queue_notify) = self.download_queue.clone().unwrap();
+
+        rocket::tokio::spawn(async move {
+            queue_hit_download(&db, hit.id).await;
+            queue_notify.notify_one();
+        });
     }
 
     pub fn queue_availability_check(&self, hit: Hit) {
@@ -33,8 +33,9 @@ pub struct ServiceStoreData {
     user_service: Option<ServiceHandle<UserService>>,
 }
 
+#[derive(Clone)]
 pub struct ServiceStore {
-    data: Mutex<ServiceStoreData>,
+    data: Arc<Mutex<ServiceStoreData>>,
 }
 
 impl ServiceStore {
@@ -76,7 +77,7 @@ impl ServiceStore {
 impl Default for ServiceStore {
     fn default() -> Self {
         Self {
-            data: Mutex::new(ServiceStoreData::default()),
+            data: Arc::new(Mutex::new(ServiceStoreData::default())),
         }
     }
 }
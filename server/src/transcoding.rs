@@ -0,0 +1,448 @@
+use hitster_core::Hit;
+use rocket::{
+    Build, Rocket, fairing,
+    http::{Accept, ContentType, MediaType},
+};
+use serde::Deserialize;
+use std::{ops::Range, path::PathBuf, sync::OnceLock};
+
+static PROFILES: OnceLock<Vec<TranscodingProfile>> = OnceLock::new();
+
+#[derive(Deserialize, Clone, Copy, Eq, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum AudioCodec {
+    Mp3,
+    Aac,
+    Opus,
+}
+
+impl AudioCodec {
+    fn encoder(&self) -> &'static str {
+        match self {
+            Self::Mp3 => "libmp3lame",
+            Self::Aac => "aac",
+            Self::Opus => "libopus",
+        }
+    }
+
+    fn extension(&self) -> &'static str {
+        match self {
+            Self::Mp3 => "mp3",
+            Self::Aac => "m4a",
+            Self::Opus => "opus",
+        }
+    }
+
+    /// opus only supports a limited set of sample rates
+    fn sample_rate(&self) -> u32 {
+        match self {
+            Self::Mp3 | Self::Aac => 44100,
+            Self::Opus => 48000,
+        }
+    }
+
+    /// remove tags which could give away the song.
+    /// Newly processed files don't carry any, but files processed by older versions might
+    pub fn strip_metadata(&self, data: &mut Vec<u8>) {
+        if *self != Self::Mp3 {
+            return;
+        }
+
+        // id3v2 tags in front, their size doesn't include the header and footer
+        while data.len() >= 10 && data.starts_with(b"ID3") {
+            let size = data[6..10]
+                .iter()
+                .fold(0usize, |size, b| (size << 7) | (*b & 0x7f) as usize);
+            let footer = if data[5] & 0x10 != 0 { 10 } else { 0 };
+
+            data.drain(..(10 + size + footer).min(data.len()));
+        }
+
+        // id3v1 tags at the end
+        if data.len() >= 128 && data[data.len() - 128..].starts_with(b"TAG") {
+            data.truncate(data.len() - 128);
+        }
+    }
+
+    /// add the given amount of bytes which players skip, so that the file size doesn't give away the song
+    pub fn pad(&self, data: &mut Vec<u8>, amount: usize) {
+        if amount == 0 {
+            return;
+        }
+
+        match self {
+            // an empty id3v2.4 tag, consisting of padding only
+            Self::Mp3 => {
+                let amount = amount.min(0x0fffffff);
+                let mut tag = b"ID3\x04\x00\x00".to_vec();
+
+                tag.extend((0..4).rev().map(|i| ((amount >> (7 * i)) & 0x7f) as u8));
+                tag.resize(10 + amount, 0);
+                data.splice(..0, tag);
+            }
+            // a free space box
+            Self::Aac => {
+                let size = (amount + 8).min(u32::MAX as usize);
+
+                data.extend((size as u32).to_be_bytes());
+                data.extend(b"free");
+                data.resize(data.len() + size - 8, 0);
+            }
+            // zeros after the comments within the OpusTags header, which decoders ignore
+            Self::Opus => {
+                pad_opus_tags(data, amount);
+            }
+        }
+    }
+
+    pub fn content_type(&self) -> ContentType {
+        match self {
+            Self::Mp3 => ContentType::new("audio", "mpeg"),
+            Self::Aac => ContentType::new("audio", "mp4"),
+            Self::Opus => ContentType::new("audio", "ogg"),
+        }
+    }
+}
+
+/// checksum of an ogg page, calculated while the checksum field is set to 0
+fn ogg_crc(page: &[u8]) -> u32 {
+    page.iter().fold(0u32, |crc, b| {
+        (0..8).fold(crc ^ ((*b as u32) << 24), |crc, _| {
+            if crc & 0x8000_0000 != 0 {
+                (crc << 1) ^ 0x04c1_1db7
+            } else {
+                crc << 1
+            }
+        })
+    })
+}
+
+/// the ranges of all ogg pages within the data, or none if the data isn't made of ogg pages only
+fn ogg_pages(data: &[u8]) -> Option<Vec<Range<usize>>> {
+    let mut pages = vec![];
+    let mut pos = 0;
+
+    while pos < data.len() {
+        let header = data.get(pos..pos + 27)?;
+
+        if !header.starts_with(b"OggS") {
+            return None;
+        }
+
+        let segments = data.get(pos + 27..pos + 27 + header[26] as usize)?;
+        let end = pos + 27 + segments.len() + segments.iter().map(|s| *s as usize).sum::<usize>();
+
+        if end > data.len() {
+            return None;
+        }
+
+        pages.push(pos..end);
+        pos = end;
+    }
+
+    Some(pages)
+}
+
+/// the size of the ogg pages needed to store a packet of the given length
+fn ogg_packet_size(length: usize) -> usize {
+    let segments = length / 255 + 1;
+
+    length + segments + 27 * segments.div_ceil(255)
+}
+
+/// the ogg pages of a header packet, starting with the given sequence number
+fn ogg_header_pages(serial: &[u8], sequence: u32, packet: &[u8]) -> Vec<Vec<u8>> {
+    let mut lacing = vec![255u8; packet.len() / 255];
+    let mut body = packet;
+
+    lacing.push((packet.len() % 255) as u8);
+
+    lacing
+        .chunks(255)
+        .enumerate()
+        .map(|(i, segments)| {
+            let length = segments.iter().map(|s| *s as usize).sum::<usize>();
+            let mut page = b"OggS\x00".to_vec();
+
+            // continued packet, granule position 0 for headers
+            page.push(if i > 0 { 0x01 } else { 0x00 });
+            page.extend([0; 8]);
+            page.extend(serial);
+            page.extend((sequence + i as u32).to_le_bytes());
+            page.extend([0; 4]);
+            page.push(segments.len() as u8);
+            page.extend(segments);
+            page.extend(&body[..length]);
+            body = &body[length..];
+
+            let crc = ogg_crc(&page);
+            page[22..26].copy_from_slice(&crc.to_le_bytes());
+            page
+        })
+        .collect()
+}
+
+/// enlarge the OpusTags header packet of an ogg opus file by about the given amount of bytes.
+/// Zeros following the comments are padding which decoders ignore.
+/// The header has pages of its own, so only the sequence numbers of the following pages change.
+/// Files which aren't made up as expected stay untouched
+fn pad_opus_tags(data: &mut Vec<u8>, amount: usize) {
+    let Some(pages) = ogg_pages(data) else {
+        return;
+    };
+
+    // the OpusTags packet starts on the second page and ends together with the last of its pages
+    let mut packet = vec![];
+    let mut tag_pages = 0;
+
+    for page in pages.iter().skip(1) {
+        let segments = &data[page.start + 27..page.start + 27 + data[page.start + 26] as usize];
+
+        packet.extend(&data[page.start + 27 + segments.len()..page.end]);
+        tag_pages += 1;
+
+        if segments.last().is_some_and(|s| *s < 255) {
+            break;
+        }
+    }
+
+    if !packet.starts_with(b"OpusTags") || tag_pages == 0 || pages.len() < tag_pages + 1 {
+        return;
+    }
+
+    let target = pages[tag_pages].end - pages[1].start + amount;
+    let mut length = packet.len() + amount;
+
+    // the page headers take up some of the padding as well
+    while length > packet.len() && ogg_packet_size(length) > target {
+        length -= 1;
+    }
+
+    packet.resize(length, 0);
+
+    let serial = data[pages[1].start + 14..pages[1].start + 18].to_vec();
+    let sequence = u32::from_le_bytes(
+        data[pages[1].start + 18..pages[1].start + 22]
+            .try_into()
+            .unwrap(),
+    );
+    let new_pages = ogg_header_pages(&serial, sequence, &packet);
+    let shift = new_pages.len() as i64 - tag_pages as i64;
+    let mut tail = data.split_off(pages[tag_pages].end);
+
+    // the following pages get renumbered
+    if shift != 0 {
+        for page in pages[tag_pages + 1..].iter() {
+            let page =
+                &mut tail[page.start - pages[tag_pages].end..page.end - pages[tag_pages].end];
+            let sequence = u32::from_le_bytes(page[18..22].try_into().unwrap());
+
+            page[18..22].copy_from_slice(&((sequence as i64 + shift) as u32).to_le_bytes());
+            page[22..26].fill(0);
+
+            let crc = ogg_crc(page);
+            page[22..26].copy_from_slice(&crc.to_le_bytes());
+        }
+    }
+
+    data.truncate(pages[1].start);
+    data.extend(new_pages.into_iter().flatten());
+    data.append(&mut tail);
+}
+
+/// a format the processed hits get stored in.
+/// Profiles are configured as transcoding_profiles within the Rocket config
+#[derive(Deserialize, Clone, Debug)]
+pub struct TranscodingProfile {
+    pub name: String,
+    pub codec: AudioCodec,
+    #[serde(default = "default_bitrate")]
+    pub bitrate: String,
+    /// the integrated loudness target in LUFS, the streaming-video preset of ffmpeg-normalize will be used if not set
+    #[serde(default)]
+    pub loudness: Option<f64>,
+    /// the first profile keeps the plain file names
+    #[serde(skip)]
+    extension: String,
+}
+
+fn default_bitrate() -> String {
+    "128k".into()
+}
+
+impl Default for TranscodingProfile {
+    fn default() -> Self {
+        Self {
+            name: "mp3".into(),
+            codec: AudioCodec::Mp3,
+            bitrate: default_bitrate(),
+            loudness: None,
+            extension: AudioCodec::Mp3.extension().into(),
+        }
+    }
+}
+
+impl TranscodingProfile {
+    /// the file of the snippet starting at the given offset in this format
+    pub fn file(&self, hit: &Hit, offset: u16) -> PathBuf {
+        hit.snippet_file(offset, &self.extension)
+    }
+
+    /// the arguments to hand to ffmpeg-normalize to write a file in this format
+    pub fn ffmpeg_normalize_args(&self) -> Vec<String> {
+        let mut args = match self.loudness {
+            Some(loudness) => vec!["-t".into(), loudness.to_string()],
+            None => vec!["--preset".into(), "streaming-video".into()],
+        };
+
+        args.extend([
+            "-ar".into(),
+            self.codec.sample_rate().to_string(),
+            "-b:a".into(),
+            self.bitrate.clone(),
+            "-c:a".into(),
+            self.codec.encoder().into(),
+            "--extension".into(),
+            self.codec.extension().into(),
+        ]);
+
+        args
+    }
+
+    fn accepts(&self, media_type: &MediaType) -> bool {
+        let content_type = self.codec.content_type();
+
+        (media_type.top() == "*" || media_type.top() == content_type.top())
+            && (media_type.sub() == "*" || media_type.sub() == content_type.sub())
+    }
+}
+
+/// all configured profiles, the first one is the default one
+pub fn profiles() -> &'static [TranscodingProfile] {
+    PROFILES.get_or_init(|| vec![TranscodingProfile::default()])
+}
+
+/// the file extensions of all profiles, used to check if a hit got processed entirely
+pub fn extensions() -> Vec<&'static str> {
+    profiles().iter().map(|p| p.extension.as_str()).collect()
+}
+
+/// the profile which fits the accepted media types best, the default profile if none of them matches
+pub fn negotiate(accept: Option<&Accept>) -> &'static TranscodingProfile {
+    let profiles = profiles();
+
+    if let Some(accept) = accept {
+        let mut media_types = accept
+            .iter()
+            .filter(|m| m.weight_or(1.0) > 0.0)
+            .collect::<Vec<_>>();
+
+        // sorting is stable, media types with the same weight keep the client's order
+        media_types.sort_by(|a, b| b.weight_or(1.0).total_cmp(&a.weight_or(1.0)));
+
+        for media_type in media_types.into_iter() {
+            if let Some(profile) = profiles.iter().find(|p| p.accepts(media_type.media_type())) {
+                return profile;
+            }
+        }
+    }
+
+    &profiles[0]
+}
+
+pub async fn load_profiles(rocket: Rocket<Build>) -> fairing::Result {
+    let mut profiles = match rocket
+        .figment()
+        .extract_inner::<Vec<TranscodingProfile>>("transcoding_profiles")
+    {
+        Ok(profiles) => profiles,
+        Err(e) if e.missing() => vec![TranscodingProfile::default()],
+        Err(e) => {
+            rocket::error!("Invalid transcoding profiles: {}", e);
+            return Err(rocket);
+        }
+    };
+
+    if profiles.is_empty() {
+        rocket::error!("At least one transcoding profile is required");
+        return Err(rocket);
+    }
+
+    for i in 0..profiles.len() {
+        let name = profiles[i].name.clone();
+
+        // the name ends up within the file names
+        if name.is_empty()
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            rocket::error!(
+                "Transcoding profile names may only contain letters, digits, - and _, got \"{}\"",
+                name
+            );
+            return Err(rocket);
+        }
+
+        if profiles[..i].iter().any(|p| p.name == name) {
+            rocket::error!("Transcoding profile \"{}\" is configured twice", name);
+            return Err(rocket);
+        }
+
+        profiles[i].extension = if i == 0 {
+            profiles[i].codec.extension().into()
+        } else {
+            format!("{}.{}", name, profiles[i].codec.extension())
+        };
+    }
+
+    let _ = PROFILES.set(profiles);
+
+    Ok(rocket)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OPUS: &[u8] = include_bytes!("../../client/sfx/claim_hit.opus");
+
+    #[test]
+    fn opus_padding_keeps_valid_pages() {
+        for amount in [1, 100, 4096, 70000, 300000] {
+            let mut data = OPUS.to_vec();
+
+            AudioCodec::Opus.pad(&mut data, amount);
+
+            let pages = ogg_pages(&data).unwrap();
+
+            assert!(data.len() <= OPUS.len() + amount);
+            assert!(data.len() + 32 >= OPUS.len() + amount);
+            let body = pages[1].start + 27 + data[pages[1].start + 26] as usize;
+
+            assert!(data[body..].starts_with(b"OpusTags"));
+
+            for (i, page) in pages.iter().enumerate() {
+                let mut page = data[page.clone()].to_vec();
+                let crc = u32::from_le_bytes(page[22..26].try_into().unwrap());
+
+                page[22..26].fill(0);
+
+                assert_eq!(ogg_crc(&page), crc);
+                assert_eq!(
+                    u32::from_le_bytes(page[18..22].try_into().unwrap()),
+                    i as u32
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn opus_padding_ignores_other_data() {
+        let mut data = b"not an ogg file".to_vec();
+
+        AudioCodec::Opus.pad(&mut data, 100);
+
+        assert_eq!(data, b"not an ogg file");
+    }
+}
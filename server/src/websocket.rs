@@ -0,0 +1,130 @@
+use rocket::{
+    data::{IoHandler, IoStream},
+    futures::future::BoxFuture,
+    http::Status,
+    request::{self, FromRequest, Outcome, Request},
+    response::{self, Responder, Response},
+};
+use rocket_okapi::{
+    OpenApiError,
+    r#gen::OpenApiGenerator,
+    okapi::{
+        openapi3::{RefOr, Response as OpenApiResponse, Responses},
+        schemars::Map,
+    },
+    request::{OpenApiFromRequest, RequestHeaderInput},
+    response::OpenApiResponderInner,
+};
+use std::{io, pin::Pin};
+use tokio_tungstenite::tungstenite::{handshake::derive_accept_key, protocol::Role};
+
+pub use tokio_tungstenite::tungstenite::Message;
+
+/// an established websocket connection
+pub type WebSocketStream = tokio_tungstenite::WebSocketStream<IoStream>;
+
+/// request guard for requests which want to upgrade to a websocket connection
+pub struct WebSocket {
+    key: String,
+}
+
+impl WebSocket {
+    /// upgrade the connection and let the handler communicate with the client
+    pub fn channel<'r, F>(self, handler: F) -> Channel<'r>
+    where
+        F: FnOnce(WebSocketStream) -> BoxFuture<'r, io::Result<()>> + Send + 'r,
+    {
+        Channel {
+            key: self.key,
+            handler: Box::new(handler),
+        }
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for WebSocket {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        let headers = req.headers();
+        let upgrade = headers
+            .get("Upgrade")
+            .any(|u| u.eq_ignore_ascii_case("websocket"));
+        let version = headers.get_one("Sec-WebSocket-Version") == Some("13");
+
+        match headers.get_one("Sec-WebSocket-Key") {
+            Some(key) if upgrade && version => Outcome::Success(WebSocket { key: key.into() }),
+            _ => Outcome::Error((Status::UpgradeRequired, ())),
+        }
+    }
+}
+
+impl OpenApiFromRequest<'_> for WebSocket {
+    fn from_request_input(
+        _gen: &mut OpenApiGenerator,
+        _name: String,
+        _required: bool,
+    ) -> rocket_okapi::Result<RequestHeaderInput> {
+        Ok(RequestHeaderInput::None)
+    }
+}
+
+/// response which upgrades the connection to a websocket
+pub struct Channel<'r> {
+    key: String,
+    handler: Box<dyn FnOnce(WebSocketStream) -> BoxFuture<'r, io::Result<()>> + Send + 'r>,
+}
+
+impl<'r, 'o: 'r> Responder<'r, 'o> for Channel<'o> {
+    fn respond_to(self, _: &'r Request<'_>) -> response::Result<'o> {
+        Response::build()
+            .raw_header(
+                "Sec-WebSocket-Accept",
+                derive_accept_key(self.key.as_bytes()),
+            )
+            .upgrade("websocket", self)
+            .ok()
+    }
+}
+
+#[rocket::async_trait]
+impl IoHandler for Channel<'_> {
+    async fn io(self: Pin<Box<Self>>, io: IoStream) -> io::Result<()> {
+        let channel = Pin::into_inner(self);
+        let stream = WebSocketStream::from_raw_socket(io, Role::Server, None).await;
+
+        (channel.handler)(stream).await
+    }
+}
+
+impl OpenApiResponderInner for Channel<'_> {
+    fn responses(_generator: &mut OpenApiGenerator) -> Result<Responses, OpenApiError> {
+        let mut responses = Map::new();
+        responses.insert(
+            "101".to_string(),
+            RefOr::Object(OpenApiResponse {
+                description: "\
+                # [101 Switching Protocols](https://developer.mozilla.org/en-US/docs/Web/HTTP/Status/101)\n\
+                The connection got upgraded to a websocket.\
+                "
+                .to_string(),
+                ..Default::default()
+            }),
+        );
+        responses.insert(
+            "426".to_string(),
+            RefOr::Object(OpenApiResponse {
+                description: "\
+                # [426 Upgrade Required](https://developer.mozilla.org/en-US/docs/Web/HTTP/Status/426)\n\
+                The request didn't ask for a websocket connection.\
+                "
+                .to_string(),
+                ..Default::default()
+            }),
+        );
+        Ok(Responses {
+            responses,
+            ..Default::default()
+        })
+    }
+}